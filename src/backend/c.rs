@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+
+use crate::{
+    checks::typing::{substitute, Type},
+    ir::{BlockId, Function, Instruction, InstructionKind, Module, Terminator, Value},
+    lexer::cursor::Span,
+    parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+    token::Literal,
+};
+
+use super::{quote, BackendError, BackendErrorKind, Instance, Instances};
+
+/// Helpers for checked arithmetic, strings and printing, included at the top of every emitted
+/// file.
+const RUNTIME: &str = include_str!("runtime.h");
+
+/// Translates a program in SSA form into a single, self contained C99 source file. Generic
+/// functions are monomorphised, with a copy emitted for each set of type arguments that they are
+/// called with, and the top level statements become the body of `main`.
+///
+/// Each value becomes a local, and each block a label that is jumped to, so that side effects
+/// happen in the same order as they would in the interpreter.
+pub fn emit_c(module: &Module) -> Result<String, BackendError> {
+    let (main, functions) = module
+        .functions
+        .split_last()
+        .expect("lowering to add a function for the top level statements");
+    let mut instances = Instances::new(functions);
+
+    let main = FunctionEmitter::new(main, HashMap::new(), true).emit_body(&mut instances)?;
+
+    // Emitting a function may instantiate further functions, which are then emitted in turn
+    let mut definitions = Vec::new();
+    while let Some(instance) = instances.next_pending() {
+        definitions.push(emit_function(&mut instances, instance)?);
+    }
+
+    let mut output = String::from(RUNTIME);
+
+    if !definitions.is_empty() {
+        output.push('\n');
+    }
+    for (signature, _) in &definitions {
+        output.push_str(&format!("{signature};\n"));
+    }
+
+    for (signature, body) in &definitions {
+        output.push_str(&format!("\n{signature} {{\n{body}}}\n"));
+    }
+
+    output.push_str(&format!("\nint main(void) {{\n{main}}}\n"));
+
+    Ok(output)
+}
+
+/// Emits an instance of a function, returning its signature and body.
+fn emit_function(
+    instances: &mut Instances<Function>,
+    instance: Instance<Function>,
+) -> Result<(String, String), BackendError> {
+    let function = instance.function;
+    let emitter = FunctionEmitter::new(function, instance.bindings, false);
+
+    let return_type = emitter.c_type(&function.return_type, &function.span)?;
+    let parameters = function
+        .parameters
+        .iter()
+        .map(|(value, ty)| {
+            Ok(format!(
+                "{} {}",
+                emitter.c_type(ty, &function.span)?,
+                name(*value)
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let signature = format!(
+        "static {return_type} {}({})",
+        instance.name,
+        if parameters.is_empty() {
+            "void".to_string()
+        } else {
+            parameters.join(", ")
+        }
+    );
+
+    Ok((signature, emitter.emit_body(instances)?))
+}
+
+/// The C name of a value. Names are prefixed, so that they can't collide with C keywords,
+/// functions or the runtime.
+fn name(value: Value) -> String {
+    format!("v{}", value.0)
+}
+
+/// Emits the body of a single function instance.
+struct FunctionEmitter<'a> {
+    function: &'a Function,
+    /// The type that each of the function's type parameters is instantiated with.
+    bindings: HashMap<String, Type>,
+    /// The type of each parameter and instruction.
+    types: HashMap<Value, Type>,
+    /// The number of times that each value is used, so that unused results needn't be stored.
+    uses: HashMap<Value, usize>,
+    /// Whether this is the body of `main`, which returns an exit code rather than its value.
+    main: bool,
+    body: String,
+    /// Used to give each temporary a unique name.
+    temporaries: usize,
+    /// The number of levels that lines are indented by.
+    depth: usize,
+}
+impl<'a> FunctionEmitter<'a> {
+    fn new(function: &'a Function, bindings: HashMap<String, Type>, main: bool) -> Self {
+        let instructions = function.blocks.iter().flat_map(|block| &block.instructions);
+
+        let mut types = function
+            .parameters
+            .iter()
+            .cloned()
+            .collect::<HashMap<_, _>>();
+        let mut uses = HashMap::new();
+        for instruction in instructions.clone() {
+            types.insert(instruction.value, instruction.ty.clone());
+        }
+        // `main` returns an exit code, so doesn't use the value that it returns
+        let terminators = function
+            .blocks
+            .iter()
+            .filter_map(|block| block.terminator.as_ref())
+            .filter(|terminator| !(main && matches!(terminator, Terminator::Return(_))));
+        for value in instructions
+            .flat_map(|instruction| instruction.kind.operands())
+            .chain(terminators.flat_map(Terminator::operands))
+        {
+            *uses.entry(value).or_default() += 1;
+        }
+
+        Self {
+            function,
+            bindings,
+            types,
+            uses,
+            main,
+            body: String::new(),
+            temporaries: 0,
+            depth: 1,
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        self.body.push_str(&"    ".repeat(self.depth));
+        self.body.push_str(line);
+        self.body.push('\n');
+    }
+
+    fn is_used(&self, value: Value) -> bool {
+        self.uses.contains_key(&value)
+    }
+
+    /// Stores a value in a new temporary, returning its name.
+    fn temporary(&mut self, ty: &str, value: String) -> String {
+        let name = format!("t{}", self.temporaries);
+        self.temporaries += 1;
+
+        self.line(&format!("{ty} {name} = {value};"));
+
+        name
+    }
+
+    /// Resolves the concrete type of a value within this instance.
+    fn resolve(&self, ty: &Type) -> Type {
+        substitute(ty, &self.bindings)
+    }
+
+    fn c_type(&self, ty: &Type, span: &Span) -> Result<&'static str, BackendError> {
+        Ok(match self.resolve(ty) {
+            Type::Integer => "int64_t",
+            Type::Boolean => "bool",
+            Type::String => "lang_string",
+            Type::Unit => "lang_unit",
+            Type::Named { .. } => return Err(unsupported("structs and enums", span)),
+            Type::Parameter(_) | Type::Unknown => {
+                unreachable!("type checker and instantiation to resolve every type")
+            }
+        })
+    }
+
+    /// Emits each block in reverse postorder, so that every value is declared before it is used.
+    /// The entry is the only block that isn't jumped to, so is the only one without a label.
+    fn emit_body(mut self, instances: &mut Instances<Function>) -> Result<String, BackendError> {
+        // Phis are assigned by each of their predecessors, so have to be declared up front
+        for instruction in self
+            .function
+            .blocks
+            .iter()
+            .flat_map(|block| &block.instructions)
+        {
+            if matches!(instruction.kind, InstructionKind::Phi(_))
+                && self.is_used(instruction.value)
+            {
+                let ty = self.c_type(&instruction.ty, &instruction.span)?;
+                self.line(&format!("{ty} {};", name(instruction.value)));
+            }
+        }
+
+        for block in self.function.reverse_postorder() {
+            if block != Function::ENTRY {
+                // A label has to be followed by a statement, rather than a declaration
+                self.body.push_str(&format!("{block}:;\n"));
+            }
+
+            for instruction in &self.function.blocks[block.0].instructions {
+                self.emit_instruction(instances, instruction)?;
+            }
+
+            match self.function.blocks[block.0]
+                .terminator
+                .as_ref()
+                .expect("lowering to terminate every block")
+            {
+                Terminator::Jump(target) => self.emit_jump(block, *target),
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.line(&format!("if ({}) {{", name(*condition)));
+                    self.depth += 1;
+                    self.emit_jump(block, *then);
+                    self.depth -= 1;
+                    self.line("} else {");
+                    self.depth += 1;
+                    self.emit_jump(block, *otherwise);
+                    self.depth -= 1;
+                    self.line("}");
+                }
+                Terminator::Return(_) if self.main => self.line("return 0;"),
+                Terminator::Return(value) => self.line(&format!("return {};", name(*value))),
+            }
+        }
+
+        Ok(self.body)
+    }
+
+    /// Jumps from one block to another, first assigning each phi of the target the value that it
+    /// takes from this block. There are no loops, so a phi never takes the value of another phi
+    /// of the same block, and the order of the assignments doesn't matter.
+    fn emit_jump(&mut self, from: BlockId, to: BlockId) {
+        for instruction in &self.function.blocks[to.0].instructions {
+            let InstructionKind::Phi(incoming) = &instruction.kind else {
+                break;
+            };
+            if !self.is_used(instruction.value) {
+                continue;
+            }
+
+            let (_, value) = incoming
+                .iter()
+                .find(|(predecessor, _)| *predecessor == from)
+                .expect("each phi to take a value from every predecessor");
+            self.line(&format!("{} = {};", name(instruction.value), name(*value)));
+        }
+
+        self.line(&format!("goto {to};"));
+    }
+
+    /// Emits the statements required to execute an instruction. Results that are never used are
+    /// discarded rather than stored.
+    fn emit_instruction(
+        &mut self,
+        instances: &mut Instances<Function>,
+        instruction: &Instruction,
+    ) -> Result<(), BackendError> {
+        let span = &instruction.span;
+        let position = format!("\"{span}\"");
+
+        // A C expression for the result, which is [None] for unit values that don't need to be
+        // computed
+        let value = match &instruction.kind {
+            InstructionKind::Constant(literal) => Some(match literal {
+                // The most negative integer can't be written as a negated literal
+                Literal::Integer(integer) if *integer == isize::MIN => "INT64_MIN".to_string(),
+                Literal::Integer(integer) => format!("INT64_C({integer})"),
+                Literal::Boolean(boolean) => boolean.to_string(),
+                Literal::String(string) => {
+                    format!("(lang_string){{{}, {}}}", quote(string), string.len())
+                }
+            }),
+            InstructionKind::Unit => None,
+            InstructionKind::Binary {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                let (lhs, rhs) = (name(*lhs), name(*rhs));
+
+                let call = |helper: &str| format!("{helper}({lhs}, {rhs}, {position})");
+                Some(match (operation, self.resolve(&instruction.ty)) {
+                    (BinaryOperationKind::Add, Type::String) => call("lang_concat"),
+                    (_, ty) if ty != Type::Integer => {
+                        return Err(unsupported("operators on values other than integers", span))
+                    }
+                    (BinaryOperationKind::Add, _) => call("lang_add"),
+                    (BinaryOperationKind::Sub, _) => call("lang_sub"),
+                    (BinaryOperationKind::Mult, _) => call("lang_mul"),
+                    (BinaryOperationKind::Div, _) => call("lang_div"),
+                    (BinaryOperationKind::Mod, _) => call("lang_rem"),
+                    (BinaryOperationKind::Exp, _) => call("lang_pow"),
+                    (BinaryOperationKind::Shl, _) => call("lang_shl"),
+                    (BinaryOperationKind::Shr, _) => call("lang_shr"),
+                    // These can't fail, so don't need a helper
+                    (BinaryOperationKind::And, _) => format!("({lhs} & {rhs})"),
+                    (BinaryOperationKind::Or, _) => format!("({lhs} | {rhs})"),
+                    (BinaryOperationKind::Xor, _) => format!("({lhs} ^ {rhs})"),
+                })
+            }
+            InstructionKind::Unary { operation, rhs } => {
+                if self.resolve(&self.types[rhs]) != Type::Integer {
+                    return Err(unsupported("operators on values other than integers", span));
+                }
+                let rhs = name(*rhs);
+
+                Some(match operation {
+                    UnaryOperationKind::Negative => format!("lang_neg({rhs}, {position})"),
+                    UnaryOperationKind::Complement => format!("~{rhs}"),
+                })
+            }
+            InstructionKind::Call {
+                function,
+                type_arguments,
+                arguments,
+            } => {
+                if !instances.is_function(function) && function == "print" {
+                    let helper = match self.resolve(&self.types[&arguments[0]]) {
+                        Type::Integer => "lang_print_integer",
+                        Type::Boolean => "lang_print_boolean",
+                        Type::String => "lang_print_string",
+                        Type::Unit => "lang_print_unit",
+                        _ => return Err(unsupported("structs and enums", span)),
+                    };
+                    self.line(&format!("{helper}({});", name(arguments[0])));
+
+                    None
+                } else if instances.is_function(function) {
+                    let symbol = instances.instance(
+                        function,
+                        type_arguments.iter().map(|ty| self.resolve(ty)).collect(),
+                    );
+                    let arguments = arguments
+                        .iter()
+                        .map(|argument| name(*argument))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    Some(format!("{symbol}({arguments})"))
+                } else {
+                    // Host functions are only available when running on the virtual machine
+                    return Err(unsupported("host functions", span));
+                }
+            }
+            InstructionKind::Variant { .. } | InstructionKind::Struct { .. } => {
+                return Err(unsupported("structs and enums", span))
+            }
+            InstructionKind::Interpolate(parts) => {
+                let mut string = "(lang_string){\"\", 0}".to_string();
+
+                for part in parts {
+                    let value = match self.resolve(&self.types[part]) {
+                        Type::Integer => {
+                            format!("lang_integer_string({}, {position})", name(*part))
+                        }
+                        Type::Boolean => format!("lang_boolean_string({})", name(*part)),
+                        _ => name(*part),
+                    };
+
+                    string = self.temporary(
+                        "lang_string",
+                        format!("lang_concat({string}, {value}, {position})"),
+                    );
+                }
+
+                Some(string)
+            }
+            // Phis are assigned by the blocks that jump to them
+            InstructionKind::Phi(_) => return Ok(()),
+        };
+
+        let used = self.is_used(instruction.value);
+        match value {
+            Some(value) if used => {
+                let ty = self.c_type(&instruction.ty, span)?;
+                self.line(&format!("{ty} {} = {value};", name(instruction.value)));
+            }
+            Some(value) => self.line(&format!("(void){value};")),
+            None if used => self.line(&format!(
+                "lang_unit {} = LANG_UNIT;",
+                name(instruction.value)
+            )),
+            None => (),
+        }
+
+        Ok(())
+    }
+}
+
+fn unsupported(feature: &'static str, span: &Span) -> BackendError {
+    BackendError::new(BackendErrorKind::Unsupported(feature, "C"), span)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process::Command};
+
+    use super::*;
+    use crate::{backend::tests::assert_matches_interpreter, front_end, ir::lower};
+
+    #[test]
+    fn matches_interpreter() {
+        assert_matches_interpreter("c", |typed_ast, binary_path| {
+            let source_path = binary_path.with_extension("c");
+            fs::write(&source_path, emit_c(&lower(typed_ast)).unwrap()).unwrap();
+
+            let status = Command::new("cc")
+                .args(["-std=c99", "-Wall", "-o"])
+                .arg(binary_path)
+                .arg(&source_path)
+                .status()
+                .expect("a C compiler to be installed");
+            assert!(status.success());
+        });
+    }
+
+    #[test]
+    fn unsupported() {
+        let (_, typed_ast) =
+            front_end("struct Point { x: Integer }\nlet p = Point { x: 1 };").unwrap();
+
+        let error = emit_c(&lower(&typed_ast)).unwrap_err();
+        assert!(matches!(error.kind, BackendErrorKind::Unsupported(..)));
+        assert_eq!(error.span.to_string(), "2:9");
+    }
+}
@@ -0,0 +1,332 @@
+//! Backends that translate a type checked program into a format that can be executed without the
+//! interpreter.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    checks::typing::{Type, TypedAstNode, TypedFunction},
+    ir,
+    lexer::cursor::Span,
+};
+
+pub use self::{c::*, wasm::*, x86_64::*};
+
+mod c;
+mod wasm;
+mod x86_64;
+
+// Shared with the integration tests, which drive the golden tests from fixtures in the same way
+#[cfg(test)]
+#[path = "../../tests/common/programs.rs"]
+mod programs;
+#[cfg(test)]
+#[path = "../../tests/common/temp_dir.rs"]
+mod temp_dir;
+
+/// All of the reasons that a backend could fail to translate a program.
+#[derive(Debug, Error)]
+pub enum BackendErrorKind {
+    #[error("{0} are not supported by the {1} backend")]
+    Unsupported(&'static str, &'static str),
+}
+
+/// A backend error, along with the span of source that caused it.
+#[derive(Debug, Error)]
+#[error("{span}: {kind}")]
+pub struct BackendError {
+    pub kind: BackendErrorKind,
+    pub span: Span,
+}
+impl BackendError {
+    pub fn new(kind: BackendErrorKind, span: &Span) -> Self {
+        Self {
+            kind,
+            span: span.clone(),
+        }
+    }
+}
+
+/// A function that can be monomorphised, from either the typed AST or the IR.
+trait Generic {
+    fn ident(&self) -> &str;
+    fn type_parameters(&self) -> &[String];
+}
+impl Generic for TypedFunction {
+    fn ident(&self) -> &str {
+        &self.ident
+    }
+
+    fn type_parameters(&self) -> &[String] {
+        &self.type_parameters
+    }
+}
+impl Generic for ir::Function {
+    fn ident(&self) -> &str {
+        &self.name
+    }
+
+    fn type_parameters(&self) -> &[String] {
+        &self.type_parameters
+    }
+}
+
+/// Every function declared by a type checked program.
+fn functions(typed_ast: &[TypedAstNode]) -> impl Iterator<Item = &TypedFunction> {
+    typed_ast.iter().filter_map(|node| match node {
+        TypedAstNode::Function(function) => Some(function),
+        _ => None,
+    })
+}
+
+/// A function along with the types that its type parameters are instantiated with.
+struct Instance<'a, F = TypedFunction> {
+    function: &'a F,
+    bindings: HashMap<String, Type>,
+    /// The symbol that the instance is emitted as.
+    name: String,
+}
+
+/// Tracks each instance of a function that is used, so that generic functions can be
+/// monomorphised by backends that need to know the concrete type of every value.
+struct Instances<'a, F = TypedFunction> {
+    functions: HashMap<&'a str, &'a F>,
+    /// Each function instance that has been used, along with its type arguments.
+    used: Vec<(String, Vec<Type>)>,
+    /// The number of instances that have been taken to be emitted.
+    emitted: usize,
+}
+impl<'a, F: Generic> Instances<'a, F> {
+    /// Collects the functions of a program, queueing every non-generic function to be emitted.
+    /// Generic functions are only queued once it's known what they're instantiated with.
+    fn new(functions: impl IntoIterator<Item = &'a F>) -> Self {
+        let mut instances = Self {
+            functions: HashMap::new(),
+            used: Vec::new(),
+            emitted: 0,
+        };
+
+        for function in functions {
+            instances.functions.insert(function.ident(), function);
+
+            if function.type_parameters().is_empty() {
+                instances.instance(function.ident(), Vec::new());
+            }
+        }
+
+        instances
+    }
+
+    /// Whether the ident refers to a declared function, rather than an intrinsic.
+    fn is_function(&self, ident: &str) -> bool {
+        self.functions.contains_key(ident)
+    }
+
+    /// Finds the symbol of a function instance, queueing it to be emitted if it hasn't been used
+    /// before.
+    fn instance(&mut self, ident: &str, type_arguments: Vec<Type>) -> String {
+        let name = mangle(ident, &type_arguments);
+        self.index(ident, type_arguments);
+
+        name
+    }
+
+    /// Finds the position of a function instance in the order that instances are emitted,
+    /// queueing it to be emitted if it hasn't been used before.
+    fn index(&mut self, ident: &str, type_arguments: Vec<Type>) -> usize {
+        let instance = (ident.to_string(), type_arguments);
+
+        self.used
+            .iter()
+            .position(|used| *used == instance)
+            .unwrap_or_else(|| {
+                self.used.push(instance);
+                self.used.len() - 1
+            })
+    }
+
+    /// Takes the next instance that is yet to be emitted. Emitting an instance may use further
+    /// instances, which will then be returned in turn.
+    fn next_pending(&mut self) -> Option<Instance<'a, F>> {
+        let (ident, type_arguments) = self.used.get(self.emitted)?;
+        self.emitted += 1;
+
+        let function = self.functions[ident.as_str()];
+
+        Some(Instance {
+            function,
+            bindings: function
+                .type_parameters()
+                .iter()
+                .cloned()
+                .zip(type_arguments.iter().cloned())
+                .collect(),
+            name: mangle(ident, type_arguments),
+        })
+    }
+}
+
+/// Builds the symbol of a function instance, which includes its type arguments so that each
+/// instance is distinct. Every name is prefixed with its length, and the arguments of a generic
+/// type are wrapped in `I` and `E`, so that no two instances can share a symbol. Eg `id::<Integer>`
+/// becomes `fn_2id_7Integer`, which a function named `id_Integer` can't produce.
+fn mangle(ident: &str, type_arguments: &[Type]) -> String {
+    let mut name = format!("fn_{}{ident}", ident.len());
+
+    for ty in type_arguments {
+        name.push('_');
+        mangle_type(ty, &mut name);
+    }
+
+    name
+}
+
+fn mangle_type(ty: &Type, name: &mut String) {
+    let (ident, arguments) = match ty {
+        Type::Named { ident, arguments } => (ident.clone(), arguments.as_slice()),
+        ty => (ty.to_string(), [].as_slice()),
+    };
+    name.push_str(&format!("{}{ident}", ident.len()));
+
+    if !arguments.is_empty() {
+        name.push('I');
+        for argument in arguments {
+            mangle_type(argument, name);
+        }
+        name.push('E');
+    }
+}
+
+/// Quotes a string so that it can be used as a literal in C or assembly, escaping anything that
+/// isn't printable ASCII.
+fn quote(string: &str) -> String {
+    let mut literal = String::from("\"");
+
+    for byte in string.bytes() {
+        match byte {
+            b'"' | b'\\' => literal.push_str(&format!("\\{}", byte as char)),
+            b' '..=b'~' => literal.push(byte as char),
+            // Octal escapes are always three digits, so they can't absorb a following digit
+            _ => literal.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, process::Command};
+
+    use super::{programs::programs, temp_dir::TempDir, *};
+    use crate::{
+        bytecode::{compile, Vm},
+        checks::typing::{TypedExpression, TypedExpressionKind},
+        front_end,
+        ir::lower,
+        parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+        token::Literal,
+    };
+
+    /// Builds every program in `tests/backend` into an executable with `build`, and checks that
+    /// running it produces the same output, errors and exit code as the interpreter.
+    pub(super) fn assert_matches_interpreter(
+        backend: &str,
+        build: impl Fn(&[TypedAstNode], &Path),
+    ) {
+        let directory = TempDir::new(&format!("lang-{backend}"));
+
+        for path in programs(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/backend")) {
+            let (_, typed_ast) = front_end(&fs::read_to_string(&path).unwrap()).unwrap();
+
+            let mut expected_output = Vec::new();
+            let expected_error = Vm::new(&compile(&typed_ast), &mut expected_output)
+                .run()
+                .err()
+                .map(|error| format!("{error}\n"));
+
+            let binary_path = directory.join(path.file_stem().unwrap());
+            build(&typed_ast, &binary_path);
+
+            let output = Command::new(&binary_path).output().unwrap();
+            let name = path.display();
+            assert_eq!(
+                String::from_utf8(output.stdout).unwrap(),
+                String::from_utf8(expected_output).unwrap(),
+                "{name} printed something different when built by the {backend} backend"
+            );
+            assert_eq!(
+                String::from_utf8(output.stderr).unwrap(),
+                expected_error.as_deref().unwrap_or_default(),
+                "{name} failed differently when built by the {backend} backend"
+            );
+            assert_eq!(
+                output.status.code(),
+                Some(if expected_error.is_some() { 1 } else { 0 }),
+                "{name} exited differently when built by the {backend} backend"
+            );
+        }
+    }
+
+    #[test]
+    fn mangling() {
+        let option = Type::Named {
+            ident: "Option".to_string(),
+            arguments: vec![Type::Integer],
+        };
+
+        assert_eq!(mangle("id", &[Type::Integer]), "fn_2id_7Integer");
+        assert_eq!(
+            mangle("pair", &[option, Type::String]),
+            "fn_4pair_6OptionI7IntegerE_6String"
+        );
+        assert_ne!(mangle("id", &[Type::Integer]), mangle("id_Integer", &[]));
+    }
+
+    #[test]
+    fn ill_typed_operators() {
+        // The type checker rejects these, so they can only be built directly
+        let boolean = || {
+            Box::new(TypedExpression {
+                kind: TypedExpressionKind::Literal(Literal::Boolean(true)),
+                ty: Type::Boolean,
+                span: Span::default(),
+            })
+        };
+        for kind in [
+            TypedExpressionKind::BinaryOperation {
+                operation: BinaryOperationKind::Add,
+                lhs: boolean(),
+                rhs: boolean(),
+            },
+            TypedExpressionKind::UnaryOperation {
+                operation: UnaryOperationKind::Negative,
+                rhs: boolean(),
+            },
+        ] {
+            let typed_ast = [TypedAstNode::Expression(TypedExpression {
+                kind,
+                ty: Type::Boolean,
+                span: Span::default(),
+            })];
+
+            for (backend, result) in [
+                ("C", emit_c(&lower(&typed_ast))),
+                ("x86-64", emit_x86_64(&typed_ast)),
+            ] {
+                assert!(
+                    matches!(
+                        result,
+                        Err(BackendError {
+                            kind: BackendErrorKind::Unsupported(..),
+                            ..
+                        })
+                    ),
+                    "the {backend} backend accepted an ill typed operator"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+/* Runtime support for programs emitted by the C backend. */
+
+#include <inttypes.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef unsigned char lang_unit;
+#define LANG_UNIT ((lang_unit)0)
+
+typedef struct {
+    const char *data;
+    size_t length;
+} lang_string;
+
+/* Reports a runtime error in the same format as the interpreter, and exits. */
+static void lang_panic(const char *position, const char *message) {
+    fflush(stdout);
+    fprintf(stderr, "%s: %s\n", position, message);
+    exit(1);
+}
+
+static inline int64_t lang_add(int64_t lhs, int64_t rhs, const char *position) {
+    if ((rhs > 0 && lhs > INT64_MAX - rhs) || (rhs < 0 && lhs < INT64_MIN - rhs)) {
+        lang_panic(position, "integer overflow");
+    }
+    return lhs + rhs;
+}
+
+static inline int64_t lang_sub(int64_t lhs, int64_t rhs, const char *position) {
+    if ((rhs < 0 && lhs > INT64_MAX + rhs) || (rhs > 0 && lhs < INT64_MIN + rhs)) {
+        lang_panic(position, "integer overflow");
+    }
+    return lhs - rhs;
+}
+
+static inline int64_t lang_mul(int64_t lhs, int64_t rhs, const char *position) {
+    bool overflow;
+    if (lhs > 0) {
+        overflow = rhs > 0 ? lhs > INT64_MAX / rhs : rhs < INT64_MIN / lhs;
+    } else {
+        overflow = rhs > 0 ? lhs < INT64_MIN / rhs : lhs != 0 && rhs < INT64_MAX / lhs;
+    }
+    if (overflow) {
+        lang_panic(position, "integer overflow");
+    }
+    return lhs * rhs;
+}
+
+static inline int64_t lang_div(int64_t lhs, int64_t rhs, const char *position) {
+    if (rhs == 0) {
+        lang_panic(position, "attempted to divide by zero");
+    }
+    if (lhs == INT64_MIN && rhs == -1) {
+        lang_panic(position, "integer overflow");
+    }
+    return lhs / rhs;
+}
+
+/* The remainder of INT64_MIN / -1 is zero, even though the division overflows. */
+static inline int64_t lang_rem(int64_t lhs, int64_t rhs, const char *position) {
+    if (rhs == 0) {
+        lang_panic(position, "attempted to divide by zero");
+    }
+    if (rhs == -1) {
+        return 0;
+    }
+    return lhs % rhs;
+}
+
+/* Bits are shifted out rather than overflowing, which is done unsigned to be defined in C. */
+static inline int64_t lang_shl(int64_t lhs, int64_t rhs, const char *position) {
+    if (rhs < 0) {
+        lang_panic(position, "attempted to shift by a negative amount");
+    }
+    return rhs >= 64 ? 0 : (int64_t)((uint64_t)lhs << rhs);
+}
+
+/* An arithmetic shift, written so that it doesn't rely on how the compiler shifts negatives. */
+static inline int64_t lang_shr(int64_t lhs, int64_t rhs, const char *position) {
+    if (rhs < 0) {
+        lang_panic(position, "attempted to shift by a negative amount");
+    }
+    if (rhs > 63) {
+        rhs = 63;
+    }
+    return lhs < 0 ? ~(~lhs >> rhs) : lhs >> rhs;
+}
+
+/* Exponentiation by squaring, where every multiplication is checked for overflow. */
+static inline int64_t lang_pow(int64_t base, int64_t exponent, const char *position) {
+    int64_t result = 1;
+    if (exponent < 0) {
+        lang_panic(position, "attempted to raise to a negative exponent");
+    }
+    while (exponent > 0) {
+        if (exponent & 1) {
+            result = lang_mul(result, base, position);
+        }
+        exponent >>= 1;
+        if (exponent > 0) {
+            base = lang_mul(base, base, position);
+        }
+    }
+    return result;
+}
+
+static inline int64_t lang_neg(int64_t value, const char *position) {
+    if (value == INT64_MIN) {
+        lang_panic(position, "integer overflow");
+    }
+    return -value;
+}
+
+/* Strings are immutable, so the result of a concatenation is never freed. */
+static inline lang_string lang_concat(lang_string lhs, lang_string rhs, const char *position) {
+    char *data = malloc(lhs.length + rhs.length + 1);
+    if (data == NULL) {
+        lang_panic(position, "out of memory");
+    }
+    memcpy(data, lhs.data, lhs.length);
+    memcpy(data + lhs.length, rhs.data, rhs.length);
+    return (lang_string){data, lhs.length + rhs.length};
+}
+
+static inline lang_string lang_integer_string(int64_t value, const char *position) {
+    /* The longest integer is 20 characters, and snprintf also writes a terminator */
+    char *data = malloc(21);
+    if (data == NULL) {
+        lang_panic(position, "out of memory");
+    }
+    int length = snprintf(data, 21, "%" PRId64, value);
+    return (lang_string){data, (size_t)length};
+}
+
+static inline lang_string lang_boolean_string(bool value) {
+    return value ? (lang_string){"true", 4} : (lang_string){"false", 5};
+}
+
+static inline void lang_print_integer(int64_t value) {
+    printf("%" PRId64 "\n", value);
+}
+
+static inline void lang_print_boolean(bool value) {
+    puts(value ? "true" : "false");
+}
+
+static inline void lang_print_string(lang_string value) {
+    fwrite(value.data, 1, value.length, stdout);
+    putchar('\n');
+}
+
+static inline void lang_print_unit(lang_unit value) {
+    (void)value;
+    puts("()");
+}
@@ -0,0 +1,284 @@
+# Runtime support for programs emitted by the x86-64 backend.
+#
+# Every value is a single quad word. Strings are a pointer to their length, which is immediately
+# followed by their bytes. Generated code doesn't keep the stack aligned, so each helper aligns it
+# before calling in to libc.
+
+    .section .rodata
+lang_integer_format: .asciz "%ld\n"
+lang_integer_string_format: .asciz "%ld"
+lang_panic_format: .asciz "%s: %s\n"
+lang_true: .asciz "true"
+lang_false: .asciz "false"
+lang_unit: .asciz "()"
+lang_overflow: .asciz "integer overflow"
+lang_division_by_zero: .asciz "attempted to divide by zero"
+lang_negative_exponent: .asciz "attempted to raise to a negative exponent"
+lang_negative_shift: .asciz "attempted to shift by a negative amount"
+lang_out_of_memory: .asciz "out of memory"
+    .p2align 3
+lang_true_string: .quad 4
+    .ascii "true"
+    .p2align 3
+lang_false_string: .quad 5
+    .ascii "false"
+
+    .text
+# Reports a runtime error at the position in rdi with the message in rsi, in the same format as
+# the interpreter, and exits.
+lang_panic:
+    and rsp, -16
+    mov rcx, rsi
+    mov rdx, rdi
+    lea rsi, [rip + lang_panic_format]
+    mov edi, 2
+    xor eax, eax
+    call dprintf@PLT
+    mov edi, 1
+    call exit@PLT
+
+lang_panic_overflow:
+    lea rsi, [rip + lang_overflow]
+    jmp lang_panic
+
+lang_panic_division_by_zero:
+    lea rsi, [rip + lang_division_by_zero]
+    jmp lang_panic
+
+# Divides rdi by rsi, reporting errors at the position in rdx.
+lang_div:
+    test rsi, rsi
+    jnz 1f
+    mov rdi, rdx
+    jmp lang_panic_division_by_zero
+1:
+    mov rax, rdi
+    # Dividing by -1 is negation, which avoids idiv faulting on overflow
+    cmp rsi, -1
+    jne 2f
+    neg rax
+    jo 3f
+    ret
+2:
+    cqo
+    idiv rsi
+    ret
+3:
+    mov rdi, rdx
+    jmp lang_panic_overflow
+
+# Finds the remainder of dividing rdi by rsi, reporting errors at the position in rdx.
+lang_rem:
+    test rsi, rsi
+    jnz 1f
+    mov rdi, rdx
+    jmp lang_panic_division_by_zero
+1:
+    # The remainder of dividing by -1 is zero, which avoids idiv faulting on overflow
+    xor eax, eax
+    cmp rsi, -1
+    je 2f
+    mov rax, rdi
+    cqo
+    idiv rsi
+    mov rax, rdx
+2:
+    ret
+
+# Reports a shift by a negative amount at the position in rdx.
+lang_panic_negative_shift:
+    mov rdi, rdx
+    lea rsi, [rip + lang_negative_shift]
+    jmp lang_panic
+
+# Shifts rdi left by rsi, discarding the bits shifted out, reporting errors at the position in rdx.
+lang_shl:
+    test rsi, rsi
+    js lang_panic_negative_shift
+    xor eax, eax
+    cmp rsi, 64
+    jge 1f
+    mov rcx, rsi
+    mov rax, rdi
+    shl rax, cl
+1:
+    ret
+
+# Shifts rdi right by rsi, copying in its sign bit, reporting errors at the position in rdx.
+lang_shr:
+    test rsi, rsi
+    js lang_panic_negative_shift
+    mov ecx, 63
+    cmp rsi, rcx
+    cmovl rcx, rsi
+    mov rax, rdi
+    sar rax, cl
+    ret
+
+# Raises rdi to the power of rsi using exponentiation by squaring, reporting errors at the
+# position in rdx.
+lang_pow:
+    test rsi, rsi
+    jns 1f
+    mov rdi, rdx
+    lea rsi, [rip + lang_negative_exponent]
+    jmp lang_panic
+1:
+    mov rax, 1
+2:
+    test rsi, rsi
+    jz 4f
+    test rsi, 1
+    jz 3f
+    imul rax, rdi
+    jo 5f
+3:
+    shr rsi, 1
+    jz 4f
+    imul rdi, rdi
+    jo 5f
+    jmp 2b
+4:
+    ret
+5:
+    mov rdi, rdx
+    jmp lang_panic_overflow
+
+# Concatenates the strings in rdi and rsi, reporting errors at the position in rdx. Strings are
+# immutable, so the result is never freed.
+lang_concat:
+    push rbp
+    mov rbp, rsp
+    push rbx
+    push r12
+    push r13
+    push r14
+    and rsp, -16
+    mov rbx, rdi
+    mov r12, rsi
+    mov r13, rdx
+    mov rdi, qword ptr [rbx]
+    add rdi, qword ptr [r12]
+    add rdi, 8
+    call malloc@PLT
+    test rax, rax
+    jnz 1f
+    mov rdi, r13
+    lea rsi, [rip + lang_out_of_memory]
+    jmp lang_panic
+1:
+    mov r14, rax
+    mov rcx, qword ptr [rbx]
+    add rcx, qword ptr [r12]
+    mov qword ptr [r14], rcx
+    lea rdi, [r14 + 8]
+    lea rsi, [rbx + 8]
+    mov rdx, qword ptr [rbx]
+    call memcpy@PLT
+    mov rdi, qword ptr [rbx]
+    lea rdi, [r14 + rdi + 8]
+    lea rsi, [r12 + 8]
+    mov rdx, qword ptr [r12]
+    call memcpy@PLT
+    mov rax, r14
+    lea rsp, [rbp - 32]
+    pop r14
+    pop r13
+    pop r12
+    pop rbx
+    pop rbp
+    ret
+
+# Formats the integer in rdi as a string, reporting errors at the position in rsi.
+lang_integer_string:
+    push rbp
+    mov rbp, rsp
+    push rbx
+    push r12
+    and rsp, -16
+    mov rbx, rdi
+    mov r12, rsi
+    # The length, followed by up to 20 characters and the terminator that snprintf writes
+    mov edi, 29
+    call malloc@PLT
+    test rax, rax
+    jnz 1f
+    mov rdi, r12
+    lea rsi, [rip + lang_out_of_memory]
+    jmp lang_panic
+1:
+    mov r12, rax
+    lea rdi, [r12 + 8]
+    mov esi, 21
+    lea rdx, [rip + lang_integer_string_format]
+    mov rcx, rbx
+    xor eax, eax
+    call snprintf@PLT
+    # The length is returned as an int
+    cdqe
+    mov qword ptr [r12], rax
+    mov rax, r12
+    lea rsp, [rbp - 16]
+    pop r12
+    pop rbx
+    pop rbp
+    ret
+
+# Finds the string for the boolean in rdi.
+lang_boolean_string:
+    lea rax, [rip + lang_true_string]
+    test rdi, rdi
+    lea rdi, [rip + lang_false_string]
+    cmovz rax, rdi
+    ret
+
+lang_print_integer:
+    push rbp
+    mov rbp, rsp
+    and rsp, -16
+    mov rsi, rdi
+    lea rdi, [rip + lang_integer_format]
+    xor eax, eax
+    call printf@PLT
+    mov rsp, rbp
+    pop rbp
+    ret
+
+lang_print_boolean:
+    push rbp
+    mov rbp, rsp
+    and rsp, -16
+    lea rax, [rip + lang_true]
+    test rdi, rdi
+    lea rdi, [rip + lang_false]
+    cmovnz rdi, rax
+    call puts@PLT
+    mov rsp, rbp
+    pop rbp
+    ret
+
+lang_print_string:
+    push rbp
+    mov rbp, rsp
+    and rsp, -16
+    mov rax, qword ptr [rip + stdout@GOTPCREL]
+    mov rcx, qword ptr [rax]
+    mov rdx, qword ptr [rdi]
+    lea rdi, [rdi + 8]
+    mov esi, 1
+    call fwrite@PLT
+    mov edi, 10
+    call putchar@PLT
+    mov rsp, rbp
+    pop rbp
+    ret
+
+lang_print_unit:
+    push rbp
+    mov rbp, rsp
+    and rsp, -16
+    lea rdi, [rip + lang_unit]
+    call puts@PLT
+    mov rsp, rbp
+    pop rbp
+    ret
@@ -0,0 +1,1284 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    checks::typing::{
+        substitute, Type, TypedAstNode, TypedBlock, TypedExpression, TypedExpressionKind,
+    },
+    lexer::cursor::Span,
+    parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+    token::Literal,
+};
+
+use super::{functions, BackendError, BackendErrorKind, Instance, Instances};
+
+const MAGIC: [u8; 4] = *b"\0asm";
+const VERSION: u32 = 1;
+
+/// Functions that the host must provide, in the order of their indices.
+const IMPORTS: [(&str, &[ValueType]); 3] = [
+    ("print_integer", &[ValueType::I64]),
+    ("print_boolean", &[ValueType::I32]),
+    ("print_unit", &[]),
+];
+
+/// Indices of the helper functions included in every module, which follow the imports.
+const ADD: u32 = 3;
+const SUB: u32 = 4;
+const MUL: u32 = 5;
+const POW: u32 = 6;
+const SHL: u32 = 7;
+const SHR: u32 = 8;
+/// The index of the function containing the top level statements.
+const START: u32 = 9;
+/// The index of the first function instance, after which instances follow in the order they're
+/// emitted.
+const FIRST_INSTANCE: u32 = 10;
+
+/// The opcodes of the instructions that are emitted.
+mod opcode {
+    pub const UNREACHABLE: u8 = 0x00;
+    pub const BLOCK: u8 = 0x02;
+    pub const LOOP: u8 = 0x03;
+    pub const IF: u8 = 0x04;
+    pub const ELSE: u8 = 0x05;
+    pub const END: u8 = 0x0b;
+    pub const BR: u8 = 0x0c;
+    pub const BR_IF: u8 = 0x0d;
+    pub const RETURN: u8 = 0x0f;
+    pub const CALL: u8 = 0x10;
+    pub const DROP: u8 = 0x1a;
+    pub const LOCAL_GET: u8 = 0x20;
+    pub const LOCAL_SET: u8 = 0x21;
+    pub const LOCAL_TEE: u8 = 0x22;
+    pub const I32_CONST: u8 = 0x41;
+    pub const I64_CONST: u8 = 0x42;
+    pub const I32_EQZ: u8 = 0x45;
+    pub const I64_EQZ: u8 = 0x50;
+    pub const I64_NE: u8 = 0x52;
+    pub const I64_LT_S: u8 = 0x53;
+    pub const I64_GT_S: u8 = 0x55;
+    pub const I64_ADD: u8 = 0x7c;
+    pub const I64_SUB: u8 = 0x7d;
+    pub const I64_MUL: u8 = 0x7e;
+    pub const I64_DIV_S: u8 = 0x7f;
+    pub const I64_REM_S: u8 = 0x81;
+    pub const I64_AND: u8 = 0x83;
+    pub const I64_OR: u8 = 0x84;
+    pub const I64_XOR: u8 = 0x85;
+    pub const I64_SHL: u8 = 0x86;
+    pub const I64_SHR_S: u8 = 0x87;
+    pub const I64_SHR_U: u8 = 0x88;
+    /// The block type of a block that doesn't produce a value.
+    pub const EMPTY: u8 = 0x40;
+    /// The block type of a block that produces an `i64`.
+    pub const I64_RESULT: u8 = 0x7e;
+}
+
+/// The WebAssembly types that values are represented with. Booleans and unit are both `i32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueType {
+    I32,
+    I64,
+}
+impl ValueType {
+    fn encode(self) -> u8 {
+        match self {
+            ValueType::I32 => 0x7f,
+            ValueType::I64 => 0x7e,
+        }
+    }
+}
+
+/// Translates a type checked program into a WebAssembly binary module. Printing is imported from
+/// the `env` module of the host, and the top level statements are exported as `_start`, alongside
+/// each non-generic function under its own name.
+///
+/// Integer overflow, division by zero, negative exponents and negative shifts trap.
+pub fn emit_wasm(typed_ast: &[TypedAstNode]) -> Result<Vec<u8>, BackendError> {
+    let mut instances = Instances::new(functions(typed_ast));
+    let mut module = Module::default();
+
+    for (parameters, code) in [add(), sub(), mul(), pow(), shl(), shr()] {
+        let locals = vec![ValueType::I64; parameters + 1];
+        module.define(&locals[..parameters], &[ValueType::I64], &locals, code);
+    }
+
+    let mut start = FunctionEmitter::new(HashMap::new());
+    for node in typed_ast {
+        match node {
+            TypedAstNode::Let(_) | TypedAstNode::Expression(_) => {
+                start.emit_statement(&mut instances, node)?
+            }
+            TypedAstNode::Function(_) => (),
+        }
+    }
+    module.define(&[], &[], &start.local_types, start.code);
+    module.exports.push(("_start".to_string(), START));
+
+    // Emitting a function may instantiate further functions, which are then emitted in turn
+    let mut index = FIRST_INSTANCE;
+    while let Some(instance) = instances.next_pending() {
+        let function = instance.function;
+        if function.type_parameters.is_empty() && function.ident != "_start" {
+            module.exports.push((function.ident.clone(), index));
+        }
+
+        emit_function(&mut instances, &mut module, instance)?;
+        index += 1;
+    }
+
+    Ok(module.encode())
+}
+
+/// Emits an instance of a function as the next function of the module.
+fn emit_function(
+    instances: &mut Instances,
+    module: &mut Module,
+    instance: Instance,
+) -> Result<(), BackendError> {
+    let function = instance.function;
+    let mut emitter = FunctionEmitter::new(instance.bindings);
+
+    let result = emitter.value_type(&function.return_type, &function.span)?;
+    for (ident, ty) in &function.parameters {
+        let ty = emitter.value_type(ty, &function.span)?;
+        emitter.declare(ident, ty);
+    }
+    let parameters = emitter.local_types.clone();
+
+    emitter.emit_block(instances, &function.body)?;
+
+    module.define(&parameters, &[result], &emitter.local_types, emitter.code);
+
+    Ok(())
+}
+
+/// The sections of a module that are built up whilst emitting.
+#[derive(Default)]
+struct Module {
+    /// Each distinct function type, made up of its parameters and results.
+    types: Vec<(Vec<ValueType>, Vec<ValueType>)>,
+    /// The type index of each defined function, excluding imports.
+    functions: Vec<u32>,
+    exports: Vec<(String, u32)>,
+    bodies: Vec<Vec<u8>>,
+}
+impl Module {
+    /// Finds the index of a function type, adding it if it hasn't been used before.
+    fn type_index(&mut self, parameters: &[ValueType], results: &[ValueType]) -> u32 {
+        let ty = (parameters.to_vec(), results.to_vec());
+
+        self.types
+            .iter()
+            .position(|existing| *existing == ty)
+            .unwrap_or_else(|| {
+                self.types.push(ty);
+                self.types.len() - 1
+            }) as u32
+    }
+
+    /// Adds a function, where `locals` includes the parameters and `code` doesn't include the
+    /// final `end`.
+    fn define(
+        &mut self,
+        parameters: &[ValueType],
+        results: &[ValueType],
+        locals: &[ValueType],
+        code: Vec<u8>,
+    ) {
+        let type_index = self.type_index(parameters, results);
+        self.functions.push(type_index);
+
+        // Locals are declared as runs of the same type
+        let mut runs: Vec<(u32, ValueType)> = Vec::new();
+        for ty in &locals[parameters.len()..] {
+            match runs.last_mut() {
+                Some((count, run_ty)) if run_ty == ty => *count += 1,
+                _ => runs.push((1, *ty)),
+            }
+        }
+
+        let mut body = Vec::new();
+        unsigned(&mut body, runs.len() as u64);
+        for (count, ty) in runs {
+            unsigned(&mut body, count as u64);
+            body.push(ty.encode());
+        }
+        body.extend(code);
+        body.push(opcode::END);
+
+        self.bodies.push(body);
+    }
+
+    fn encode(mut self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+
+        let imports = IMPORTS
+            .iter()
+            .map(|(name, parameters)| (*name, self.type_index(parameters, &[])))
+            .collect::<Vec<_>>();
+
+        section(
+            &mut bytes,
+            1,
+            &self.types,
+            |bytes, (parameters, results)| {
+                bytes.push(0x60);
+                for types in [parameters, results] {
+                    unsigned(bytes, types.len() as u64);
+                    bytes.extend(types.iter().map(|ty| ty.encode()));
+                }
+            },
+        );
+
+        section(&mut bytes, 2, &imports, |bytes, (name, type_index)| {
+            name_bytes(bytes, "env");
+            name_bytes(bytes, name);
+            bytes.push(0x00);
+            unsigned(bytes, *type_index as u64);
+        });
+
+        section(&mut bytes, 3, &self.functions, |bytes, type_index| {
+            unsigned(bytes, *type_index as u64)
+        });
+
+        section(&mut bytes, 7, &self.exports, |bytes, (name, index)| {
+            name_bytes(bytes, name);
+            bytes.push(0x00);
+            unsigned(bytes, *index as u64);
+        });
+
+        section(&mut bytes, 10, &self.bodies, |bytes, body| {
+            unsigned(bytes, body.len() as u64);
+            bytes.extend(body);
+        });
+
+        bytes
+    }
+}
+
+/// Emits the body of a single function instance.
+struct FunctionEmitter {
+    code: Vec<u8>,
+    /// The ident and index of each local in scope, in the order that they were declared.
+    locals: Vec<(String, u32)>,
+    /// The type of every local in the function, starting with the parameters.
+    local_types: Vec<ValueType>,
+    /// The type that each of the function's type parameters is instantiated with.
+    bindings: HashMap<String, Type>,
+}
+impl FunctionEmitter {
+    fn new(bindings: HashMap<String, Type>) -> Self {
+        Self {
+            code: Vec::new(),
+            locals: Vec::new(),
+            local_types: Vec::new(),
+            bindings,
+        }
+    }
+
+    /// Brings a local in to scope, returning its index.
+    fn declare(&mut self, ident: &str, ty: ValueType) -> u32 {
+        let index = self.local_types.len() as u32;
+        self.local_types.push(ty);
+        self.locals.push((ident.to_string(), index));
+
+        index
+    }
+
+    fn instruction(&mut self, opcode: u8, immediate: Option<u32>) {
+        self.code.push(opcode);
+
+        if let Some(immediate) = immediate {
+            unsigned(&mut self.code, immediate as u64);
+        }
+    }
+
+    /// Resolves the concrete type of a value within this instance.
+    fn resolve(&self, ty: &Type, span: &Span) -> Result<Type, BackendError> {
+        match substitute(ty, &self.bindings) {
+            Type::String => Err(unsupported("strings", span)),
+            Type::Named { .. } => Err(unsupported("structs and enums", span)),
+            Type::Parameter(_) | Type::Unknown => {
+                unreachable!("type checker and instantiation to resolve every type")
+            }
+            ty => Ok(ty),
+        }
+    }
+
+    fn value_type(&self, ty: &Type, span: &Span) -> Result<ValueType, BackendError> {
+        Ok(match self.resolve(ty, span)? {
+            Type::Integer => ValueType::I64,
+            _ => ValueType::I32,
+        })
+    }
+
+    fn emit_statement(
+        &mut self,
+        instances: &mut Instances,
+        statement: &TypedAstNode,
+    ) -> Result<(), BackendError> {
+        match statement {
+            TypedAstNode::Let(let_node) => {
+                self.emit_expression(instances, &let_node.rhs)?;
+                let ty = self.value_type(&let_node.rhs.ty, &let_node.rhs.span)?;
+
+                let index = self.declare(&let_node.ident, ty);
+                self.instruction(opcode::LOCAL_SET, Some(index));
+            }
+            TypedAstNode::Expression(expression) => {
+                self.emit_expression(instances, expression)?;
+                self.instruction(opcode::DROP, None);
+            }
+            // Functions can only be declared at the top level
+            TypedAstNode::Function(_) => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Emits each of the statements in a block, leaving the value it evaluates to on the stack.
+    fn emit_block(
+        &mut self,
+        instances: &mut Instances,
+        block: &TypedBlock,
+    ) -> Result<(), BackendError> {
+        let scope = self.locals.len();
+
+        for statement in &block.statements {
+            self.emit_statement(instances, statement)?;
+        }
+
+        match &block.expression {
+            Some(expression) => self.emit_expression(instances, expression)?,
+            None => self.instruction(opcode::I32_CONST, Some(0)),
+        }
+
+        self.locals.truncate(scope);
+
+        Ok(())
+    }
+
+    /// Emits the instructions to evaluate an expression, leaving the result on the stack.
+    fn emit_expression(
+        &mut self,
+        instances: &mut Instances,
+        expression: &TypedExpression,
+    ) -> Result<(), BackendError> {
+        let span = &expression.span;
+
+        match &expression.kind {
+            TypedExpressionKind::Ident(ident) => {
+                let index = self
+                    .locals
+                    .iter()
+                    .rfind(|(local, _)| local == ident)
+                    .map(|(_, index)| *index)
+                    .expect("type checker to reject unknown idents");
+
+                self.instruction(opcode::LOCAL_GET, Some(index));
+            }
+            TypedExpressionKind::Literal(literal) => match literal {
+                Literal::Integer(integer) => {
+                    self.code.push(opcode::I64_CONST);
+                    signed(&mut self.code, *integer as i64);
+                }
+                Literal::Boolean(boolean) => {
+                    self.instruction(opcode::I32_CONST, Some(*boolean as u32))
+                }
+                Literal::String(_) => return Err(unsupported("strings", span)),
+            },
+            TypedExpressionKind::Interpolation(_) => return Err(unsupported("strings", span)),
+            TypedExpressionKind::BinaryOperation {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                self.resolve(&expression.ty, span)?;
+                self.emit_expression(instances, lhs)?;
+                self.emit_expression(instances, rhs)?;
+
+                match operation {
+                    BinaryOperationKind::Add => self.instruction(opcode::CALL, Some(ADD)),
+                    BinaryOperationKind::Sub => self.instruction(opcode::CALL, Some(SUB)),
+                    BinaryOperationKind::Mult => self.instruction(opcode::CALL, Some(MUL)),
+                    // Traps on division by zero and overflow
+                    BinaryOperationKind::Div => self.instruction(opcode::I64_DIV_S, None),
+                    // Traps on division by zero, but gives zero for `i64::MIN % -1`
+                    BinaryOperationKind::Mod => self.instruction(opcode::I64_REM_S, None),
+                    BinaryOperationKind::Exp => self.instruction(opcode::CALL, Some(POW)),
+                    BinaryOperationKind::And => self.instruction(opcode::I64_AND, None),
+                    BinaryOperationKind::Or => self.instruction(opcode::I64_OR, None),
+                    BinaryOperationKind::Xor => self.instruction(opcode::I64_XOR, None),
+                    BinaryOperationKind::Shl => self.instruction(opcode::CALL, Some(SHL)),
+                    BinaryOperationKind::Shr => self.instruction(opcode::CALL, Some(SHR)),
+                }
+            }
+            TypedExpressionKind::UnaryOperation { operation, rhs } => match operation {
+                UnaryOperationKind::Negative => {
+                    self.code.push(opcode::I64_CONST);
+                    signed(&mut self.code, 0);
+                    self.emit_expression(instances, rhs)?;
+                    self.instruction(opcode::CALL, Some(SUB));
+                }
+                UnaryOperationKind::Complement => {
+                    self.emit_expression(instances, rhs)?;
+                    self.code.push(opcode::I64_CONST);
+                    signed(&mut self.code, -1);
+                    self.instruction(opcode::I64_XOR, None);
+                }
+            },
+            TypedExpressionKind::Call {
+                ident,
+                type_arguments,
+                arguments,
+            } => {
+                for argument in arguments {
+                    self.emit_expression(instances, argument)?;
+                }
+
+                if !instances.is_function(ident) && ident == "print" {
+                    let argument = &arguments[0];
+
+                    match self.resolve(&argument.ty, &argument.span)? {
+                        Type::Integer => self.instruction(opcode::CALL, Some(0)),
+                        Type::Boolean => self.instruction(opcode::CALL, Some(1)),
+                        _ => {
+                            self.instruction(opcode::DROP, None);
+                            self.instruction(opcode::CALL, Some(2));
+                        }
+                    }
+                    self.instruction(opcode::I32_CONST, Some(0));
+
+                    return Ok(());
+                }
+
+                // Host functions are only available when running on the virtual machine
+                if !instances.is_function(ident) {
+                    return Err(unsupported("host functions", span));
+                }
+
+                let type_arguments = type_arguments
+                    .iter()
+                    .map(|ty| substitute(ty, &self.bindings))
+                    .collect();
+                let index = instances.index(ident, type_arguments) as u32;
+
+                self.instruction(opcode::CALL, Some(FIRST_INSTANCE + index));
+            }
+            TypedExpressionKind::Variant { .. } | TypedExpressionKind::Struct { .. } => {
+                return Err(unsupported("structs and enums", span))
+            }
+            TypedExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let ty = self.value_type(&expression.ty, span)?;
+                self.emit_expression(instances, condition)?;
+
+                self.code.extend([opcode::IF, ty.encode()]);
+                self.emit_block(instances, then)?;
+                self.instruction(opcode::ELSE, None);
+                match otherwise {
+                    Some(otherwise) => self.emit_block(instances, otherwise)?,
+                    None => self.instruction(opcode::I32_CONST, Some(0)),
+                }
+                self.instruction(opcode::END, None);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `add(a: i64, b: i64) -> i64`, trapping if the signs of both operands differ from the result.
+fn add() -> (usize, Vec<u8>) {
+    use opcode::*;
+
+    (
+        2,
+        vec![
+            LOCAL_GET,
+            0,
+            LOCAL_GET,
+            1,
+            I64_ADD,
+            LOCAL_TEE,
+            2, //
+            LOCAL_GET,
+            0,
+            I64_XOR,
+            LOCAL_GET,
+            2,
+            LOCAL_GET,
+            1,
+            I64_XOR,
+            I64_AND, //
+            I64_CONST,
+            0,
+            I64_LT_S,
+            IF,
+            EMPTY,
+            UNREACHABLE,
+            END, //
+            LOCAL_GET,
+            2,
+        ],
+    )
+}
+
+/// `sub(a: i64, b: i64) -> i64`, trapping if the operands have different signs and the sign of
+/// the result differs from `a`.
+fn sub() -> (usize, Vec<u8>) {
+    use opcode::*;
+
+    (
+        2,
+        vec![
+            LOCAL_GET,
+            0,
+            LOCAL_GET,
+            1,
+            I64_SUB,
+            LOCAL_SET,
+            2, //
+            LOCAL_GET,
+            0,
+            LOCAL_GET,
+            1,
+            I64_XOR,
+            LOCAL_GET,
+            0,
+            LOCAL_GET,
+            2,
+            I64_XOR,
+            I64_AND, //
+            I64_CONST,
+            0,
+            I64_LT_S,
+            IF,
+            EMPTY,
+            UNREACHABLE,
+            END, //
+            LOCAL_GET,
+            2,
+        ],
+    )
+}
+
+/// `mul(a: i64, b: i64) -> i64`, trapping unless dividing the result by `a` gives back `b`.
+fn mul() -> (usize, Vec<u8>) {
+    use opcode::*;
+
+    (
+        2,
+        vec![
+            LOCAL_GET,
+            0,
+            LOCAL_GET,
+            1,
+            I64_MUL,
+            LOCAL_SET,
+            2, //
+            LOCAL_GET,
+            0,
+            I64_EQZ,
+            I32_EQZ,
+            IF,
+            EMPTY, //
+            // Division itself traps when the result is `i64::MIN` and `a` is -1
+            LOCAL_GET,
+            2,
+            LOCAL_GET,
+            0,
+            I64_DIV_S,
+            LOCAL_GET,
+            1,
+            I64_NE, //
+            IF,
+            EMPTY,
+            UNREACHABLE,
+            END, //
+            END, //
+            LOCAL_GET,
+            2,
+        ],
+    )
+}
+
+/// `pow(base: i64, exponent: i64) -> i64` using exponentiation by squaring, trapping on negative
+/// exponents and overflow.
+fn pow() -> (usize, Vec<u8>) {
+    use opcode::*;
+
+    let mul = MUL as u8;
+
+    (
+        2,
+        vec![
+            LOCAL_GET,
+            1,
+            I64_CONST,
+            0,
+            I64_LT_S,
+            IF,
+            EMPTY,
+            UNREACHABLE,
+            END, //
+            I64_CONST,
+            1,
+            LOCAL_SET,
+            2, //
+            BLOCK,
+            EMPTY,
+            LOOP,
+            EMPTY, //
+            LOCAL_GET,
+            1,
+            I64_EQZ,
+            BR_IF,
+            1, //
+            LOCAL_GET,
+            1,
+            I64_CONST,
+            1,
+            I64_AND,
+            I64_EQZ,
+            I32_EQZ,
+            IF,
+            EMPTY, //
+            LOCAL_GET,
+            2,
+            LOCAL_GET,
+            0,
+            CALL,
+            mul,
+            LOCAL_SET,
+            2,   //
+            END, //
+            LOCAL_GET,
+            1,
+            I64_CONST,
+            1,
+            I64_SHR_U,
+            LOCAL_TEE,
+            1,
+            I64_EQZ,
+            BR_IF,
+            1, //
+            LOCAL_GET,
+            0,
+            LOCAL_GET,
+            0,
+            CALL,
+            mul,
+            LOCAL_SET,
+            0, //
+            BR,
+            0,
+            END,
+            END, //
+            LOCAL_GET,
+            2,
+        ],
+    )
+}
+
+/// `shl(a: i64, b: i64) -> i64`, trapping on negative shifts. WebAssembly only uses the lowest
+/// six bits of `b`, so shifting by 64 or more is handled separately to give zero.
+fn shl() -> (usize, Vec<u8>) {
+    use opcode::*;
+
+    (
+        2,
+        vec![
+            LOCAL_GET,
+            1,
+            I64_CONST,
+            0,
+            I64_LT_S,
+            IF,
+            EMPTY,
+            UNREACHABLE,
+            END, //
+            LOCAL_GET,
+            1,
+            I64_CONST,
+            63,
+            I64_GT_S,
+            IF,
+            I64_RESULT,
+            I64_CONST,
+            0,
+            ELSE, //
+            LOCAL_GET,
+            0,
+            LOCAL_GET,
+            1,
+            I64_SHL,
+            END,
+        ],
+    )
+}
+
+/// `shr(a: i64, b: i64) -> i64`, an arithmetic shift that traps on negative shifts. Shifts of 64
+/// or more are clamped to 63, which gives the same result.
+fn shr() -> (usize, Vec<u8>) {
+    use opcode::*;
+
+    (
+        2,
+        vec![
+            LOCAL_GET,
+            1,
+            I64_CONST,
+            0,
+            I64_LT_S,
+            IF,
+            EMPTY,
+            UNREACHABLE,
+            END, //
+            LOCAL_GET,
+            0, //
+            LOCAL_GET,
+            1,
+            I64_CONST,
+            63,
+            I64_GT_S,
+            IF,
+            I64_RESULT,
+            I64_CONST,
+            63,
+            ELSE,
+            LOCAL_GET,
+            1,
+            END, //
+            I64_SHR_S,
+        ],
+    )
+}
+
+fn unsupported(feature: &'static str, span: &Span) -> BackendError {
+    BackendError::new(BackendErrorKind::Unsupported(feature, "WebAssembly"), span)
+}
+
+/// Writes a section made up of a vector of items, prefixed by its id and size.
+fn section<T>(bytes: &mut Vec<u8>, id: u8, items: &[T], mut item: impl FnMut(&mut Vec<u8>, &T)) {
+    let mut contents = Vec::new();
+    unsigned(&mut contents, items.len() as u64);
+    for value in items {
+        item(&mut contents, value);
+    }
+
+    bytes.push(id);
+    unsigned(bytes, contents.len() as u64);
+    bytes.extend(contents);
+}
+
+fn name_bytes(bytes: &mut Vec<u8>, name: &str) {
+    unsigned(bytes, name.len() as u64);
+    bytes.extend(name.as_bytes());
+}
+
+/// Writes an unsigned LEB128 integer.
+fn unsigned(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Writes a signed LEB128 integer.
+fn signed(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        // Finished once the remaining bits are all copies of the sign bit
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// All of the reasons that a module could fail validation.
+#[derive(Debug, Error)]
+pub enum WasmError {
+    #[error("not a WebAssembly module")]
+    InvalidMagic,
+    #[error("unsupported WebAssembly version {0}")]
+    UnsupportedVersion(u32),
+    #[error("unexpected end of module at byte {0}")]
+    UnexpectedEnd(usize),
+    #[error("invalid {kind} at byte {offset}")]
+    Invalid { kind: &'static str, offset: usize },
+    #[error("section {0} is out of order or repeated")]
+    SectionOrder(u8),
+    #[error("section {id} should be {expected} bytes, but was {found} bytes")]
+    SectionSize {
+        id: u8,
+        expected: usize,
+        found: usize,
+    },
+    #[error("{kind} index {index} is out of range at byte {offset}")]
+    OutOfRange {
+        kind: &'static str,
+        index: u32,
+        offset: usize,
+    },
+    #[error("{functions} functions were declared, but {bodies} bodies were provided")]
+    BodyCount { functions: usize, bodies: usize },
+}
+
+/// A summary of a module that passed validation.
+#[derive(Debug)]
+pub struct WasmModule {
+    pub imports: Vec<String>,
+    pub functions: usize,
+    pub exports: Vec<String>,
+}
+
+/// Validates the structure of a module offline, without instantiating it. Every section is
+/// checked to be well formed and in order, every index to be in range, and every function body to
+/// be made up of properly nested instructions.
+pub fn validate_wasm(bytes: &[u8]) -> Result<WasmModule, WasmError> {
+    if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+        return Err(WasmError::InvalidMagic);
+    }
+
+    let mut reader = Reader {
+        bytes,
+        offset: MAGIC.len(),
+    };
+
+    let version = u32::from_le_bytes(reader.take(4)?.try_into().expect("slice to have length 4"));
+    if version != VERSION {
+        return Err(WasmError::UnsupportedVersion(version));
+    }
+
+    let mut module = WasmModule {
+        imports: Vec::new(),
+        functions: 0,
+        exports: Vec::new(),
+    };
+    let mut types = Vec::new();
+    let mut imported_types = Vec::new();
+    let mut function_types = Vec::new();
+    let mut bodies = 0;
+    let mut last_id = 0;
+
+    while reader.offset < bytes.len() {
+        let id = reader.u8()?;
+        let size = reader.unsigned()? as usize;
+        let start = reader.offset;
+
+        // Custom sections may appear anywhere, but the others must be in order
+        if id != 0 {
+            if id <= last_id {
+                return Err(WasmError::SectionOrder(id));
+            }
+            last_id = id;
+        }
+
+        match id {
+            1 => {
+                for _ in 0..reader.unsigned()? {
+                    reader.expect(0x60, "function type")?;
+
+                    let mut counts = [0; 2];
+                    for count in &mut counts {
+                        *count = reader.unsigned()?;
+                        for _ in 0..*count {
+                            reader.value_type()?;
+                        }
+                    }
+                    types.push(counts[0]);
+                }
+            }
+            2 => {
+                for _ in 0..reader.unsigned()? {
+                    let module_name = reader.name()?;
+                    let name = reader.name()?;
+                    reader.expect(0x00, "import kind")?;
+
+                    imported_types.push(reader.index("type", types.len())?);
+                    module.imports.push(format!("{module_name}.{name}"));
+                }
+            }
+            3 => {
+                for _ in 0..reader.unsigned()? {
+                    function_types.push(reader.index("type", types.len())?);
+                }
+            }
+            7 => {
+                let functions = imported_types.len() + function_types.len();
+
+                for _ in 0..reader.unsigned()? {
+                    let name = reader.name()?;
+
+                    let offset = reader.offset;
+                    match reader.u8()? {
+                        0x00 => {
+                            reader.index("function", functions)?;
+                        }
+                        0x01..=0x03 => {
+                            reader.unsigned()?;
+                        }
+                        _ => {
+                            return Err(WasmError::Invalid {
+                                kind: "export kind",
+                                offset,
+                            })
+                        }
+                    }
+
+                    module.exports.push(name);
+                }
+            }
+            10 => {
+                bodies = reader.unsigned()? as usize;
+                if bodies != function_types.len() {
+                    return Err(WasmError::BodyCount {
+                        functions: function_types.len(),
+                        bodies,
+                    });
+                }
+
+                let functions = imported_types.len() + function_types.len();
+                for type_index in &function_types {
+                    let size = reader.unsigned()? as usize;
+                    let end = reader.offset + size;
+
+                    let mut locals = types[*type_index as usize] as usize;
+                    for _ in 0..reader.unsigned()? {
+                        locals += reader.unsigned()? as usize;
+                        reader.value_type()?;
+                    }
+
+                    reader.code(end, functions, locals)?;
+                }
+            }
+            // The contents of the remaining sections aren't emitted, so they're skipped
+            0 | 4..=6 | 8 | 9 | 11 | 12 => {
+                reader.take(size)?;
+            }
+            _ => {
+                return Err(WasmError::Invalid {
+                    kind: "section id",
+                    offset: start - 1,
+                })
+            }
+        }
+
+        if reader.offset - start != size {
+            return Err(WasmError::SectionSize {
+                id,
+                expected: size,
+                found: reader.offset - start,
+            });
+        }
+    }
+
+    if bodies != function_types.len() {
+        return Err(WasmError::BodyCount {
+            functions: function_types.len(),
+            bodies,
+        });
+    }
+
+    module.functions = imported_types.len() + function_types.len();
+
+    Ok(module)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8], WasmError> {
+        let bytes = self
+            .offset
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or(WasmError::UnexpectedEnd(self.offset))?;
+        self.offset += length;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, WasmError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn expect(&mut self, expected: u8, kind: &'static str) -> Result<(), WasmError> {
+        let offset = self.offset;
+
+        if self.u8()? != expected {
+            return Err(WasmError::Invalid { kind, offset });
+        }
+
+        Ok(())
+    }
+
+    /// Reads an unsigned LEB128 integer of up to 32 bits.
+    fn unsigned(&mut self) -> Result<u32, WasmError> {
+        let offset = self.offset;
+        let mut value = 0u64;
+
+        for shift in (0..35).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return u32::try_from(value).map_err(|_| WasmError::Invalid {
+                    kind: "integer",
+                    offset,
+                });
+            }
+        }
+
+        Err(WasmError::Invalid {
+            kind: "integer",
+            offset,
+        })
+    }
+
+    /// Skips over a signed LEB128 integer of up to 64 bits.
+    fn skip_signed(&mut self) -> Result<(), WasmError> {
+        let offset = self.offset;
+
+        for _ in 0..10 {
+            if self.u8()? & 0x80 == 0 {
+                return Ok(());
+            }
+        }
+
+        Err(WasmError::Invalid {
+            kind: "integer",
+            offset,
+        })
+    }
+
+    /// Reads an index, checking that it's less than `count`.
+    fn index(&mut self, kind: &'static str, count: usize) -> Result<u32, WasmError> {
+        let offset = self.offset;
+        let index = self.unsigned()?;
+
+        if index as usize >= count {
+            return Err(WasmError::OutOfRange {
+                kind,
+                index,
+                offset,
+            });
+        }
+
+        Ok(index)
+    }
+
+    fn name(&mut self) -> Result<String, WasmError> {
+        let offset = self.offset;
+        let length = self.unsigned()? as usize;
+
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| WasmError::Invalid {
+            kind: "name",
+            offset,
+        })
+    }
+
+    fn value_type(&mut self) -> Result<(), WasmError> {
+        let offset = self.offset;
+
+        match self.u8()? {
+            0x7c..=0x7f => Ok(()),
+            _ => Err(WasmError::Invalid {
+                kind: "value type",
+                offset,
+            }),
+        }
+    }
+
+    /// Checks the instructions of a function body that finishes at `end`, making sure that
+    /// blocks are nested properly and that calls, locals and branches are in range.
+    fn code(&mut self, end: usize, functions: usize, locals: usize) -> Result<(), WasmError> {
+        // The function body itself is the outermost block
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.offset >= end {
+                return Err(WasmError::UnexpectedEnd(self.offset));
+            }
+
+            let offset = self.offset;
+            match self.u8()? {
+                opcode::BLOCK | opcode::LOOP | opcode::IF => {
+                    let block_type_offset = self.offset;
+                    match self.u8()? {
+                        opcode::EMPTY | 0x7c..=0x7f => (),
+                        _ => {
+                            return Err(WasmError::Invalid {
+                                kind: "block type",
+                                offset: block_type_offset,
+                            })
+                        }
+                    }
+
+                    depth += 1;
+                }
+                opcode::END => depth -= 1,
+                opcode::BR | opcode::BR_IF => {
+                    self.index("label", depth)?;
+                }
+                opcode::CALL => {
+                    self.index("function", functions)?;
+                }
+                opcode::LOCAL_GET | opcode::LOCAL_SET | opcode::LOCAL_TEE => {
+                    self.index("local", locals)?;
+                }
+                opcode::I32_CONST | opcode::I64_CONST => self.skip_signed()?,
+                opcode::UNREACHABLE | opcode::ELSE | opcode::RETURN | opcode::DROP => (),
+                // Numeric instructions without any immediates
+                0x45..=0xc4 => (),
+                _ => {
+                    return Err(WasmError::Invalid {
+                        kind: "instruction",
+                        offset,
+                    })
+                }
+            }
+        }
+
+        if self.offset != end {
+            return Err(WasmError::Invalid {
+                kind: "function body size",
+                offset: self.offset,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_end;
+
+    fn emit(source: &str) -> Result<Vec<u8>, BackendError> {
+        let (_, typed_ast) = front_end(source).unwrap();
+        emit_wasm(&typed_ast)
+    }
+
+    #[test]
+    fn valid_module() {
+        let bytes = emit(
+            "fn id<T>(x: T) -> T { x }
+            fn square(x: Integer) -> Integer { let y = x * x; y }
+            fn nothing() {}
+            print(square(id(-3)) - 2 ^ 3 ^ 2 / 4);
+            print(1 << 2 >> 1 & 3 | 4 ~ ~5 % 6);
+            print(id(true));
+            print(nothing());",
+        )
+        .unwrap();
+
+        let module = validate_wasm(&bytes).unwrap();
+        assert_eq!(
+            module.imports,
+            ["env.print_integer", "env.print_boolean", "env.print_unit"]
+        );
+        // Imports, helpers, start, square, nothing and two instances of id
+        assert_eq!(module.functions, 14);
+        assert_eq!(module.exports, ["_start", "square", "nothing"]);
+
+        let bytes = emit(
+            "fn abs(x: Integer, negative: Boolean) -> Integer { if negative { -x } else { x } }
+            if true { let a = abs(2, true); print(a); }",
+        )
+        .unwrap();
+        assert_eq!(validate_wasm(&bytes).unwrap().exports, ["_start", "abs"]);
+    }
+
+    #[test]
+    fn leb128() {
+        let mut bytes = Vec::new();
+        unsigned(&mut bytes, 624485);
+        assert_eq!(bytes, [0xe5, 0x8e, 0x26]);
+
+        bytes.clear();
+        signed(&mut bytes, -123456);
+        assert_eq!(bytes, [0xc0, 0xbb, 0x78]);
+
+        bytes.clear();
+        signed(&mut bytes, 64);
+        assert_eq!(bytes, [0xc0, 0x00]);
+    }
+
+    #[test]
+    fn rejects_invalid_modules() {
+        let bytes = emit("print(1);").unwrap();
+
+        assert!(matches!(
+            validate_wasm(b"\0elf"),
+            Err(WasmError::InvalidMagic)
+        ));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 2;
+        assert!(matches!(
+            validate_wasm(&wrong_version),
+            Err(WasmError::UnsupportedVersion(2))
+        ));
+
+        assert!(matches!(
+            validate_wasm(&bytes[..bytes.len() - 1]),
+            Err(WasmError::UnexpectedEnd(_))
+        ));
+
+        // A code section with a body calling a function that doesn't exist
+        let mut module = MAGIC.to_vec();
+        module.extend(VERSION.to_le_bytes());
+        module.extend([1, 4, 1, 0x60, 0, 0]);
+        module.extend([3, 2, 1, 0]);
+        module.extend([10, 6, 1, 4, 0, opcode::CALL, 5, opcode::END]);
+        assert!(matches!(
+            validate_wasm(&module),
+            Err(WasmError::OutOfRange {
+                kind: "function",
+                index: 5,
+                ..
+            })
+        ));
+
+        // The same body, without the function section declaring it
+        module.drain(14..18);
+        assert!(matches!(
+            validate_wasm(&module),
+            Err(WasmError::BodyCount {
+                functions: 0,
+                bodies: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn unsupported() {
+        let error = emit("let a = 1;\nprint(\"hello\");").unwrap_err();
+        assert!(matches!(error.kind, BackendErrorKind::Unsupported(..)));
+        assert_eq!(error.span.to_string(), "2:7");
+    }
+}
@@ -0,0 +1,543 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    checks::typing::{
+        substitute, Type, TypedAstNode, TypedBlock, TypedExpression, TypedExpressionKind,
+    },
+    lexer::cursor::Span,
+    parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+    token::Literal,
+};
+
+use super::{functions, quote, BackendError, BackendErrorKind, Instance, Instances};
+
+/// Helpers for division, exponents, strings, printing and reporting errors, included in every
+/// emitted file.
+const RUNTIME: &str = include_str!("runtime.s");
+
+/// The registers that the first integer arguments of a call are passed in.
+const ARGUMENT_REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+
+/// Translates a type checked program into x86-64 assembly for the GNU assembler, using Intel
+/// syntax. Functions follow the System V calling convention, and the top level statements become
+/// `main`, so the result can be linked against libc.
+///
+/// Expressions are evaluated in to `rax`, with intermediate values kept on the stack.
+pub fn emit_x86_64(typed_ast: &[TypedAstNode]) -> Result<String, BackendError> {
+    let mut instances = Instances::new(functions(typed_ast));
+    let mut data = Data::default();
+
+    let mut main = FunctionEmitter::new(HashMap::new());
+    for node in typed_ast {
+        match node {
+            TypedAstNode::Let(_) | TypedAstNode::Expression(_) => {
+                main.emit_statement(&mut instances, &mut data, node)?
+            }
+            TypedAstNode::Function(_) => (),
+        }
+    }
+    main.line("xor eax, eax");
+
+    let mut output = String::from("    .intel_syntax noprefix\n\n");
+    output.push_str(RUNTIME);
+    output.push_str("\n    .text\n");
+
+    // Emitting a function may instantiate further functions, which are then emitted in turn
+    while let Some(instance) = instances.next_pending() {
+        let name = instance.name.clone();
+        let function = emit_function(&mut instances, &mut data, instance)?;
+
+        output.push_str(&function.finish(&name));
+    }
+
+    output.push_str("\n    .globl main\n");
+    output.push_str(&main.finish("main"));
+
+    output.push_str("\n    .section .rodata\n");
+    output.push_str(&data.output);
+    output.push_str("\n    .section .note.GNU-stack,\"\",@progbits\n");
+
+    Ok(output)
+}
+
+/// Assembles and links the output of [emit_x86_64] into an executable, using the system's
+/// assembler and C compiler.
+pub fn link_x86_64(assembly: &str, output: &Path) -> io::Result<()> {
+    let mut object = output.as_os_str().to_owned();
+    object.push(".o");
+
+    let mut assembler = Command::new("as")
+        .args(["--64", "-o"])
+        .arg(&object)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    assembler
+        .stdin
+        .take()
+        .expect("stdin to be piped")
+        .write_all(assembly.as_bytes())?;
+    check_tool("as", assembler.wait_with_output()?)?;
+
+    let linked = Command::new("cc")
+        .arg("-o")
+        .arg(output)
+        .arg(&object)
+        .output();
+    fs::remove_file(&object)?;
+
+    check_tool("cc", linked?)
+}
+
+/// Turns the failure of an external tool into an error that includes what it reported.
+fn check_tool(tool: &str, output: std::process::Output) -> io::Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+
+    Err(io::Error::other(format!(
+        "{tool} failed ({}): {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    )))
+}
+
+/// Read only data referred to by the emitted code, such as string literals and the positions
+/// used when reporting runtime errors.
+#[derive(Default)]
+struct Data {
+    output: String,
+    positions: HashMap<String, String>,
+    labels: usize,
+}
+impl Data {
+    /// A local label that is unique within the file, for both code and data.
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!(".L{}", self.labels)
+    }
+
+    /// The label of a string literal, laid out as its length followed by its bytes.
+    fn string(&mut self, string: &str) -> String {
+        let label = self.label();
+        self.output.push_str(&format!(
+            "    .p2align 3\n{label}:\n    .quad {}\n    .ascii {}\n",
+            string.len(),
+            quote(string)
+        ));
+
+        label
+    }
+
+    /// The label of the null terminated position of a span, for reporting runtime errors.
+    fn position(&mut self, span: &Span) -> String {
+        let position = span.to_string();
+
+        if let Some(label) = self.positions.get(&position) {
+            return label.clone();
+        }
+
+        let label = self.label();
+        self.output
+            .push_str(&format!("{label}:\n    .asciz {}\n", quote(&position)));
+        self.positions.insert(position, label.clone());
+
+        label
+    }
+}
+
+/// Emits an instance of a function, moving each of its parameters in to a local.
+fn emit_function(
+    instances: &mut Instances,
+    data: &mut Data,
+    instance: Instance,
+) -> Result<FunctionEmitter, BackendError> {
+    let function = instance.function;
+    let mut emitter = FunctionEmitter::new(instance.bindings);
+
+    emitter.resolve(&function.return_type, &function.span)?;
+
+    for (i, (ident, ty)) in function.parameters.iter().enumerate() {
+        emitter.resolve(ty, &function.span)?;
+        let local = emitter.declare(ident);
+
+        match ARGUMENT_REGISTERS.get(i) {
+            Some(register) => emitter.line(&format!("mov {local}, {register}")),
+            None => {
+                // Above the saved frame pointer and return address
+                let offset = 16 + 8 * (i - ARGUMENT_REGISTERS.len());
+                emitter.line(&format!("mov rax, qword ptr [rbp + {offset}]"));
+                emitter.line(&format!("mov {local}, rax"));
+            }
+        }
+    }
+
+    emitter.emit_block(instances, data, &function.body)?;
+
+    Ok(emitter)
+}
+
+/// Emits the body of a single function instance. Every local has its own slot below the frame
+/// pointer.
+struct FunctionEmitter {
+    body: String,
+    /// The ident and slot of each local in scope, in the order that they were declared.
+    locals: Vec<(String, usize)>,
+    /// The type that each of the function's type parameters is instantiated with.
+    bindings: HashMap<String, Type>,
+    /// The number of slots required by the function.
+    slots: usize,
+}
+impl FunctionEmitter {
+    fn new(bindings: HashMap<String, Type>) -> Self {
+        Self {
+            body: String::new(),
+            locals: Vec::new(),
+            bindings,
+            slots: 0,
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        self.body.push_str("    ");
+        self.body.push_str(line);
+        self.body.push('\n');
+    }
+
+    /// Wraps the body in a prologue and epilogue, returning whatever is left in `rax`.
+    fn finish(self, name: &str) -> String {
+        let mut output = format!("{name}:\n    push rbp\n    mov rbp, rsp\n");
+
+        if self.slots > 0 {
+            output.push_str(&format!("    sub rsp, {}\n", 8 * self.slots));
+        }
+
+        output.push_str(&self.body);
+        output.push_str("    mov rsp, rbp\n    pop rbp\n    ret\n");
+
+        output
+    }
+
+    /// Brings a local in to scope, returning the memory operand of its slot.
+    fn declare(&mut self, ident: &str) -> String {
+        let slot = self.slots;
+        self.slots += 1;
+
+        self.locals.push((ident.to_string(), slot));
+
+        slot_operand(slot)
+    }
+
+    /// Resolves the concrete type of a value within this instance, rejecting any that can't be
+    /// represented.
+    fn resolve(&self, ty: &Type, span: &Span) -> Result<Type, BackendError> {
+        match substitute(ty, &self.bindings) {
+            Type::Named { .. } => Err(unsupported("structs and enums", span)),
+            Type::Parameter(_) | Type::Unknown => {
+                unreachable!("type checker and instantiation to resolve every type")
+            }
+            ty => Ok(ty),
+        }
+    }
+
+    /// Jumps to a runtime error handler if the previous instruction overflowed.
+    fn check_overflow(&mut self, data: &mut Data, span: &Span) {
+        let ok = data.label();
+
+        self.line(&format!("jno {ok}"));
+        self.line(&format!("lea rdi, [rip + {}]", data.position(span)));
+        self.line("jmp lang_panic_overflow");
+        self.body.push_str(&format!("{ok}:\n"));
+    }
+
+    fn emit_statement(
+        &mut self,
+        instances: &mut Instances,
+        data: &mut Data,
+        statement: &TypedAstNode,
+    ) -> Result<(), BackendError> {
+        match statement {
+            TypedAstNode::Let(let_node) => {
+                self.emit_expression(instances, data, &let_node.rhs)?;
+                self.resolve(&let_node.rhs.ty, &let_node.rhs.span)?;
+
+                let local = self.declare(&let_node.ident);
+                self.line(&format!("mov {local}, rax"));
+            }
+            TypedAstNode::Expression(expression) => {
+                self.emit_expression(instances, data, expression)?;
+            }
+            // Functions can only be declared at the top level
+            TypedAstNode::Function(_) => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Emits each of the statements in a block, leaving the value it evaluates to in `rax`.
+    fn emit_block(
+        &mut self,
+        instances: &mut Instances,
+        data: &mut Data,
+        block: &TypedBlock,
+    ) -> Result<(), BackendError> {
+        let scope = self.locals.len();
+
+        for statement in &block.statements {
+            self.emit_statement(instances, data, statement)?;
+        }
+
+        match &block.expression {
+            Some(expression) => self.emit_expression(instances, data, expression)?,
+            None => self.line("xor eax, eax"),
+        }
+
+        self.locals.truncate(scope);
+
+        Ok(())
+    }
+
+    /// Emits the instructions to evaluate an expression, leaving the result in `rax`.
+    fn emit_expression(
+        &mut self,
+        instances: &mut Instances,
+        data: &mut Data,
+        expression: &TypedExpression,
+    ) -> Result<(), BackendError> {
+        let span = &expression.span;
+
+        match &expression.kind {
+            TypedExpressionKind::Ident(ident) => {
+                let slot = self
+                    .locals
+                    .iter()
+                    .rfind(|(local, _)| local == ident)
+                    .map(|(_, slot)| *slot)
+                    .expect("type checker to reject unknown idents");
+
+                self.line(&format!("mov rax, {}", slot_operand(slot)));
+            }
+            TypedExpressionKind::Literal(literal) => match literal {
+                Literal::Integer(integer) => self.line(&format!("mov rax, {integer}")),
+                Literal::Boolean(boolean) => self.line(&format!("mov eax, {}", *boolean as u8)),
+                Literal::String(string) => {
+                    let label = data.string(string);
+                    self.line(&format!("lea rax, [rip + {label}]"));
+                }
+            },
+            TypedExpressionKind::BinaryOperation {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                self.emit_expression(instances, data, lhs)?;
+                self.line("push rax");
+                self.emit_expression(instances, data, rhs)?;
+                self.line("mov rcx, rax");
+                self.line("pop rax");
+
+                let helper = match (operation, self.resolve(&expression.ty, span)?) {
+                    (BinaryOperationKind::Add, Type::String) => "lang_concat",
+                    (_, ty) if ty != Type::Integer => {
+                        return Err(unsupported("operators on values other than integers", span))
+                    }
+                    (BinaryOperationKind::Div, _) => "lang_div",
+                    (BinaryOperationKind::Mod, _) => "lang_rem",
+                    (BinaryOperationKind::Exp, _) => "lang_pow",
+                    (BinaryOperationKind::Shl, _) => "lang_shl",
+                    (BinaryOperationKind::Shr, _) => "lang_shr",
+                    // These can't overflow
+                    (
+                        operation @ (BinaryOperationKind::And
+                        | BinaryOperationKind::Or
+                        | BinaryOperationKind::Xor),
+                        _,
+                    ) => {
+                        self.line(match operation {
+                            BinaryOperationKind::And => "and rax, rcx",
+                            BinaryOperationKind::Or => "or rax, rcx",
+                            _ => "xor rax, rcx",
+                        });
+
+                        return Ok(());
+                    }
+                    (operation, _) => {
+                        self.line(match operation {
+                            BinaryOperationKind::Add => "add rax, rcx",
+                            BinaryOperationKind::Sub => "sub rax, rcx",
+                            _ => "imul rax, rcx",
+                        });
+                        self.check_overflow(data, span);
+
+                        return Ok(());
+                    }
+                };
+
+                self.line("mov rdi, rax");
+                self.line("mov rsi, rcx");
+                self.line(&format!("lea rdx, [rip + {}]", data.position(span)));
+                self.line(&format!("call {helper}"));
+            }
+            TypedExpressionKind::UnaryOperation { operation, rhs } => {
+                if self.resolve(&rhs.ty, span)? != Type::Integer {
+                    return Err(unsupported("operators on values other than integers", span));
+                }
+                self.emit_expression(instances, data, rhs)?;
+
+                match operation {
+                    UnaryOperationKind::Negative => {
+                        self.line("neg rax");
+                        self.check_overflow(data, span);
+                    }
+                    UnaryOperationKind::Complement => self.line("not rax"),
+                }
+            }
+            TypedExpressionKind::Call {
+                ident,
+                type_arguments,
+                arguments,
+            } => {
+                if !instances.is_function(ident) && ident == "print" {
+                    let argument = &arguments[0];
+                    self.emit_expression(instances, data, argument)?;
+
+                    let helper = match self.resolve(&argument.ty, &argument.span)? {
+                        Type::Integer => "lang_print_integer",
+                        Type::Boolean => "lang_print_boolean",
+                        Type::String => "lang_print_string",
+                        _ => "lang_print_unit",
+                    };
+                    self.line("mov rdi, rax");
+                    self.line(&format!("call {helper}"));
+                    self.line("xor eax, eax");
+
+                    return Ok(());
+                }
+
+                // Host functions are only available when running on the virtual machine
+                if !instances.is_function(ident) {
+                    return Err(unsupported("host functions", span));
+                }
+
+                for argument in arguments {
+                    self.emit_expression(instances, data, argument)?;
+                    self.line("push rax");
+                }
+
+                // Arguments were pushed in order, so the last is on top of the stack
+                let count = arguments.len();
+                for (i, register) in ARGUMENT_REGISTERS.iter().enumerate().take(count) {
+                    self.line(&format!(
+                        "mov {register}, qword ptr [rsp + {}]",
+                        8 * (count - 1 - i)
+                    ));
+                }
+
+                // Any remaining arguments are pushed again in reverse, so the first is on top
+                let stack_arguments = count.saturating_sub(ARGUMENT_REGISTERS.len());
+                for (pushed, i) in (ARGUMENT_REGISTERS.len()..count).rev().enumerate() {
+                    self.line(&format!(
+                        "push qword ptr [rsp + {}]",
+                        8 * (count - 1 - i + pushed)
+                    ));
+                }
+
+                let name = instances.instance(
+                    ident,
+                    type_arguments
+                        .iter()
+                        .map(|ty| self.resolve(ty, span))
+                        .collect::<Result<_, _>>()?,
+                );
+                self.line(&format!("call {name}"));
+
+                if count + stack_arguments > 0 {
+                    self.line(&format!("add rsp, {}", 8 * (count + stack_arguments)));
+                }
+            }
+            TypedExpressionKind::Variant { .. } | TypedExpressionKind::Struct { .. } => {
+                return Err(unsupported("structs and enums", span))
+            }
+            TypedExpressionKind::Interpolation(parts) => {
+                let position = data.position(span);
+                let empty = data.string("");
+                self.line(&format!("lea rax, [rip + {empty}]"));
+
+                for part in parts {
+                    self.line("push rax");
+                    self.emit_expression(instances, data, part)?;
+
+                    match self.resolve(&part.ty, &part.span)? {
+                        Type::Integer => {
+                            self.line("mov rdi, rax");
+                            self.line(&format!("lea rsi, [rip + {position}]"));
+                            self.line("call lang_integer_string");
+                        }
+                        Type::Boolean => {
+                            self.line("mov rdi, rax");
+                            self.line("call lang_boolean_string");
+                        }
+                        _ => (),
+                    }
+
+                    self.line("mov rsi, rax");
+                    self.line("pop rdi");
+                    self.line(&format!("lea rdx, [rip + {position}]"));
+                    self.line("call lang_concat");
+                }
+            }
+            TypedExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let otherwise_label = data.label();
+                let end = data.label();
+
+                self.emit_expression(instances, data, condition)?;
+                self.line("test rax, rax");
+                self.line(&format!("jz {otherwise_label}"));
+
+                self.emit_block(instances, data, then)?;
+                self.line(&format!("jmp {end}"));
+
+                self.body.push_str(&format!("{otherwise_label}:\n"));
+                match otherwise {
+                    Some(otherwise) => self.emit_block(instances, data, otherwise)?,
+                    None => self.line("xor eax, eax"),
+                }
+                self.body.push_str(&format!("{end}:\n"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The memory operand of a local's slot.
+fn slot_operand(slot: usize) -> String {
+    format!("qword ptr [rbp - {}]", 8 * (slot + 1))
+}
+
+fn unsupported(feature: &'static str, span: &Span) -> BackendError {
+    BackendError::new(BackendErrorKind::Unsupported(feature, "x86-64"), span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    #[test]
+    fn matches_interpreter() {
+        crate::backend::tests::assert_matches_interpreter("x86-64", |typed_ast, binary_path| {
+            link_x86_64(&emit_x86_64(typed_ast).unwrap(), binary_path).unwrap();
+        });
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use crate::{
+    checks::typing::{
+        TypedAstNode, TypedBlock, TypedExpression, TypedExpressionKind, TypedFunction,
+    },
+    lexer::cursor::Span,
+    parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+    token::Literal,
+};
+
+use super::{Chunk, Import, Instruction, Layout, Program, Value};
+
+/// Compiles a type checked program into bytecode. Each function is compiled into its own chunk,
+/// and the top level statements are compiled into a final entry chunk.
+pub fn compile(typed_ast: &[TypedAstNode]) -> Program {
+    let mut compiler = Compiler::default();
+
+    // Allocate a chunk index for each function up front, so they can be called before they appear
+    for node in typed_ast {
+        if let TypedAstNode::Function(function) = node {
+            compiler
+                .functions
+                .insert(function.ident.clone(), compiler.functions.len());
+        }
+    }
+
+    let mut chunks = typed_ast
+        .iter()
+        .filter_map(|node| match node {
+            TypedAstNode::Function(function) => Some(compiler.compile_function(function)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut entry = FunctionCompiler::new(Chunk::new("<main>".to_string(), 0), Vec::new());
+    for node in typed_ast {
+        match node {
+            TypedAstNode::Let(_) | TypedAstNode::Expression(_) => {
+                entry.compile_statement(&mut compiler, node)
+            }
+            TypedAstNode::Function(_) => (),
+        }
+    }
+    entry.chunk.push(Instruction::Unit, Span::default());
+    entry.chunk.push(Instruction::Return, Span::default());
+
+    chunks.push(entry.chunk);
+
+    Program {
+        entry: chunks.len() - 1,
+        chunks,
+        layouts: compiler.layouts,
+        imports: compiler.imports,
+    }
+}
+
+/// State shared whilst compiling every chunk in a program.
+#[derive(Default)]
+struct Compiler {
+    /// The chunk index of each function.
+    functions: HashMap<String, usize>,
+    layouts: Vec<Layout>,
+    /// Functions that aren't declared in the program, so must be provided by the host.
+    imports: Vec<Import>,
+}
+impl Compiler {
+    fn compile_function(&mut self, function: &TypedFunction) -> Chunk {
+        let mut function_compiler = FunctionCompiler::new(
+            Chunk::new(function.ident.clone(), function.parameters.len()),
+            function
+                .parameters
+                .iter()
+                .map(|(ident, _)| Some(ident.clone()))
+                .collect(),
+        );
+
+        function_compiler.compile_block(self, &function.body);
+        function_compiler
+            .chunk
+            .push(Instruction::Return, Span::default());
+
+        function_compiler.chunk
+    }
+
+    /// Finds the index of a layout, adding it if it hasn't been used before.
+    fn layout(&mut self, layout: Layout) -> usize {
+        self.layouts
+            .iter()
+            .position(|existing| *existing == layout)
+            .unwrap_or_else(|| {
+                self.layouts.push(layout);
+                self.layouts.len() - 1
+            })
+    }
+
+    /// Finds the index of a host function import, adding it if it hasn't been called before.
+    fn import(&mut self, name: &str, arity: usize) -> usize {
+        self.imports
+            .iter()
+            .position(|import| import.name == name)
+            .unwrap_or_else(|| {
+                self.imports.push(Import {
+                    name: name.to_string(),
+                    arity,
+                });
+                self.imports.len() - 1
+            })
+    }
+}
+
+/// Compiles the body of a single function into a chunk. Locals live on the stack in the order
+/// they were declared, starting with the parameters.
+struct FunctionCompiler {
+    chunk: Chunk,
+    /// What each slot of the stack holds. A slot without an ident holds a temporary, such as the
+    /// lhs of an operation whilst its rhs is evaluated, which a local declared within the rhs is
+    /// placed after.
+    locals: Vec<Option<String>>,
+}
+impl FunctionCompiler {
+    fn new(chunk: Chunk, locals: Vec<Option<String>>) -> Self {
+        Self { chunk, locals }
+    }
+
+    /// Compiles each expression, leaving all of their values on the stack.
+    fn compile_operands<'a>(
+        &mut self,
+        compiler: &mut Compiler,
+        operands: impl IntoIterator<Item = &'a TypedExpression>,
+    ) {
+        let temporaries = self.locals.len();
+        for operand in operands {
+            self.compile_expression(compiler, operand);
+            self.locals.push(None);
+        }
+
+        self.locals.truncate(temporaries);
+    }
+
+    fn compile_statement(&mut self, compiler: &mut Compiler, statement: &TypedAstNode) {
+        match statement {
+            TypedAstNode::Let(let_node) => {
+                // The value is left on the stack, where it becomes the local's slot
+                self.compile_expression(compiler, &let_node.rhs);
+                self.locals.push(Some(let_node.ident.clone()));
+            }
+            TypedAstNode::Expression(expression) => {
+                self.compile_expression(compiler, expression);
+                self.chunk.push(Instruction::Pop, expression.span.clone());
+            }
+            // Functions can only be declared at the top level
+            TypedAstNode::Function(_) => unreachable!(),
+        }
+    }
+
+    /// Compiles one of the branches of an `if`. Any locals declared within the branch are dropped
+    /// at the end of it, leaving only the value it evaluates to on the stack.
+    fn compile_branch(&mut self, compiler: &mut Compiler, block: &TypedBlock) {
+        let locals = self.locals.len();
+        self.compile_block(compiler, block);
+
+        let declared = self.locals.len() - locals;
+        if declared > 0 {
+            self.chunk
+                .push(Instruction::Drop(declared), Span::default());
+            self.locals.truncate(locals);
+        }
+    }
+
+    /// Replaces the target of a previously emitted jump with the current end of the chunk.
+    fn patch_jump(&mut self, jump: usize) {
+        let target = self.chunk.code.len();
+
+        match &mut self.chunk.code[jump] {
+            Instruction::Jump(offset) | Instruction::JumpIfFalse(offset) => *offset = target,
+            _ => unreachable!("only jumps to be patched"),
+        }
+    }
+
+    /// Compiles a block, leaving the value it evaluates to on the stack.
+    fn compile_block(&mut self, compiler: &mut Compiler, block: &TypedBlock) {
+        for statement in &block.statements {
+            self.compile_statement(compiler, statement);
+        }
+
+        match &block.expression {
+            Some(expression) => self.compile_expression(compiler, expression),
+            None => self.chunk.push(Instruction::Unit, Span::default()),
+        }
+    }
+
+    fn compile_expression(&mut self, compiler: &mut Compiler, expression: &TypedExpression) {
+        let span = expression.span.clone();
+
+        match &expression.kind {
+            TypedExpressionKind::Ident(ident) => {
+                let slot = self
+                    .locals
+                    .iter()
+                    .rposition(|local| local.as_ref() == Some(ident))
+                    .expect("type checker to reject unknown idents");
+
+                self.chunk.push(Instruction::GetLocal(slot), span);
+            }
+            TypedExpressionKind::BinaryOperation {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                self.compile_operands(compiler, [lhs.as_ref(), rhs.as_ref()]);
+
+                self.chunk.push(
+                    match operation {
+                        BinaryOperationKind::Add => Instruction::Add,
+                        BinaryOperationKind::Sub => Instruction::Sub,
+                        BinaryOperationKind::Mult => Instruction::Mult,
+                        BinaryOperationKind::Div => Instruction::Div,
+                        BinaryOperationKind::Mod => Instruction::Mod,
+                        BinaryOperationKind::Exp => Instruction::Exp,
+                        BinaryOperationKind::And => Instruction::And,
+                        BinaryOperationKind::Or => Instruction::Or,
+                        BinaryOperationKind::Xor => Instruction::Xor,
+                        BinaryOperationKind::Shl => Instruction::Shl,
+                        BinaryOperationKind::Shr => Instruction::Shr,
+                    },
+                    span,
+                );
+            }
+            TypedExpressionKind::UnaryOperation { operation, rhs } => {
+                self.compile_expression(compiler, rhs);
+
+                self.chunk.push(
+                    match operation {
+                        UnaryOperationKind::Negative => Instruction::Negate,
+                        UnaryOperationKind::Complement => Instruction::Complement,
+                    },
+                    span,
+                );
+            }
+            TypedExpressionKind::Literal(literal) => {
+                let constant = self.chunk.add_constant(match literal {
+                    Literal::Integer(integer) => Value::Integer(*integer),
+                    Literal::Boolean(boolean) => Value::Boolean(*boolean),
+                    Literal::String(string) => Value::String(string.as_str().into()),
+                });
+
+                self.chunk.push(Instruction::Constant(constant), span);
+            }
+            TypedExpressionKind::Call {
+                ident, arguments, ..
+            } => {
+                self.compile_operands(compiler, arguments);
+
+                match compiler.functions.get(ident) {
+                    Some(function) => self.chunk.push(Instruction::Call(*function), span),
+                    None if ident == "print" => {
+                        self.chunk.push(Instruction::Print, span.clone());
+                        self.chunk.push(Instruction::Unit, span);
+                    }
+                    // The type checker rejects unknown functions, so any others are from the host
+                    None => {
+                        let import = compiler.import(ident, arguments.len());
+                        self.chunk.push(Instruction::CallHost(import), span);
+                    }
+                }
+            }
+            TypedExpressionKind::Variant {
+                ident,
+                variant,
+                arguments,
+            } => {
+                self.compile_operands(compiler, arguments);
+
+                let layout = compiler.layout(Layout::Variant {
+                    ident: ident.clone(),
+                    variant: variant.clone(),
+                    arity: arguments.len(),
+                });
+                self.chunk.push(Instruction::Construct(layout), span);
+            }
+            TypedExpressionKind::Struct { ident, fields } => {
+                self.compile_operands(compiler, fields.iter().map(|(_, value)| value));
+
+                let layout = compiler.layout(Layout::Struct {
+                    ident: ident.clone(),
+                    fields: fields.iter().map(|(field, _)| field.clone()).collect(),
+                });
+                self.chunk.push(Instruction::Construct(layout), span);
+            }
+            TypedExpressionKind::Interpolation(parts) => {
+                self.compile_operands(compiler, parts);
+                self.chunk.push(Instruction::Interpolate(parts.len()), span);
+            }
+            TypedExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.compile_expression(compiler, condition);
+                let to_otherwise = self.chunk.code.len();
+                self.chunk.push(Instruction::JumpIfFalse(0), span.clone());
+
+                self.compile_branch(compiler, then);
+                let to_end = self.chunk.code.len();
+                self.chunk.push(Instruction::Jump(0), span.clone());
+
+                self.patch_jump(to_otherwise);
+                match otherwise {
+                    Some(otherwise) => self.compile_branch(compiler, otherwise),
+                    None => self.chunk.push(Instruction::Unit, span),
+                }
+                self.patch_jump(to_end);
+            }
+        }
+    }
+}
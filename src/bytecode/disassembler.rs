@@ -0,0 +1,72 @@
+use std::fmt::Display;
+
+use super::{Chunk, Import, Instruction, Layout, Program};
+
+/// Writes a human readable listing of every chunk in the program, for debugging.
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            self.fmt_chunk(f, i, chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Program {
+    fn fmt_chunk(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        index: usize,
+        chunk: &Chunk,
+    ) -> std::fmt::Result {
+        writeln!(f, "== {index}: {} (arity {}) ==", chunk.name, chunk.arity)?;
+
+        for (offset, (instruction, span)) in chunk.code.iter().zip(&chunk.spans).enumerate() {
+            write!(f, "{offset:04} {:>7}  ", span.to_string())?;
+
+            match instruction {
+                Instruction::Constant(constant) => {
+                    write!(f, "Constant {constant} ({})", chunk.constants[*constant])?
+                }
+                Instruction::GetLocal(slot) => write!(f, "GetLocal {slot}")?,
+                Instruction::Drop(count) => write!(f, "Drop {count}")?,
+                Instruction::Interpolate(count) => write!(f, "Interpolate {count}")?,
+                Instruction::Jump(offset) => write!(f, "Jump {offset:04}")?,
+                Instruction::JumpIfFalse(offset) => write!(f, "JumpIfFalse {offset:04}")?,
+                Instruction::Call(callee) => {
+                    write!(f, "Call {callee} ({})", self.chunks[*callee].name)?
+                }
+                Instruction::CallHost(import) => {
+                    let Import { name, arity } = &self.imports[*import];
+                    write!(f, "CallHost {import} ({name}/{arity})")?
+                }
+                Instruction::Construct(layout) => {
+                    write!(f, "Construct {layout} (")?;
+
+                    match &self.layouts[*layout] {
+                        Layout::Struct { ident, fields } => {
+                            write!(f, "{ident} {{ {} }}", fields.join(", "))?
+                        }
+                        Layout::Variant {
+                            ident,
+                            variant,
+                            arity,
+                        } => write!(f, "{ident}::{variant}/{arity}")?,
+                    }
+
+                    write!(f, ")")?;
+                }
+                instruction => write!(f, "{instruction:?}")?,
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
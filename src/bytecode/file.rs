@@ -0,0 +1,637 @@
+//! A versioned binary file format for compiled programs, so that they can be run later without
+//! lexing, parsing or type checking the source again.
+//!
+//! All integers are little endian, and strings are a `u32` length followed by UTF-8 bytes.
+//!
+//! ```txt
+//! file     -> magic version entry layouts imports chunks checksum
+//! magic    -> "\0LBC"
+//! version  -> u16
+//! entry    -> u32
+//! layouts  -> u32 {0 string {string} | 1 string string u32}
+//! imports  -> u32 {string u32}
+//! chunks   -> u32 {string u32 constants code}
+//! constant -> 0 i64 | 1 u8 | 2 string | 3
+//! code     -> u32 {opcode [u32] span}
+//! span     -> u32 u32 u32 u32
+//! checksum -> u32
+//! ```
+//!
+//! The span of each instruction forms the debug line table, mapping it back to the source.
+
+use thiserror::Error;
+
+use crate::lexer::cursor::{Position, Span};
+
+use super::{Chunk, Import, Instruction, Layout, Program, Value};
+
+/// Identifies a file as containing compiled bytecode.
+pub const MAGIC: [u8; 4] = *b"\0LBC";
+
+/// The version of the file format. Files written with a different version are rejected.
+pub const VERSION: u16 = 3;
+
+/// All of the reasons that a bytecode file could be rejected.
+#[derive(Debug, Error)]
+pub enum BytecodeFileError {
+    #[error("not a bytecode file")]
+    InvalidMagic,
+    #[error("unsupported bytecode version {found} (expected {VERSION})")]
+    UnsupportedVersion { found: u16 },
+    #[error("unexpected end of file at byte {0}")]
+    UnexpectedEnd(usize),
+    #[error("checksum mismatch, the file is corrupt")]
+    ChecksumMismatch,
+    #[error("invalid {kind} tag {tag} at byte {offset}")]
+    InvalidTag {
+        kind: &'static str,
+        tag: u8,
+        offset: usize,
+    },
+    #[error("invalid string at byte {0}")]
+    InvalidString(usize),
+    #[error("{0} out of range")]
+    OutOfRange(&'static str),
+    #[error("instruction {offset} of chunk {chunk} doesn't have the values it needs on the stack")]
+    UnbalancedStack { chunk: usize, offset: usize },
+    #[error("{0} unexpected trailing bytes")]
+    TrailingBytes(usize),
+}
+
+/// Serialises a program into the bytecode file format.
+pub fn serialise(program: &Program) -> Vec<u8> {
+    let mut writer = Writer::default();
+
+    writer.bytes.extend(MAGIC);
+    writer.bytes.extend(VERSION.to_le_bytes());
+    writer.usize(program.entry);
+
+    writer.usize(program.layouts.len());
+    for layout in &program.layouts {
+        match layout {
+            Layout::Struct { ident, fields } => {
+                writer.u8(0);
+                writer.string(ident);
+                writer.usize(fields.len());
+                for field in fields {
+                    writer.string(field);
+                }
+            }
+            Layout::Variant {
+                ident,
+                variant,
+                arity,
+            } => {
+                writer.u8(1);
+                writer.string(ident);
+                writer.string(variant);
+                writer.usize(*arity);
+            }
+        }
+    }
+
+    writer.usize(program.imports.len());
+    for import in &program.imports {
+        writer.string(&import.name);
+        writer.usize(import.arity);
+    }
+
+    writer.usize(program.chunks.len());
+    for chunk in &program.chunks {
+        writer.string(&chunk.name);
+        writer.usize(chunk.arity);
+
+        writer.usize(chunk.constants.len());
+        for constant in &chunk.constants {
+            match constant {
+                Value::Integer(integer) => {
+                    writer.u8(0);
+                    writer.bytes.extend((*integer as i64).to_le_bytes());
+                }
+                Value::Boolean(boolean) => {
+                    writer.u8(1);
+                    writer.u8(*boolean as u8);
+                }
+                Value::String(string) => {
+                    writer.u8(2);
+                    writer.string(string);
+                }
+                Value::Unit => writer.u8(3),
+                Value::Struct { .. } | Value::Variant { .. } => {
+                    unreachable!("compiler to only emit scalar constants")
+                }
+            }
+        }
+
+        writer.usize(chunk.code.len());
+        for (instruction, span) in chunk.code.iter().zip(&chunk.spans) {
+            let (opcode, operand) = encode(instruction);
+            writer.u8(opcode);
+            if let Some(operand) = operand {
+                writer.usize(operand);
+            }
+
+            for position in [&span.start, &span.end] {
+                writer.usize(position.line());
+                writer.usize(position.character());
+            }
+        }
+    }
+
+    let checksum = checksum(&writer.bytes);
+    writer.bytes.extend(checksum.to_le_bytes());
+
+    writer.bytes
+}
+
+/// Loads a program from the bytecode file format, validating that it is well formed and can be
+/// safely executed.
+pub fn deserialise(bytes: &[u8]) -> Result<Program, BytecodeFileError> {
+    if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+        return Err(BytecodeFileError::InvalidMagic);
+    }
+
+    let mut reader = Reader {
+        bytes,
+        offset: MAGIC.len(),
+    };
+
+    let version = u16::from_le_bytes(reader.array()?);
+    if version != VERSION {
+        return Err(BytecodeFileError::UnsupportedVersion { found: version });
+    }
+
+    // Everything but the trailing checksum is covered by it
+    let Some(body_length) = bytes.len().checked_sub(4) else {
+        return Err(BytecodeFileError::UnexpectedEnd(bytes.len()));
+    };
+    if body_length < reader.offset
+        || checksum(&bytes[..body_length]).to_le_bytes() != bytes[body_length..]
+    {
+        return Err(BytecodeFileError::ChecksumMismatch);
+    }
+    reader.bytes = &bytes[..body_length];
+
+    let entry = reader.usize()?;
+
+    let layouts = (0..reader.usize()?)
+        .map(|_| {
+            let offset = reader.offset;
+
+            Ok(match reader.u8()? {
+                0 => Layout::Struct {
+                    ident: reader.string()?,
+                    fields: (0..reader.usize()?)
+                        .map(|_| reader.string())
+                        .collect::<Result<_, _>>()?,
+                },
+                1 => Layout::Variant {
+                    ident: reader.string()?,
+                    variant: reader.string()?,
+                    arity: reader.usize()?,
+                },
+                tag => {
+                    return Err(BytecodeFileError::InvalidTag {
+                        kind: "layout",
+                        tag,
+                        offset,
+                    })
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let imports = (0..reader.usize()?)
+        .map(|_| {
+            Ok(Import {
+                name: reader.string()?,
+                arity: reader.usize()?,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let chunks = (0..reader.usize()?)
+        .map(|_| {
+            let mut chunk = Chunk::new(reader.string()?, reader.usize()?);
+
+            for _ in 0..reader.usize()? {
+                let offset = reader.offset;
+
+                chunk.constants.push(match reader.u8()? {
+                    0 => Value::Integer(i64::from_le_bytes(reader.array()?) as isize),
+                    1 => Value::Boolean(reader.u8()? != 0),
+                    2 => Value::String(reader.string()?.into()),
+                    3 => Value::Unit,
+                    tag => {
+                        return Err(BytecodeFileError::InvalidTag {
+                            kind: "constant",
+                            tag,
+                            offset,
+                        })
+                    }
+                });
+            }
+
+            for _ in 0..reader.usize()? {
+                let instruction = reader.instruction()?;
+
+                let mut positions = [Position::new(), Position::new()];
+                for position in &mut positions {
+                    *position = Position::at(reader.usize()?, reader.usize()?);
+                }
+                let [start, end] = positions;
+
+                chunk.push(instruction, Span::new(start, end));
+            }
+
+            Ok(chunk)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if reader.offset != reader.bytes.len() {
+        return Err(BytecodeFileError::TrailingBytes(
+            reader.bytes.len() - reader.offset,
+        ));
+    }
+
+    let program = Program {
+        chunks,
+        layouts,
+        imports,
+        entry,
+    };
+    validate(&program)?;
+
+    Ok(program)
+}
+
+/// Makes sure that every index within the program refers to something that exists, and that every
+/// instruction has the values it needs on the stack, so that the virtual machine won't panic whilst
+/// executing it.
+fn validate(program: &Program) -> Result<(), BytecodeFileError> {
+    let Some(entry) = program.chunks.get(program.entry) else {
+        return Err(BytecodeFileError::OutOfRange("entry chunk"));
+    };
+    if entry.arity != 0 {
+        return Err(BytecodeFileError::OutOfRange("entry chunk arity"));
+    }
+
+    for chunk in &program.chunks {
+        // Every chunk must finish by returning, otherwise execution would run off the end
+        if chunk.code.last() != Some(&Instruction::Return) {
+            return Err(BytecodeFileError::OutOfRange("chunk end"));
+        }
+
+        for instruction in &chunk.code {
+            match instruction {
+                Instruction::Constant(constant) if *constant >= chunk.constants.len() => {
+                    return Err(BytecodeFileError::OutOfRange("constant"))
+                }
+                Instruction::Call(callee) if *callee >= program.chunks.len() => {
+                    return Err(BytecodeFileError::OutOfRange("call"))
+                }
+                Instruction::CallHost(import) if *import >= program.imports.len() => {
+                    return Err(BytecodeFileError::OutOfRange("host import"))
+                }
+                Instruction::Construct(layout) if *layout >= program.layouts.len() => {
+                    return Err(BytecodeFileError::OutOfRange("layout"))
+                }
+                Instruction::Jump(offset) | Instruction::JumpIfFalse(offset)
+                    if *offset >= chunk.code.len() =>
+                {
+                    return Err(BytecodeFileError::OutOfRange("jump"))
+                }
+                _ => (),
+            }
+        }
+    }
+
+    for (index, chunk) in program.chunks.iter().enumerate() {
+        validate_stack(program, index, chunk)?;
+    }
+
+    Ok(())
+}
+
+/// Follows every path through a chunk, tracking how many values are on the stack of its frame
+/// before each instruction. Every instruction must only use values within the frame, and each
+/// instruction must be reached with the same number of values along every path. Indices are
+/// already known to be in range.
+fn validate_stack(program: &Program, index: usize, chunk: &Chunk) -> Result<(), BytecodeFileError> {
+    let mut depths = vec![None; chunk.code.len()];
+    // The arguments are the first values in the frame
+    let mut pending = vec![(0, chunk.arity)];
+
+    while let Some((offset, depth)) = pending.pop() {
+        let unbalanced = BytecodeFileError::UnbalancedStack {
+            chunk: index,
+            offset,
+        };
+
+        match depths[offset] {
+            Some(existing) if existing == depth => continue,
+            Some(_) => return Err(unbalanced),
+            None => depths[offset] = Some(depth),
+        }
+
+        // How many values the instruction takes from the top of the stack, and how many it
+        // leaves in their place
+        let (taken, pushed) = match chunk.code[offset] {
+            Instruction::Constant(_) | Instruction::Unit => (0, 1),
+            Instruction::GetLocal(slot) if slot >= depth => {
+                return Err(BytecodeFileError::OutOfRange("local"))
+            }
+            Instruction::GetLocal(_) => (0, 1),
+            Instruction::Pop | Instruction::Print | Instruction::JumpIfFalse(_) => (1, 0),
+            Instruction::Drop(count) => (count + 1, 1),
+            Instruction::Jump(_) => (0, 0),
+            Instruction::Add
+            | Instruction::Sub
+            | Instruction::Mult
+            | Instruction::Div
+            | Instruction::Mod
+            | Instruction::Exp
+            | Instruction::And
+            | Instruction::Or
+            | Instruction::Xor
+            | Instruction::Shl
+            | Instruction::Shr => (2, 1),
+            Instruction::Negate | Instruction::Complement | Instruction::Return => (1, 1),
+            Instruction::Call(callee) => (program.chunks[callee].arity, 1),
+            Instruction::CallHost(import) => (program.imports[import].arity, 1),
+            Instruction::Construct(layout) => (program.layouts[layout].arity(), 1),
+            Instruction::Interpolate(count) => (count, 1),
+        };
+        if taken > depth {
+            return Err(unbalanced);
+        }
+        let depth = depth - taken + pushed;
+
+        match chunk.code[offset] {
+            Instruction::Return => (),
+            Instruction::Jump(target) => pending.push((target, depth)),
+            Instruction::JumpIfFalse(target) => {
+                pending.push((target, depth));
+                pending.push((offset + 1, depth));
+            }
+            // Every chunk ends with a return, so there is always a following instruction
+            _ => pending.push((offset + 1, depth)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits an instruction into its opcode and operand.
+fn encode(instruction: &Instruction) -> (u8, Option<usize>) {
+    match instruction {
+        Instruction::Constant(constant) => (0, Some(*constant)),
+        Instruction::Unit => (1, None),
+        Instruction::GetLocal(slot) => (2, Some(*slot)),
+        Instruction::Pop => (3, None),
+        Instruction::Add => (4, None),
+        Instruction::Sub => (5, None),
+        Instruction::Mult => (6, None),
+        Instruction::Div => (7, None),
+        Instruction::Exp => (8, None),
+        Instruction::Negate => (9, None),
+        Instruction::Call(callee) => (10, Some(*callee)),
+        Instruction::Construct(layout) => (11, Some(*layout)),
+        Instruction::Print => (12, None),
+        Instruction::Return => (13, None),
+        Instruction::Drop(count) => (14, Some(*count)),
+        Instruction::Jump(offset) => (15, Some(*offset)),
+        Instruction::JumpIfFalse(offset) => (16, Some(*offset)),
+        Instruction::CallHost(import) => (17, Some(*import)),
+        Instruction::Mod => (18, None),
+        Instruction::And => (19, None),
+        Instruction::Or => (20, None),
+        Instruction::Xor => (21, None),
+        Instruction::Shl => (22, None),
+        Instruction::Shr => (23, None),
+        Instruction::Complement => (24, None),
+        Instruction::Interpolate(count) => (25, Some(*count)),
+    }
+}
+
+/// A 32 bit FNV-1a hash, used to detect corruption.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn usize(&mut self, value: usize) {
+        let value = u32::try_from(value).expect("program to fit within the file format");
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn string(&mut self, string: &str) {
+        self.usize(string.len());
+        self.bytes.extend(string.as_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8], BytecodeFileError> {
+        let bytes = self
+            .offset
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or(BytecodeFileError::UnexpectedEnd(self.offset))?;
+        self.offset += length;
+
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BytecodeFileError> {
+        Ok(self.take(N)?.try_into().expect("slice to have length N"))
+    }
+
+    fn u8(&mut self) -> Result<u8, BytecodeFileError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn usize(&mut self) -> Result<usize, BytecodeFileError> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn string(&mut self) -> Result<String, BytecodeFileError> {
+        let offset = self.offset;
+        let length = self.usize()?;
+
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| BytecodeFileError::InvalidString(offset))
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, BytecodeFileError> {
+        let offset = self.offset;
+
+        Ok(match self.u8()? {
+            0 => Instruction::Constant(self.usize()?),
+            1 => Instruction::Unit,
+            2 => Instruction::GetLocal(self.usize()?),
+            3 => Instruction::Pop,
+            4 => Instruction::Add,
+            5 => Instruction::Sub,
+            6 => Instruction::Mult,
+            7 => Instruction::Div,
+            8 => Instruction::Exp,
+            9 => Instruction::Negate,
+            10 => Instruction::Call(self.usize()?),
+            11 => Instruction::Construct(self.usize()?),
+            12 => Instruction::Print,
+            13 => Instruction::Return,
+            14 => Instruction::Drop(self.usize()?),
+            15 => Instruction::Jump(self.usize()?),
+            16 => Instruction::JumpIfFalse(self.usize()?),
+            17 => Instruction::CallHost(self.usize()?),
+            18 => Instruction::Mod,
+            19 => Instruction::And,
+            20 => Instruction::Or,
+            21 => Instruction::Xor,
+            22 => Instruction::Shl,
+            23 => Instruction::Shr,
+            24 => Instruction::Complement,
+            25 => Instruction::Interpolate(self.usize()?),
+            tag => {
+                return Err(BytecodeFileError::InvalidTag {
+                    kind: "instruction",
+                    tag,
+                    offset,
+                })
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bytecode::compile, front_end};
+
+    fn program() -> Program {
+        let (_, typed_ast) = front_end(
+            "enum Option<T> { Some(T), None }
+            fn double(x: Integer) -> Integer { x * 2 }
+            let a = if true { let b = 21; double(b) } else { 0 };
+            print(Option::Some(a));
+            print(\"done\");",
+        )
+        .unwrap();
+
+        compile(&typed_ast)
+    }
+
+    /// The program, with the code of its entry chunk replaced.
+    fn with_entry(code: Vec<Instruction>) -> Program {
+        let mut program = program();
+        let entry = &mut program.chunks[program.entry];
+        entry.spans = vec![Span::default(); code.len()];
+        entry.code = code;
+
+        program
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = program();
+
+        assert_eq!(
+            deserialise(&serialise(&program)).unwrap().to_string(),
+            program.to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let bytes = serialise(&program());
+
+        assert!(matches!(
+            deserialise(b"not bytecode"),
+            Err(BytecodeFileError::InvalidMagic)
+        ));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 99;
+        assert!(matches!(
+            deserialise(&wrong_version),
+            Err(BytecodeFileError::UnsupportedVersion { found: 99 })
+        ));
+
+        let mut corrupt = bytes.clone();
+        corrupt[20] ^= 0xff;
+        assert!(matches!(
+            deserialise(&corrupt),
+            Err(BytecodeFileError::ChecksumMismatch)
+        ));
+
+        assert!(matches!(
+            deserialise(&bytes[..bytes.len() - 10]),
+            Err(BytecodeFileError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        let mut program = program();
+        program.chunks[0].code[0] = Instruction::Constant(100);
+
+        assert!(matches!(
+            deserialise(&serialise(&program)),
+            Err(BytecodeFileError::OutOfRange("constant"))
+        ));
+
+        let local = with_entry(vec![Instruction::GetLocal(50), Instruction::Return]);
+
+        assert!(matches!(
+            deserialise(&serialise(&local)),
+            Err(BytecodeFileError::OutOfRange("local"))
+        ));
+
+        let mut jump = self::program();
+        jump.chunks[0].code[0] = Instruction::JumpIfFalse(100);
+
+        assert!(matches!(
+            deserialise(&serialise(&jump)),
+            Err(BytecodeFileError::OutOfRange("jump"))
+        ));
+    }
+
+    #[test]
+    fn rejects_unbalanced_stacks() {
+        let underflow = with_entry(vec![Instruction::Pop, Instruction::Return]);
+
+        assert!(matches!(
+            deserialise(&serialise(&underflow)),
+            Err(BytecodeFileError::UnbalancedStack { offset: 0, .. })
+        ));
+
+        // The branches of a conditional leave different numbers of values behind
+        let mismatch = with_entry(vec![
+            Instruction::Unit,
+            Instruction::Unit,
+            Instruction::JumpIfFalse(4),
+            Instruction::Unit,
+            Instruction::Return,
+        ]);
+
+        assert!(matches!(
+            deserialise(&serialise(&mismatch)),
+            Err(BytecodeFileError::UnbalancedStack { offset: 4, .. })
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+/// Bounds on the resources that a program can use whilst running, so that untrusted programs
+/// can't hang or exhaust memory. Each limit is unbounded if it is `None`.
+///
+/// By default, the call depth is limited to [Limits::DEFAULT_CALL_DEPTH] and the stack to
+/// [Limits::DEFAULT_STACK], so that runaway recursion stops with an error rather than exhausting
+/// memory. Every other limit is unbounded unless it is set, and [Limits::unbounded] lifts the
+/// defaults too.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// The number of instructions that can be executed.
+    pub instructions: Option<usize>,
+    /// How deeply function calls can be nested.
+    pub call_depth: Option<usize>,
+    /// The number of values that can be on the stack at once, covering every local and
+    /// temporary. Within a function, the stack only grows by a fixed amount that depends on its
+    /// code, so this is checked whenever a function is called rather than on every push. The
+    /// stack can therefore exceed the limit by the values a single function uses.
+    pub stack: Option<usize>,
+    /// The length in bytes of the longest string that can be built.
+    pub string_length: Option<usize>,
+    /// When the program must have finished by.
+    pub deadline: Option<Instant>,
+    /// Stops the program early when cancelled.
+    pub cancellation: Option<Cancellation>,
+}
+impl Limits {
+    /// The default [Limits::call_depth].
+    pub const DEFAULT_CALL_DEPTH: usize = 10_000;
+    /// The default [Limits::stack], in values.
+    pub const DEFAULT_STACK: usize = 1 << 20;
+
+    /// No limits at all, so a program may recurse until it runs out of memory. Only suitable for
+    /// programs that are trusted.
+    pub fn unbounded() -> Self {
+        Self {
+            instructions: None,
+            call_depth: None,
+            stack: None,
+            string_length: None,
+            deadline: None,
+            cancellation: None,
+        }
+    }
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            call_depth: Some(Self::DEFAULT_CALL_DEPTH),
+            stack: Some(Self::DEFAULT_STACK),
+            ..Self::unbounded()
+        }
+    }
+}
+
+/// Which of the [Limits] was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Instructions,
+    CallDepth,
+    Stack,
+    StringLength,
+    Deadline,
+}
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Limit::Instructions => "instruction limit",
+                Limit::CallDepth => "call depth limit",
+                Limit::Stack => "stack limit",
+                Limit::StringLength => "string length limit",
+                Limit::Deadline => "deadline",
+            }
+        )
+    }
+}
+
+/// A handle for cooperatively stopping a running program, which can be cloned and sent to other
+/// threads. The virtual machine checks it periodically, so the program stops shortly after it is
+/// cancelled rather than immediately.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::lexer::cursor::Span;
+
+pub use self::{compiler::*, limits::*, value::*, vm::*};
+
+mod compiler;
+mod disassembler;
+pub mod file;
+mod limits;
+mod value;
+mod vm;
+
+/// Each of the instructions that the virtual machine can execute. Instructions pop their operands
+/// from the value stack, and push their result back on to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// Push the value at the given index of the chunk's constant pool.
+    Constant(usize),
+    /// Push the unit value.
+    Unit,
+    /// Push a copy of the local in the given slot of the current frame.
+    GetLocal(usize),
+    /// Discard the top value.
+    Pop,
+    /// Discard the given number of values beneath the top value, keeping the top value in place.
+    Drop(usize),
+    /// Continue execution from the given offset of the chunk.
+    Jump(usize),
+    /// Pop a boolean, continuing execution from the given offset of the chunk if it is false.
+    JumpIfFalse(usize),
+    /// Addition of integers, or concatenation of strings.
+    Add,
+    /// Integer subtraction.
+    Sub,
+    /// Integer multiplication.
+    Mult,
+    /// Integer division.
+    Div,
+    /// Integer remainder.
+    Mod,
+    /// Integer exponent.
+    Exp,
+    /// Bitwise and of integers.
+    And,
+    /// Bitwise or of integers.
+    Or,
+    /// Bitwise exclusive or of integers.
+    Xor,
+    /// Shift an integer left, discarding the bits shifted out.
+    Shl,
+    /// Shift an integer right, copying its sign bit into the bits shifted in.
+    Shr,
+    /// Integer negation.
+    Negate,
+    /// Bitwise complement of an integer.
+    Complement,
+    /// Call the chunk at the given index, with its arguments on top of the stack.
+    Call(usize),
+    /// Call the host function imported at the given index of the program, with its arguments on
+    /// top of the stack.
+    CallHost(usize),
+    /// Build a struct or variant using the layout at the given index, with its values on top of
+    /// the stack.
+    Construct(usize),
+    /// Pop the given number of values, and push a string of each of them displayed in order.
+    Interpolate(usize),
+    /// Write the top value to the output.
+    Print,
+    /// Return the top value to the calling frame.
+    Return,
+}
+
+/// The shape of a struct or enum variant that can be built by [Instruction::Construct].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    Struct {
+        ident: String,
+        fields: Vec<String>,
+    },
+    Variant {
+        ident: String,
+        variant: String,
+        arity: usize,
+    },
+}
+impl Layout {
+    /// The number of values that are consumed to build this layout.
+    pub fn arity(&self) -> usize {
+        match self {
+            Layout::Struct { fields, .. } => fields.len(),
+            Layout::Variant { arity, .. } => *arity,
+        }
+    }
+}
+
+/// A function that the program expects to be provided by the host, which is looked up by name
+/// when it is called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub name: String,
+    pub arity: usize,
+}
+
+/// A compiled function, with its own constant pool. The span of source that each instruction was
+/// compiled from is kept alongside it, so that runtime errors can be reported.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub name: String,
+    pub arity: usize,
+    pub code: Vec<Instruction>,
+    pub constants: Vec<Value>,
+    pub spans: Vec<Span>,
+}
+impl Chunk {
+    pub fn new(name: String, arity: usize) -> Self {
+        Self {
+            name,
+            arity,
+            ..Default::default()
+        }
+    }
+
+    /// Appends an instruction to the chunk.
+    pub fn push(&mut self, instruction: Instruction, span: Span) {
+        self.code.push(instruction);
+        self.spans.push(span);
+    }
+
+    /// Adds a value to the constant pool, reusing an existing entry if there is one.
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants
+            .iter()
+            .position(|constant| *constant == value)
+            .unwrap_or_else(|| {
+                self.constants.push(value);
+                self.constants.len() - 1
+            })
+    }
+}
+
+/// A compiled program, made up of a chunk for each function as well as the top level statements.
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub chunks: Vec<Chunk>,
+    pub layouts: Vec<Layout>,
+    pub imports: Vec<Import>,
+    /// The index of the chunk containing the top level statements, where execution begins.
+    pub entry: usize,
+}
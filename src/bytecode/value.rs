@@ -0,0 +1,103 @@
+use std::{fmt::Display, rc::Rc};
+
+use crate::checks::typing::Type;
+
+/// A value that can be stored in the constant pool, or manipulated on the stack of the virtual
+/// machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Integer(isize),
+    Boolean(bool),
+    String(Rc<str>),
+    Unit,
+    /// An instance of a struct, with each of the fields in declaration order.
+    Struct {
+        ident: Rc<str>,
+        fields: Rc<[(Rc<str>, Value)]>,
+    },
+    /// An instance of an enum variant, with each of the values it carries.
+    Variant {
+        ident: Rc<str>,
+        variant: Rc<str>,
+        values: Rc<[Value]>,
+    },
+}
+impl Value {
+    /// A short description of the kind of value, for use in error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "integer",
+            Value::Boolean(_) => "boolean",
+            Value::String(_) => "string",
+            Value::Unit => "unit",
+            Value::Struct { .. } => "struct",
+            Value::Variant { .. } => "variant",
+        }
+    }
+
+    /// Whether the value could be of the type. Only the outermost struct or enum is compared,
+    /// rather than the values within it, and a type parameter could be anything.
+    pub fn has_type(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            (_, Type::Parameter(_) | Type::Unknown) => true,
+            (Value::Integer(_), Type::Integer)
+            | (Value::Boolean(_), Type::Boolean)
+            | (Value::String(_), Type::String)
+            | (Value::Unit, Type::Unit) => true,
+            (
+                Value::Struct { ident, .. } | Value::Variant { ident, .. },
+                Type::Named {
+                    ident: expected, ..
+                },
+            ) => **ident == **expected,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(integer) => write!(f, "{integer}"),
+            Value::Boolean(boolean) => write!(f, "{boolean}"),
+            Value::String(string) => write!(f, "{string}"),
+            Value::Unit => write!(f, "()"),
+            Value::Struct { ident, fields } => {
+                write!(f, "{ident} {{")?;
+
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, " {field}: {value}")?;
+                }
+
+                write!(f, " }}")
+            }
+            Value::Variant {
+                ident,
+                variant,
+                values,
+            } => {
+                write!(f, "{ident}::{variant}")?;
+
+                if !values.is_empty() {
+                    write!(f, "(")?;
+
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+
+                        write!(f, "{value}")?;
+                    }
+
+                    write!(f, ")")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
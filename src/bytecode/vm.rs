@@ -0,0 +1,688 @@
+use std::{io::Write, time::Instant};
+
+use thiserror::Error;
+
+use crate::{host::Host, lexer::cursor::Span};
+
+use super::{Instruction, Layout, Limit, Limits, Program, Value};
+
+/// All of the possible errors that could arise whilst running a program.
+#[derive(Debug, Error)]
+pub enum RuntimeErrorKind {
+    #[error("attempted to divide by zero")]
+    DivisionByZero,
+    #[error("integer overflow")]
+    Overflow,
+    #[error("attempted to raise to a negative exponent")]
+    NegativeExponent,
+    #[error("attempted to shift by a negative amount")]
+    NegativeShift,
+    #[error("invalid operands for {operation}: {lhs} and {rhs}")]
+    InvalidOperands {
+        operation: &'static str,
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    #[error("invalid operand for {operation}: {value}")]
+    InvalidOperand {
+        operation: &'static str,
+        value: &'static str,
+    },
+    #[error("no host function named {0}")]
+    UnknownHostFunction(String),
+    #[error("host function {name} failed: {message}")]
+    Host { name: String, message: String },
+    #[error("exceeded the {0}")]
+    LimitExceeded(Limit),
+    #[error("cancelled")]
+    Cancelled,
+    #[error("unable to write output: {0}")]
+    Output(#[from] std::io::Error),
+}
+
+/// A runtime error, along with the span of source that was executing when it occurred.
+#[derive(Debug, Error)]
+#[error("{span}: {kind}")]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Span,
+}
+
+/// How many instructions are executed between checking the clock and for cancellation, which
+/// would be slow to do for every instruction.
+const CHECK_INTERVAL: usize = 1024;
+
+/// The state of a single function call.
+struct Frame {
+    /// The index of the chunk being executed.
+    chunk: usize,
+    /// The index of the next instruction to execute.
+    ip: usize,
+    /// The position in the stack of the first local of this frame.
+    base: usize,
+}
+
+/// A stack based virtual machine that executes a compiled [Program], writing anything printed to
+/// `output`. Calls to host functions are resolved by name against the [Host], if one is given.
+pub struct Vm<'a, W> {
+    program: &'a Program,
+    host: Option<&'a Host>,
+    limits: Limits,
+    /// The number of instructions executed so far.
+    executed: usize,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    output: W,
+}
+impl<'a, W> Vm<'a, W>
+where
+    W: Write,
+{
+    pub fn new(program: &'a Program, output: W) -> Self {
+        Self {
+            program,
+            host: None,
+            limits: Limits::default(),
+            executed: 0,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            output,
+        }
+    }
+
+    /// Provides the functions that the program imports from the host.
+    pub fn with_host(mut self, host: &'a Host) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    /// Bounds the resources that the program can use, stopping it with an error if it exceeds
+    /// them. Programs are run within [Limits::default] otherwise.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Runs the program from its entry chunk through to completion.
+    pub fn run(&mut self) -> Result<Value, RuntimeError> {
+        self.stack.clear();
+        self.executed = 0;
+        self.frames = vec![Frame {
+            chunk: self.program.entry,
+            ip: 0,
+            base: 0,
+        }];
+
+        loop {
+            let exceeded = self.exceeded_limit();
+
+            let frame = self.frames.last_mut().expect("a frame to be executing");
+            let chunk = &self.program.chunks[frame.chunk];
+            let instruction = chunk.code[frame.ip];
+            let span = &chunk.spans[frame.ip];
+            frame.ip += 1;
+
+            let error = |kind| RuntimeError {
+                kind,
+                span: span.clone(),
+            };
+            if let Some(kind) = exceeded {
+                return Err(error(kind));
+            }
+
+            match instruction {
+                Instruction::Constant(constant) => {
+                    self.stack.push(chunk.constants[constant].clone())
+                }
+                Instruction::Unit => self.stack.push(Value::Unit),
+                Instruction::GetLocal(slot) => {
+                    let value = self.stack[frame.base + slot].clone();
+                    self.stack.push(value);
+                }
+                Instruction::Pop => {
+                    self.pop();
+                }
+                Instruction::Drop(count) => {
+                    let value = self.pop();
+                    self.stack.truncate(self.stack.len() - count);
+                    self.stack.push(value);
+                }
+                Instruction::Jump(offset) => frame.ip = offset,
+                Instruction::JumpIfFalse(offset) => {
+                    // The stack is borrowed directly, as the frame is still borrowed
+                    match self.stack.pop().expect("compiler to balance the stack") {
+                        Value::Boolean(true) => (),
+                        Value::Boolean(false) => frame.ip = offset,
+                        value => {
+                            return Err(error(RuntimeErrorKind::InvalidOperand {
+                                operation: "condition",
+                                value: value.kind(),
+                            }))
+                        }
+                    }
+                }
+                Instruction::Add
+                | Instruction::Sub
+                | Instruction::Mult
+                | Instruction::Div
+                | Instruction::Mod
+                | Instruction::Exp
+                | Instruction::And
+                | Instruction::Or
+                | Instruction::Xor
+                | Instruction::Shl
+                | Instruction::Shr => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+
+                    let value = binary_operation(instruction, lhs, rhs).map_err(error)?;
+                    self.check_string_length(&value).map_err(error)?;
+                    self.stack.push(value);
+                }
+                Instruction::Negate => match self.pop() {
+                    Value::Integer(integer) => self.stack.push(Value::Integer(
+                        integer
+                            .checked_neg()
+                            .ok_or_else(|| error(RuntimeErrorKind::Overflow))?,
+                    )),
+                    value => {
+                        return Err(error(RuntimeErrorKind::InvalidOperand {
+                            operation: "negation",
+                            value: value.kind(),
+                        }))
+                    }
+                },
+                Instruction::Complement => match self.pop() {
+                    Value::Integer(integer) => self.stack.push(Value::Integer(!integer)),
+                    value => {
+                        return Err(error(RuntimeErrorKind::InvalidOperand {
+                            operation: "complement",
+                            value: value.kind(),
+                        }))
+                    }
+                },
+                Instruction::Call(chunk) => {
+                    // The main frame isn't a call, so doesn't count towards the depth
+                    if self
+                        .limits
+                        .call_depth
+                        .is_some_and(|limit| self.frames.len() > limit)
+                    {
+                        return Err(error(RuntimeErrorKind::LimitExceeded(Limit::CallDepth)));
+                    }
+                    if self
+                        .limits
+                        .stack
+                        .is_some_and(|limit| self.stack.len() > limit)
+                    {
+                        return Err(error(RuntimeErrorKind::LimitExceeded(Limit::Stack)));
+                    }
+
+                    let base = self.stack.len() - self.program.chunks[chunk].arity;
+                    self.frames.push(Frame { chunk, ip: 0, base });
+                }
+                Instruction::CallHost(import) => {
+                    let import = &self.program.imports[import];
+                    let function = self
+                        .host
+                        .and_then(|host| host.get(&import.name))
+                        .ok_or_else(|| {
+                            error(RuntimeErrorKind::UnknownHostFunction(import.name.clone()))
+                        })?;
+
+                    let arguments = self.stack.split_off(self.stack.len() - import.arity);
+                    let value = function.call(arguments).map_err(|message| {
+                        error(RuntimeErrorKind::Host {
+                            name: import.name.clone(),
+                            message,
+                        })
+                    })?;
+                    // A mistake in the host shouldn't be blamed on the script
+                    if !value.has_type(&function.return_type) {
+                        return Err(error(RuntimeErrorKind::Host {
+                            name: import.name.clone(),
+                            message: format!(
+                                "returned {} instead of {}",
+                                value.kind(),
+                                function.return_type
+                            ),
+                        }));
+                    }
+                    self.check_string_length(&value).map_err(error)?;
+                    self.stack.push(value);
+                }
+                Instruction::Interpolate(count) => {
+                    let string = self
+                        .stack
+                        .split_off(self.stack.len() - count)
+                        .iter()
+                        .map(Value::to_string)
+                        .collect::<String>();
+
+                    let value = Value::String(string.into());
+                    self.check_string_length(&value).map_err(error)?;
+                    self.stack.push(value);
+                }
+                Instruction::Construct(layout) => {
+                    let layout = &self.program.layouts[layout];
+                    let values = self.stack.split_off(self.stack.len() - layout.arity());
+
+                    self.stack.push(match layout {
+                        Layout::Struct { ident, fields } => Value::Struct {
+                            ident: ident.as_str().into(),
+                            fields: fields
+                                .iter()
+                                .map(|field| field.as_str().into())
+                                .zip(values)
+                                .collect(),
+                        },
+                        Layout::Variant { ident, variant, .. } => Value::Variant {
+                            ident: ident.as_str().into(),
+                            variant: variant.as_str().into(),
+                            values: values.into(),
+                        },
+                    });
+                }
+                Instruction::Print => {
+                    let value = self.pop();
+                    writeln!(self.output, "{value}")
+                        .map_err(|e| error(RuntimeErrorKind::Output(e)))?;
+                }
+                Instruction::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("a frame to be executing");
+
+                    if self.frames.is_empty() {
+                        return Ok(result);
+                    }
+
+                    self.stack.truncate(frame.base);
+                    self.stack.push(result);
+                }
+            }
+        }
+    }
+
+    /// Counts the instruction that is about to be executed, checking that doing so doesn't
+    /// exceed any of the limits.
+    fn exceeded_limit(&mut self) -> Option<RuntimeErrorKind> {
+        self.executed += 1;
+
+        if self
+            .limits
+            .instructions
+            .is_some_and(|limit| self.executed > limit)
+        {
+            return Some(RuntimeErrorKind::LimitExceeded(Limit::Instructions));
+        }
+
+        // The first instruction is checked too, so that an expired deadline or cancelled program
+        // doesn't start at all
+        if (self.executed - 1).is_multiple_of(CHECK_INTERVAL) {
+            if self
+                .limits
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Some(RuntimeErrorKind::LimitExceeded(Limit::Deadline));
+            }
+            if self
+                .limits
+                .cancellation
+                .as_ref()
+                .is_some_and(|cancellation| cancellation.is_cancelled())
+            {
+                return Some(RuntimeErrorKind::Cancelled);
+            }
+        }
+
+        None
+    }
+
+    fn check_string_length(&self, value: &Value) -> Result<(), RuntimeErrorKind> {
+        match (value, self.limits.string_length) {
+            (Value::String(string), Some(limit)) if string.len() > limit => {
+                Err(RuntimeErrorKind::LimitExceeded(Limit::StringLength))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("compiler to balance the stack")
+    }
+}
+
+/// Raises `base` to a non-negative `exponent`, returning [None] if the result overflows. Only a
+/// base of 0, 1 or -1 can be raised to an exponent beyond [u32::MAX] without overflowing.
+pub(crate) fn checked_pow(base: isize, exponent: isize) -> Option<isize> {
+    match u32::try_from(exponent) {
+        Ok(exponent) => base.checked_pow(exponent),
+        Err(_) => match base {
+            0 | 1 => Some(base),
+            -1 if exponent % 2 == 0 => Some(1),
+            -1 => Some(-1),
+            _ => None,
+        },
+    }
+}
+
+/// Applies one of the binary operation instructions to a pair of values.
+///
+/// The remainder has the same sign as the lhs. Shifts move bits out of the integer rather than
+/// overflowing, so shifting by its width or more leaves `0`, or `-1` when shifting a negative
+/// integer right, but shifting by a negative amount is an error.
+fn binary_operation(
+    instruction: Instruction,
+    lhs: Value,
+    rhs: Value,
+) -> Result<Value, RuntimeErrorKind> {
+    Ok(match (instruction, lhs, rhs) {
+        (Instruction::Add, Value::String(lhs), Value::String(rhs)) => {
+            Value::String(format!("{lhs}{rhs}").into())
+        }
+        (instruction, Value::Integer(lhs), Value::Integer(rhs)) => Value::Integer(
+            match instruction {
+                Instruction::Add => lhs.checked_add(rhs),
+                Instruction::Sub => lhs.checked_sub(rhs),
+                Instruction::Mult => lhs.checked_mul(rhs),
+                Instruction::Div if rhs == 0 => return Err(RuntimeErrorKind::DivisionByZero),
+                Instruction::Div => lhs.checked_div(rhs),
+                Instruction::Mod if rhs == 0 => return Err(RuntimeErrorKind::DivisionByZero),
+                // The remainder of `i64::MIN / -1` is zero, even though the division overflows
+                Instruction::Mod => Some(lhs.wrapping_rem(rhs)),
+                Instruction::Exp if rhs < 0 => return Err(RuntimeErrorKind::NegativeExponent),
+                Instruction::Exp => checked_pow(lhs, rhs),
+                Instruction::And => Some(lhs & rhs),
+                Instruction::Or => Some(lhs | rhs),
+                Instruction::Xor => Some(lhs ^ rhs),
+                Instruction::Shl | Instruction::Shr if rhs < 0 => {
+                    return Err(RuntimeErrorKind::NegativeShift)
+                }
+                Instruction::Shl if rhs >= isize::BITS as isize => Some(0),
+                Instruction::Shl => Some(lhs << rhs),
+                Instruction::Shr => Some(lhs >> rhs.min(isize::BITS as isize - 1)),
+                _ => unreachable!(),
+            }
+            .ok_or(RuntimeErrorKind::Overflow)?,
+        ),
+        (instruction, lhs, rhs) => {
+            return Err(RuntimeErrorKind::InvalidOperands {
+                operation: match instruction {
+                    Instruction::Add => "addition",
+                    Instruction::Sub => "subtraction",
+                    Instruction::Mult => "multiplication",
+                    Instruction::Div => "division",
+                    Instruction::Mod => "remainder",
+                    Instruction::Exp => "exponent",
+                    Instruction::And => "bitwise and",
+                    Instruction::Or => "bitwise or",
+                    Instruction::Xor => "exclusive or",
+                    Instruction::Shl | Instruction::Shr => "shift",
+                    _ => unreachable!(),
+                },
+                lhs: lhs.kind(),
+                rhs: rhs.kind(),
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bytecode::{compile, Cancellation},
+        front_end,
+    };
+
+    /// Compiles and runs the source, returning everything that was printed.
+    fn run(source: &str) -> Result<String, RuntimeError> {
+        let (_, typed_ast) = front_end(source).unwrap();
+        let program = compile(&typed_ast);
+
+        let mut output = Vec::new();
+        Vm::new(&program, &mut output).run()?;
+
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            run("let a = 3; let b = 5; print(a + b * 2); print(2 ^ 3 ^ 2); print(-(7 - 10) / 2);")
+                .unwrap(),
+            "13\n512\n1\n"
+        );
+    }
+
+    #[test]
+    fn huge_exponents() {
+        // Only 0, 1 and -1 can be raised to exponents that don't fit in a u32
+        assert_eq!(checked_pow(0, 1 << 32), Some(0));
+        assert_eq!(checked_pow(1, 1 << 32), Some(1));
+        assert_eq!(checked_pow(-1, 1 << 32), Some(1));
+        assert_eq!(checked_pow(-1, (1 << 32) + 1), Some(-1));
+        assert_eq!(checked_pow(2, 1 << 32), None);
+        assert_eq!(checked_pow(-2, 3), Some(-8));
+
+        assert_eq!(
+            run("fn pow(a: Integer, b: Integer) -> Integer { a ^ b }
+                print(pow(1, 4294967296)); print(pow(-1, 9223372036854775807));")
+            .unwrap(),
+            "1\n-1\n"
+        );
+    }
+
+    #[test]
+    fn bitwise() {
+        assert_eq!(
+            run("let a = 12; let b = 10;
+                print(a & b); print(a | b); print(a ~ b); print(~a);
+                print(-7 % 3); print(7 % -3); print((-9223372036854775807 - 1) % -1);
+                print(1 << 62); print(1 << 63); print(3 << 64); print(-16 >> 2); print(-1 >> 64);
+                print(1 + 2 << 3 & 255 | 1);")
+            .unwrap(),
+            "8\n14\n6\n-13\n-1\n1\n0\n4611686018427387904\n\
+             -9223372036854775808\n0\n-4\n-1\n25\n"
+        );
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(
+            run("fn id<T>(x: T) -> T { x }
+                fn add(a: Integer, b: Integer) -> Integer { let c = a + b; c }
+                print(add(id(1), add(2, 3)));
+                print(id(\"hello\") + \" world\");")
+            .unwrap(),
+            "6\nhello world\n"
+        );
+    }
+
+    #[test]
+    fn structs_and_variants() {
+        assert_eq!(
+            run("enum Option<T> { Some(T), None }
+                struct Pair<A, B> { first: A, second: B }
+                let none: Option<Integer> = Option::None;
+                print(Option::Some(Pair { second: true, first: 1 }));
+                print(none);")
+            .unwrap(),
+            "Option::Some(Pair { first: 1, second: true })\nOption::None\n"
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(
+            run("let name = \"Ada\"; let age = 36; let known = true;
+                print(\"hello {name}, you are {age + 1}\");
+                print(\"{known}: \\{{\"nested {-age}\"}\\}\");")
+            .unwrap(),
+            "hello Ada, you are 37\ntrue: {nested -36}\n"
+        );
+    }
+
+    #[test]
+    fn locals_within_operands() {
+        // Locals declared whilst earlier operands are still on the stack are placed after them
+        assert_eq!(
+            run("let a = 1;
+                print(a + if true { let b = 2; a + b } else { 0 });
+                print(\"{a}{if true { let c = 3; c } else { 0 }}\");")
+            .unwrap(),
+            "4\n13\n"
+        );
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let error = run("let a = 0;\nprint(1 / a);").unwrap_err();
+        assert!(matches!(error.kind, RuntimeErrorKind::DivisionByZero));
+        assert_eq!(error.span.to_string(), "2:7");
+
+        assert!(matches!(
+            run("print(9223372036854775807 + 1);").unwrap_err().kind,
+            RuntimeErrorKind::Overflow
+        ));
+        assert!(matches!(
+            run("print(2 ^ -1);").unwrap_err().kind,
+            RuntimeErrorKind::NegativeExponent
+        ));
+        assert!(matches!(
+            run("let a = 0; print(5 % a);").unwrap_err().kind,
+            RuntimeErrorKind::DivisionByZero
+        ));
+        assert!(matches!(
+            run("let a = -1; print(1 << a);").unwrap_err().kind,
+            RuntimeErrorKind::NegativeShift
+        ));
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(
+            run(
+                "fn sign(x: Integer, negative: Boolean, zero: Boolean) -> Integer {
+                    if negative { let y = -x; y } else if zero { 0 } else { x }
+                }
+                let a = 1;
+                if true { let b = a + 1; print(b); }
+                print(sign(5, true, false) + sign(2, false, false) + sign(7, false, true));
+                print(if false { 1 } else { 2 });"
+            )
+            .unwrap_or_else(|e| panic!("{e}")),
+            "2\n-3\n2\n"
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let run_with_limits = |source: &str, limits: Limits| {
+            let (_, typed_ast) = front_end(source).unwrap();
+            Vm::new(&compile(&typed_ast), Vec::new())
+                .with_limits(limits)
+                .run()
+                .unwrap_err()
+        };
+        let forever = "fn forever(x: Integer) -> Integer { forever(x + 1) }\nprint(forever(0));";
+
+        let error = run_with_limits(
+            forever,
+            Limits {
+                call_depth: Some(100),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::LimitExceeded(Limit::CallDepth)
+        ));
+        assert_eq!(error.span.to_string(), "1:37");
+
+        // Runaway recursion is stopped even if no limits are given
+        let (_, typed_ast) = front_end(forever).unwrap();
+        let error = Vm::new(&compile(&typed_ast), Vec::new()).run().unwrap_err();
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::LimitExceeded(Limit::CallDepth)
+        ));
+
+        let error = run_with_limits(
+            forever,
+            Limits {
+                instructions: Some(1000),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::LimitExceeded(Limit::Instructions)
+        ));
+
+        let error = run_with_limits(
+            "fn grow(s: String) -> String { grow(s + s) }\nprint(grow(\"a\"));",
+            Limits {
+                string_length: Some(1000),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::LimitExceeded(Limit::StringLength)
+        ));
+
+        let error = run_with_limits(
+            forever,
+            Limits {
+                deadline: Some(std::time::Instant::now()),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::LimitExceeded(Limit::Deadline)
+        ));
+    }
+
+    #[test]
+    fn cancellation() {
+        // Each function calls the one before it twice, so the program takes far too long to
+        // finish without growing the stack
+        // Identifiers can't contain digits, so each function is named with letters
+        let name = |i: u8| format!("f{}{}", (b'a' + i / 26) as char, (b'a' + i % 26) as char);
+        let mut source = format!("fn {}() -> Integer {{ 1 }}\n", name(0));
+        for i in 1..=40 {
+            let previous = name(i - 1);
+            source.push_str(&format!(
+                "fn {}() -> Integer {{ {previous}() + {previous}() }}\n",
+                name(i)
+            ));
+        }
+        source.push_str(&format!("print({}());", name(40)));
+
+        let (_, typed_ast) = front_end(&source).unwrap();
+        let program = compile(&typed_ast);
+        let cancellation = Cancellation::new();
+
+        // The program is already running by the time it is cancelled from the other thread
+        let handle = cancellation.clone();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            handle.cancel();
+        });
+
+        let error = Vm::new(&program, Vec::new())
+            .with_limits(Limits {
+                cancellation: Some(cancellation),
+                // Stops the test rather than hanging if cancellation doesn't work
+                deadline: Some(std::time::Instant::now() + std::time::Duration::from_secs(30)),
+                ..Default::default()
+            })
+            .run()
+            .unwrap_err();
+        canceller.join().unwrap();
+        assert!(matches!(error.kind, RuntimeErrorKind::Cancelled));
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::lexer::cursor::Span;
+
+use super::Type;
+
+/// All of the possible type errors that could arise through type checking
+#[derive(Debug, Error)]
+pub enum TypeErrorKind {
+    #[error("Unknown ident {0}")]
+    UnknownIdent(String),
+    #[error("Unknown type {0}")]
+    UnknownType(String),
+    #[error("Cannot redeclare ident {0} (yet)")]
+    IdentRedeclared(String),
+    #[error("Cannot redeclare built in type {0}")]
+    BuiltinTypeRedeclared(String),
+    #[error("Mismatched types: {lhs} and {rhs}")]
+    MismatchedTypes { lhs: Type, rhs: Type },
+    #[error("Expected type {expected}, found {found}")]
+    UnexpectedType { expected: Type, found: Type },
+    #[error("{ident} expects {expected} type argument(s), but {found} were provided")]
+    WrongTypeArgumentCount {
+        ident: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("{ident} expects {expected} argument(s), but {found} were provided")]
+    WrongArgumentCount {
+        ident: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("Cannot infer type parameter {parameter} of {ident}")]
+    CannotInferTypeParameter { ident: String, parameter: String },
+    #[error("Unknown variant {variant} of {ident}")]
+    UnknownVariant { ident: String, variant: String },
+    #[error("Unknown field {field} of {ident}")]
+    UnknownField { ident: String, field: String },
+    #[error("Missing field {field} of {ident}")]
+    MissingField { ident: String, field: String },
+    #[error("Duplicate field {field} of {ident}")]
+    DuplicateField { ident: String, field: String },
+    #[error("Duplicate variant {variant} of {ident}")]
+    DuplicateVariant { ident: String, variant: String },
+    #[error("Duplicate type parameter {parameter} of {ident}")]
+    DuplicateTypeParameter { ident: String, parameter: String },
+    #[error("Duplicate parameter {parameter} of {ident}")]
+    DuplicateParameter { ident: String, parameter: String },
+    #[error("Cannot display a value of type {0} within a string")]
+    NotDisplayable(Type),
+}
+
+/// A type error, along with the span of source that caused it.
+#[derive(Debug, Error)]
+#[error("{span}: {kind}")]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    pub span: Span,
+}
+impl TypeError {
+    pub fn new(kind: TypeErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+/// Every type error found whilst checking a program, in the order that they were found.
+#[derive(Debug, Error)]
+pub struct TypeErrors(pub Vec<TypeError>);
+impl Display for TypeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{error}")?;
+        }
+
+        Ok(())
+    }
+}
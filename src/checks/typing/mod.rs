@@ -1,112 +1,1361 @@
-use std::collections::{hash_map::Entry, HashMap};
-
-use thiserror::Error;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt::Display,
+};
 
 use crate::{
-    parser::{parsers::Expression, AstNode},
+    host::Host,
+    lexer::cursor::Span,
+    parser::{
+        parsers::{
+            Block, Enum, Expression, ExpressionKind, Function, InterpolationPart, Let, Struct,
+            TypeAnnotation,
+        },
+        AstNode,
+    },
     token::Literal,
 };
 
+pub use self::{error::*, typed_ast::*};
+
+mod error;
+mod typed_ast;
+
 // Each of the possible types that can be expressed.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Type {
     Integer,
     String,
     Boolean,
+    /// The type of something that doesn't produce a value, such as a function without a return
+    /// type.
+    Unit,
+    /// A type parameter of the generic function or type currently being checked. Eg `T`.
+    Parameter(String),
+    /// A struct or enum along with the type arguments it is instantiated with. Eg
+    /// `Option<Integer>`.
+    Named {
+        ident: String,
+        arguments: Vec<Type>,
+    },
+    /// The type of something that failed to type check. It is compatible with every other type, so
+    /// that a single mistake doesn't cause a cascade of further errors.
+    Unknown,
+}
+impl Type {
+    /// Determines whether this type can be used in place of `other`, treating [Type::Unknown] as
+    /// compatible with everything.
+    pub fn is_compatible(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (
+                Type::Named { ident, arguments },
+                Type::Named {
+                    ident: other_ident,
+                    arguments: other_arguments,
+                },
+            ) => {
+                ident == other_ident
+                    && arguments.len() == other_arguments.len()
+                    && arguments
+                        .iter()
+                        .zip(other_arguments)
+                        .all(|(argument, other_argument)| argument.is_compatible(other_argument))
+            }
+            _ => self == other,
+        }
+    }
+}
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Integer => write!(f, "Integer"),
+            Type::String => write!(f, "String"),
+            Type::Boolean => write!(f, "Boolean"),
+            Type::Unit => write!(f, "Unit"),
+            Type::Parameter(ident) => write!(f, "{ident}"),
+            Type::Named { ident, arguments } if arguments.is_empty() => write!(f, "{ident}"),
+            Type::Named { ident, arguments } => {
+                write!(f, "{ident}<")?;
+
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{argument}")?;
+                }
+
+                write!(f, ">")
+            }
+            Type::Unknown => write!(f, "{{unknown}}"),
+        }
+    }
+}
+
+/// The signature of a declared function, which may be generic over some type parameters.
+#[derive(Clone, Debug)]
+struct FunctionSignature {
+    type_parameters: Vec<String>,
+    parameters: Vec<Type>,
+    return_type: Type,
+}
+
+impl Display for FunctionSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "fn")?;
+        if !self.type_parameters.is_empty() {
+            write!(f, "<{}>", self.type_parameters.join(", "))?;
+        }
+
+        write!(f, "(")?;
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{parameter}")?;
+        }
+
+        write!(f, ") -> {}", self.return_type)
+    }
 }
 
-/// All of the possible type errors that could arise through type checking
-#[derive(Debug, Error)]
-pub enum TypeError {
-    #[error("Unknown ident {0}")]
-    UnknownIdent(String),
-    #[error("Cannot redeclare ident {0} (yet)")]
-    IdentRedeclared(String),
-    #[error("Mismatched types: {lhs:?} and {rhs:?}")]
-    MismatchedTypes { lhs: Type, rhs: Type },
+/// A user declared struct or enum, which may be generic over some type parameters.
+#[derive(Clone, Debug)]
+enum TypeDeclaration {
+    Struct {
+        type_parameters: Vec<String>,
+        fields: Vec<(String, Type)>,
+    },
+    Enum {
+        type_parameters: Vec<String>,
+        variants: Vec<(String, Vec<Type>)>,
+    },
+}
+impl TypeDeclaration {
+    fn type_parameters(&self) -> &[String] {
+        match self {
+            TypeDeclaration::Struct {
+                type_parameters, ..
+            }
+            | TypeDeclaration::Enum {
+                type_parameters, ..
+            } => type_parameters,
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct TypeEnvironment {
     ident_types: HashMap<String, Type>,
+    /// Idents bound within the current scope. These can't be redeclared, but an ident from an
+    /// enclosing scope can be shadowed.
+    scope: HashSet<String>,
+    functions: HashMap<String, FunctionSignature>,
+    types: HashMap<String, TypeDeclaration>,
+    /// Type parameters that are in scope whilst checking a generic function or type.
+    type_parameters: Vec<String>,
+    /// Errors found so far. Checking continues past an error so that they can all be reported at
+    /// once.
+    errors: Vec<TypeError>,
 }
 impl TypeEnvironment {
-    /// Creates a typed environment from an AST.
-    pub fn from_ast(ast: Vec<AstNode>) -> Result<Self, TypeError> {
+    /// Creates a typed environment from an AST, returning it alongside the typed AST where every
+    /// expression has been annotated with its type. If any type errors are found, all of them are
+    /// returned.
+    pub fn from_ast(ast: Vec<AstNode>) -> Result<(Self, Vec<TypedAstNode>), TypeErrors> {
+        Self::from_ast_with_host(ast, &Host::default())
+    }
+
+    /// Creates a typed environment in the same way as [Self::from_ast], where the functions
+    /// registered with the host can also be called.
+    pub fn from_ast_with_host(
+        ast: Vec<AstNode>,
+        host: &Host,
+    ) -> Result<(Self, Vec<TypedAstNode>), TypeErrors> {
+        let (environment, typed_ast, errors) = Self::check(ast, host);
+
+        if errors.is_empty() {
+            Ok((environment, typed_ast))
+        } else {
+            Err(TypeErrors(errors))
+        }
+    }
+
+    /// Type checks the AST in the same way as [Self::from_ast], but always returns the typed AST
+    /// alongside any errors that were found. Anything that failed to check has the type
+    /// [Type::Unknown].
+    pub fn check(ast: Vec<AstNode>, host: &Host) -> (Self, Vec<TypedAstNode>, Vec<TypeError>) {
         let mut environment = Self::default();
+        environment.declare_intrinsics();
+        environment.declare_host_functions(host);
+
+        // Declarations may be referred to before they appear, so register them all first
+        environment.declare_types(&ast);
+        environment.declare_functions(&ast);
+
+        let mut typed_ast = Vec::new();
 
         for node in ast {
-            match node {
-                AstNode::Let(let_node) => {
-                    // Determine type of expression
-                    let expression_type = environment.get_expression_type(let_node.rhs)?;
-
-                    // Add type of expression to environment hashmap
-                    match environment.ident_types.entry(let_node.ident.clone()) {
-                        Entry::Vacant(entry) => entry.insert(expression_type),
-                        Entry::Occupied(_) => {
-                            return Err(TypeError::IdentRedeclared(let_node.ident))
-                        }
-                    };
-                }
+            typed_ast.push(match node {
+                AstNode::Let(let_node) => TypedAstNode::Let(environment.check_let(let_node)),
                 AstNode::Expression(expression_node) => {
                     // Validate type of expression
-                    environment.get_expression_type(expression_node)?;
+                    TypedAstNode::Expression(environment.check_expression(expression_node, None))
+                }
+                AstNode::Function(function) => {
+                    TypedAstNode::Function(environment.check_function(function))
+                }
+                AstNode::Struct(_) | AstNode::Enum(_) => continue,
+            });
+        }
+
+        let errors = std::mem::take(&mut environment.errors);
+        (environment, typed_ast, errors)
+    }
+
+    /// The type of a binding declared at the top level of the program.
+    pub fn binding(&self, ident: &str) -> Option<&Type> {
+        self.ident_types.get(ident)
+    }
+
+    /// Each declared function along with its signature, including those built into the language.
+    pub fn functions(&self) -> impl Iterator<Item = (&String, String)> {
+        self.functions
+            .iter()
+            .map(|(ident, signature)| (ident, signature.to_string()))
+    }
+
+    /// Each declared struct and enum, along with whether it is a struct.
+    pub fn types(&self) -> impl Iterator<Item = (&String, bool)> {
+        self.types.iter().map(|(ident, declaration)| {
+            (ident, matches!(declaration, TypeDeclaration::Struct { .. }))
+        })
+    }
+
+    /// Records an error, so that checking can continue.
+    fn error(&mut self, kind: TypeErrorKind, span: &Span) {
+        self.errors.push(TypeError::new(kind, span.clone()));
+    }
+
+    /// Reports an error if an operation that can only be applied to integers has operands of
+    /// another type, returning the type of its result.
+    fn check_integer_only(&mut self, integer_only: bool, ty: Type, span: &Span) -> Type {
+        if !integer_only || matches!(ty, Type::Integer | Type::Unknown) {
+            return ty;
+        }
+
+        self.error(
+            TypeErrorKind::UnexpectedType {
+                expected: Type::Integer,
+                found: ty,
+            },
+            span,
+        );
+        Type::Unknown
+    }
+
+    /// Reports an error if a value embedded within an interpolated string can't be displayed.
+    /// Only integers, booleans and strings have a textual form.
+    fn check_displayable(&mut self, expression: &TypedExpression) {
+        if matches!(
+            expression.ty,
+            Type::Integer | Type::Boolean | Type::String | Type::Unknown
+        ) {
+            return;
+        }
+
+        self.error(
+            TypeErrorKind::NotDisplayable(expression.ty.clone()),
+            &expression.span,
+        );
+    }
+
+    /// Registers the functions that are built into the language, rather than declared in the
+    /// source.
+    fn declare_intrinsics(&mut self) {
+        // `print` can write a value of any type to the output
+        self.functions.insert(
+            "print".to_string(),
+            FunctionSignature {
+                type_parameters: vec!["T".to_string()],
+                parameters: vec![Type::Parameter("T".to_string())],
+                return_type: Type::Unit,
+            },
+        );
+    }
+
+    /// Registers the functions provided by the embedding application. Functions declared in the
+    /// source can't have the same name as one of these.
+    fn declare_host_functions(&mut self, host: &Host) {
+        for (ident, function) in host.functions() {
+            self.functions.insert(
+                ident.clone(),
+                FunctionSignature {
+                    type_parameters: Vec::new(),
+                    parameters: function.parameters.clone(),
+                    return_type: function.return_type.clone(),
+                },
+            );
+        }
+    }
+
+    /// Registers every struct and enum declared in the AST.
+    fn declare_types(&mut self, ast: &[AstNode]) {
+        // Register each of the names first, so that declarations can refer to each other
+        for node in ast {
+            let (ident, ident_span, declaration) = match node {
+                AstNode::Struct(Struct {
+                    ident,
+                    ident_span,
+                    type_parameters,
+                    ..
+                }) => (
+                    ident,
+                    ident_span,
+                    TypeDeclaration::Struct {
+                        type_parameters: type_parameters.clone(),
+                        fields: Vec::new(),
+                    },
+                ),
+                AstNode::Enum(Enum {
+                    ident,
+                    ident_span,
+                    type_parameters,
+                    ..
+                }) => (
+                    ident,
+                    ident_span,
+                    TypeDeclaration::Enum {
+                        type_parameters: type_parameters.clone(),
+                        variants: Vec::new(),
+                    },
+                ),
+                _ => continue,
+            };
+
+            if matches!(ident.as_str(), "Integer" | "String" | "Boolean" | "Unit") {
+                self.error(
+                    TypeErrorKind::BuiltinTypeRedeclared(ident.clone()),
+                    ident_span,
+                );
+                continue;
+            }
+
+            self.check_unique(declaration.type_parameters(), ident_span, |parameter| {
+                TypeErrorKind::DuplicateTypeParameter {
+                    ident: ident.clone(),
+                    parameter,
+                }
+            });
+
+            match self.types.entry(ident.clone()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(declaration);
+                }
+                Entry::Occupied(_) => {
+                    self.error(TypeErrorKind::IdentRedeclared(ident.clone()), ident_span)
+                }
+            };
+        }
+
+        // Resolve the contents of each declaration
+        for node in ast {
+            let (ident, declaration) = match node {
+                AstNode::Struct(struct_node) => {
+                    self.type_parameters = struct_node.type_parameters.clone();
+                    self.check_unique(
+                        struct_node.fields.iter().map(|(field, _)| field),
+                        &struct_node.ident_span,
+                        |field| TypeErrorKind::DuplicateField {
+                            ident: struct_node.ident.clone(),
+                            field,
+                        },
+                    );
+
+                    (
+                        &struct_node.ident,
+                        TypeDeclaration::Struct {
+                            type_parameters: struct_node.type_parameters.clone(),
+                            fields: struct_node
+                                .fields
+                                .iter()
+                                .map(|(field, annotation)| {
+                                    (field.clone(), self.resolve_annotation(annotation))
+                                })
+                                .collect(),
+                        },
+                    )
+                }
+                AstNode::Enum(enum_node) => {
+                    self.type_parameters = enum_node.type_parameters.clone();
+                    self.check_unique(
+                        enum_node.variants.iter().map(|(variant, _)| variant),
+                        &enum_node.ident_span,
+                        |variant| TypeErrorKind::DuplicateVariant {
+                            ident: enum_node.ident.clone(),
+                            variant,
+                        },
+                    );
+
+                    (
+                        &enum_node.ident,
+                        TypeDeclaration::Enum {
+                            type_parameters: enum_node.type_parameters.clone(),
+                            variants: enum_node
+                                .variants
+                                .iter()
+                                .map(|(variant, values)| {
+                                    (
+                                        variant.clone(),
+                                        values
+                                            .iter()
+                                            .map(|annotation| self.resolve_annotation(annotation))
+                                            .collect(),
+                                    )
+                                })
+                                .collect(),
+                        },
+                    )
+                }
+                _ => continue,
+            };
+
+            // Built in types were reported and never declared, so aren't replaced here
+            if let Some(existing) = self.types.get_mut(ident) {
+                *existing = declaration;
+            }
+        }
+
+        self.type_parameters.clear();
+    }
+
+    /// Reports an error at `span` for each name that already appeared earlier in the list.
+    fn check_unique<'a>(
+        &mut self,
+        names: impl IntoIterator<Item = &'a String>,
+        span: &Span,
+        error: impl Fn(String) -> TypeErrorKind,
+    ) {
+        let mut seen = HashSet::new();
+
+        for name in names {
+            if !seen.insert(name) {
+                self.error(error(name.clone()), span);
+            }
+        }
+    }
+
+    /// Registers the signature of every function declared in the AST.
+    fn declare_functions(&mut self, ast: &[AstNode]) {
+        for node in ast {
+            let AstNode::Function(function) = node else {
+                continue;
+            };
+
+            self.type_parameters = function.type_parameters.clone();
+            self.check_unique(
+                &function.type_parameters,
+                &function.ident_span,
+                |parameter| TypeErrorKind::DuplicateTypeParameter {
+                    ident: function.ident.clone(),
+                    parameter,
+                },
+            );
+            self.check_unique(
+                function.parameters.iter().map(|(parameter, _)| parameter),
+                &function.ident_span,
+                |parameter| TypeErrorKind::DuplicateParameter {
+                    ident: function.ident.clone(),
+                    parameter,
+                },
+            );
+
+            let signature = FunctionSignature {
+                type_parameters: function.type_parameters.clone(),
+                parameters: function
+                    .parameters
+                    .iter()
+                    .map(|(_, annotation)| self.resolve_annotation(annotation))
+                    .collect(),
+                return_type: function
+                    .return_type
+                    .as_ref()
+                    .map(|annotation| self.resolve_annotation(annotation))
+                    .unwrap_or(Type::Unit),
+            };
+
+            match self.functions.entry(function.ident.clone()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(signature);
+                }
+                Entry::Occupied(_) => self.error(
+                    TypeErrorKind::IdentRedeclared(function.ident.clone()),
+                    &function.ident_span,
+                ),
+            };
+        }
+
+        self.type_parameters.clear();
+    }
+
+    /// Checks the body of a function against its signature. Functions can only see their own
+    /// parameters and bindings, so the surrounding idents are hidden whilst checking.
+    fn check_function(&mut self, function: Function) -> TypedFunction {
+        let signature = self.functions[&function.ident].clone();
+
+        let parameters = function
+            .parameters
+            .into_iter()
+            .map(|(ident, _)| ident)
+            .zip(signature.parameters)
+            .collect::<Vec<_>>();
+
+        let outer_idents =
+            std::mem::replace(&mut self.ident_types, parameters.iter().cloned().collect());
+        let outer_scope = std::mem::take(&mut self.scope);
+        self.type_parameters = signature.type_parameters.clone();
+
+        let body = self.check_block(function.body, Some(&signature.return_type));
+
+        if !body.ty.is_compatible(&signature.return_type) {
+            let span = body
+                .expression
+                .as_ref()
+                .map(|expression| &expression.span)
+                .unwrap_or(&function.ident_span)
+                .clone();
+
+            self.error(
+                TypeErrorKind::UnexpectedType {
+                    expected: signature.return_type.clone(),
+                    found: body.ty.clone(),
+                },
+                &span,
+            );
+        }
+
+        self.ident_types = outer_idents;
+        self.scope = outer_scope;
+        self.type_parameters.clear();
+
+        TypedFunction {
+            ident: function.ident,
+            span: function.ident_span,
+            type_parameters: signature.type_parameters,
+            parameters,
+            return_type: signature.return_type,
+            body,
+        }
+    }
+
+    /// Checks each of the statements within a block, determining the type that the block
+    /// evaluates to.
+    fn check_block(&mut self, block: Block, expected: Option<&Type>) -> TypedBlock {
+        let statements = block
+            .statements
+            .into_iter()
+            .map(|statement| match statement {
+                AstNode::Let(let_node) => TypedAstNode::Let(self.check_let(let_node)),
+                AstNode::Expression(expression) => {
+                    TypedAstNode::Expression(self.check_expression(expression, None))
                 }
+                // Blocks can only contain statements
+                AstNode::Function(_) | AstNode::Struct(_) | AstNode::Enum(_) => unreachable!(),
+            })
+            .collect();
+
+        let expression = block
+            .expression
+            .map(|expression| self.check_expression(*expression, expected));
+
+        TypedBlock {
+            statements,
+            ty: expression
+                .as_ref()
+                .map(|expression| expression.ty.clone())
+                .unwrap_or(Type::Unit),
+            expression: expression.map(Box::new),
+            span: block.span,
+        }
+    }
+
+    /// Checks a let binding, and adds the bound ident to the environment.
+    fn check_let(&mut self, let_node: Let) -> TypedLet {
+        let annotation = let_node
+            .type_annotation
+            .as_ref()
+            .map(|annotation| self.resolve_annotation(annotation));
+
+        // Determine type of expression
+        let rhs = self.check_expression(let_node.rhs, annotation.as_ref());
+
+        // If an annotation was provided, the ident should take on that type even if the
+        // expression doesn't match it
+        let ident_type = match annotation {
+            Some(annotation) => {
+                if !rhs.ty.is_compatible(&annotation) {
+                    self.error(
+                        TypeErrorKind::UnexpectedType {
+                            expected: annotation.clone(),
+                            found: rhs.ty.clone(),
+                        },
+                        &rhs.span,
+                    );
+                }
+
+                annotation
             }
+            None => rhs.ty.clone(),
+        };
+
+        // Add type of expression to environment hashmap, shadowing any binding from an enclosing
+        // scope
+        if self.scope.insert(let_node.ident.clone()) {
+            self.ident_types.insert(let_node.ident.clone(), ident_type);
+        } else {
+            self.error(
+                TypeErrorKind::IdentRedeclared(let_node.ident.clone()),
+                &let_node.ident_span,
+            );
         }
 
-        Ok(environment)
+        TypedLet {
+            ident: let_node.ident,
+            span: let_node.ident_span,
+            rhs,
+        }
     }
 
-    /// Determines the type of an expression
-    fn get_expression_type(&self, expression: Expression) -> Result<Type, TypeError> {
-        match expression {
-            Expression::Ident(ident) => self
-                .ident_types
-                .get(&ident)
-                .ok_or(TypeError::UnknownIdent(ident))
-                .copied(),
-            Expression::BinaryOperation { lhs, rhs, .. } => {
+    /// Resolves a type annotation from the source into a type, making sure that the correct
+    /// number of type arguments have been provided.
+    fn resolve_annotation(&mut self, annotation: &TypeAnnotation) -> Type {
+        let arguments = annotation
+            .arguments
+            .iter()
+            .map(|argument| self.resolve_annotation(argument))
+            .collect::<Vec<_>>();
+
+        let expected_arguments = match annotation.ident.as_str() {
+            "Integer" | "String" | "Boolean" | "Unit" => 0,
+            ident
+                if self
+                    .type_parameters
+                    .iter()
+                    .any(|parameter| parameter == ident) =>
+            {
+                0
+            }
+            ident => match self.types.get(ident) {
+                Some(declaration) => declaration.type_parameters().len(),
+                None => {
+                    self.error(
+                        TypeErrorKind::UnknownType(ident.to_string()),
+                        &annotation.span,
+                    );
+
+                    return Type::Unknown;
+                }
+            },
+        };
+
+        if arguments.len() != expected_arguments {
+            self.error(
+                TypeErrorKind::WrongTypeArgumentCount {
+                    ident: annotation.ident.clone(),
+                    expected: expected_arguments,
+                    found: arguments.len(),
+                },
+                &annotation.span,
+            );
+
+            return Type::Unknown;
+        }
+
+        match annotation.ident.as_str() {
+            "Integer" => Type::Integer,
+            "String" => Type::String,
+            "Boolean" => Type::Boolean,
+            "Unit" => Type::Unit,
+            ident
+                if self
+                    .type_parameters
+                    .iter()
+                    .any(|parameter| parameter == ident) =>
+            {
+                Type::Parameter(ident.to_string())
+            }
+            ident => Type::Named {
+                ident: ident.to_string(),
+                arguments,
+            },
+        }
+    }
+
+    /// Determines the type of an expression, using the `expected` type (if known) to infer any
+    /// type parameters that cannot be determined from the expression alone (eg `Option::None`).
+    /// Expressions that fail to type check are given the [Type::Unknown] type.
+    fn check_expression(
+        &mut self,
+        expression: Expression,
+        expected: Option<&Type>,
+    ) -> TypedExpression {
+        let span = expression.span;
+
+        let (kind, ty) = match expression.kind {
+            ExpressionKind::Ident(ident) => {
+                let ty = match self.ident_types.get(&ident) {
+                    Some(ty) => ty.clone(),
+                    None => {
+                        self.error(TypeErrorKind::UnknownIdent(ident.clone()), &span);
+                        Type::Unknown
+                    }
+                };
+
+                (TypedExpressionKind::Ident(ident), ty)
+            }
+            ExpressionKind::BinaryOperation {
+                operation,
+                lhs,
+                rhs,
+            } => {
                 // Check if lhs and rhs have compatible types
-                let lhs_type = self.get_expression_type(*lhs)?;
-                let rhs_type = self.get_expression_type(*rhs)?;
-
-                if lhs_type == rhs_type {
-                    Ok(lhs_type)
-                } else {
-                    Err(TypeError::MismatchedTypes {
-                        lhs: lhs_type,
-                        rhs: rhs_type,
+                let lhs = self.check_expression(*lhs, None);
+                let rhs = self.check_expression(*rhs, None);
+
+                let ty = match (&lhs.ty, &rhs.ty) {
+                    (Type::Unknown, ty) | (ty, Type::Unknown) => ty.clone(),
+                    (lhs_type, rhs_type) if lhs_type.is_compatible(rhs_type) => lhs_type.clone(),
+                    (lhs_type, rhs_type) => {
+                        self.error(
+                            TypeErrorKind::MismatchedTypes {
+                                lhs: lhs_type.clone(),
+                                rhs: rhs_type.clone(),
+                            },
+                            &span,
+                        );
+
+                        Type::Unknown
+                    }
+                };
+                // Strings can be concatenated, but every other operation needs integers
+                let integer_only = operation.is_integer_only() || ty != Type::String;
+                let ty = self.check_integer_only(integer_only, ty, &span);
+
+                (
+                    TypedExpressionKind::BinaryOperation {
+                        operation,
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    },
+                    ty,
+                )
+            }
+            ExpressionKind::UnaryOperation { operation, rhs } => {
+                // Both negation and complement are only defined for integers
+                let rhs = self.check_expression(*rhs, None);
+                let ty = self.check_integer_only(true, rhs.ty.clone(), &span);
+                (
+                    TypedExpressionKind::UnaryOperation {
+                        operation,
+                        rhs: Box::new(rhs),
+                    },
+                    ty,
+                )
+            }
+            ExpressionKind::Literal(literal) => {
+                let ty = match literal {
+                    Literal::Integer(_) => Type::Integer,
+                    Literal::String(_) => Type::String,
+                    Literal::Boolean(_) => Type::Boolean,
+                };
+
+                (TypedExpressionKind::Literal(literal), ty)
+            }
+            ExpressionKind::Interpolation(parts) => {
+                let parts = parts
+                    .into_iter()
+                    .map(|part| match part {
+                        InterpolationPart::Text(text) => TypedExpression {
+                            kind: TypedExpressionKind::Literal(Literal::String(text)),
+                            ty: Type::String,
+                            span: span.clone(),
+                        },
+                        InterpolationPart::Expression(expression) => {
+                            let expression = self.check_expression(expression, None);
+                            self.check_displayable(&expression);
+
+                            expression
+                        }
+                    })
+                    .collect();
+
+                (TypedExpressionKind::Interpolation(parts), Type::String)
+            }
+            ExpressionKind::Call {
+                ident,
+                type_arguments,
+                arguments,
+            } => {
+                let Some(signature) = self.functions.get(&ident).cloned() else {
+                    self.error(TypeErrorKind::UnknownIdent(ident.clone()), &span);
+
+                    return TypedExpression {
+                        kind: TypedExpressionKind::Call {
+                            ident,
+                            type_arguments: Vec::new(),
+                            arguments: self.check_expressions(arguments),
+                        },
+                        ty: Type::Unknown,
+                        span,
+                    };
+                };
+
+                if arguments.len() != signature.parameters.len() {
+                    self.error(
+                        TypeErrorKind::WrongArgumentCount {
+                            ident: ident.clone(),
+                            expected: signature.parameters.len(),
+                            found: arguments.len(),
+                        },
+                        &span,
+                    );
+
+                    return TypedExpression {
+                        kind: TypedExpressionKind::Call {
+                            ident,
+                            type_arguments: Vec::new(),
+                            arguments: self.check_expressions(arguments),
+                        },
+                        ty: Type::Unknown,
+                        span,
+                    };
+                }
+
+                let instance = self.instantiate(
+                    &ident,
+                    &span,
+                    &signature.type_parameters,
+                    &type_arguments,
+                    signature.parameters.iter().zip(arguments).collect(),
+                    &signature.return_type,
+                    expected,
+                );
+
+                (
+                    TypedExpressionKind::Call {
+                        ident,
+                        type_arguments: instance.type_arguments,
+                        arguments: instance.values,
+                    },
+                    instance.ty,
+                )
+            }
+            ExpressionKind::Variant {
+                ident,
+                type_arguments,
+                variant,
+                arguments,
+            } => {
+                let declaration = match self.types.get(&ident) {
+                    Some(TypeDeclaration::Enum {
+                        type_parameters,
+                        variants,
+                    }) => Ok((
+                        type_parameters.clone(),
+                        variants
+                            .iter()
+                            .find(|(name, _)| *name == variant)
+                            .map(|(_, values)| values.clone()),
+                    )),
+                    _ => Err(TypeErrorKind::UnknownType(ident.clone())),
+                }
+                .and_then(|(type_parameters, values)| match values {
+                    Some(values) => Ok((type_parameters, values)),
+                    None => Err(TypeErrorKind::UnknownVariant {
+                        ident: ident.clone(),
+                        variant: variant.clone(),
+                    }),
+                })
+                .and_then(|(type_parameters, values)| {
+                    if arguments.len() == values.len() {
+                        Ok((type_parameters, values))
+                    } else {
+                        Err(TypeErrorKind::WrongArgumentCount {
+                            ident: format!("{ident}::{variant}"),
+                            expected: values.len(),
+                            found: arguments.len(),
+                        })
+                    }
+                });
+
+                let (type_parameters, values) = match declaration {
+                    Ok(declaration) => declaration,
+                    Err(error) => {
+                        self.error(error, &span);
+
+                        return TypedExpression {
+                            kind: TypedExpressionKind::Variant {
+                                ident,
+                                variant,
+                                arguments: self.check_expressions(arguments),
+                            },
+                            ty: Type::Unknown,
+                            span,
+                        };
+                    }
+                };
+
+                let instance = self.instantiate(
+                    &ident,
+                    &span,
+                    &type_parameters,
+                    &type_arguments,
+                    values.iter().zip(arguments).collect(),
+                    &declared_type(&ident, &type_parameters),
+                    expected,
+                );
+
+                (
+                    TypedExpressionKind::Variant {
+                        ident,
+                        variant,
+                        arguments: instance.values,
+                    },
+                    instance.ty,
+                )
+            }
+            ExpressionKind::Struct {
+                ident,
+                type_arguments,
+                mut fields,
+            } => {
+                let Some(TypeDeclaration::Struct {
+                    type_parameters,
+                    fields: declared_fields,
+                }) = self.types.get(&ident).cloned()
+                else {
+                    self.error(TypeErrorKind::UnknownType(ident.clone()), &span);
+
+                    return TypedExpression {
+                        kind: TypedExpressionKind::Struct {
+                            fields: fields
+                                .into_iter()
+                                .map(|(field, value)| (field, self.check_expression(value, None)))
+                                .collect(),
+                            ident,
+                        },
+                        ty: Type::Unknown,
+                        span,
+                    };
+                };
+
+                // Fields that aren't declared are still checked, but are then discarded
+                let (unknown_fields, known_fields) = std::mem::take(&mut fields)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|(field, _)| {
+                        !declared_fields.iter().any(|(name, _)| name == field)
+                    });
+                fields = known_fields;
+
+                for (field, value) in unknown_fields {
+                    self.error(
+                        TypeErrorKind::UnknownField {
+                            ident: ident.clone(),
+                            field,
+                        },
+                        &value.span,
+                    );
+                    self.check_expression(value, None);
+                }
+
+                // Match each of the declared fields up with the provided values
+                let mut missing_field = false;
+                let values = declared_fields
+                    .iter()
+                    .filter_map(|(field, field_type)| {
+                        match fields.iter().position(|(name, _)| name == field) {
+                            Some(i) => Some((field_type, fields.remove(i).1)),
+                            None => {
+                                missing_field = true;
+                                self.error(
+                                    TypeErrorKind::MissingField {
+                                        ident: ident.clone(),
+                                        field: field.clone(),
+                                    },
+                                    &span,
+                                );
+
+                                None
+                            }
+                        }
                     })
+                    .collect();
+
+                // Only the first value given for a field is used, but any others are still checked
+                for (field, value) in fields {
+                    self.error(
+                        TypeErrorKind::DuplicateField {
+                            ident: ident.clone(),
+                            field,
+                        },
+                        &value.span,
+                    );
+                    self.check_expression(value, None);
+                }
+
+                let instance = self.instantiate(
+                    &ident,
+                    &span,
+                    &type_parameters,
+                    &type_arguments,
+                    values,
+                    &declared_type(&ident, &type_parameters),
+                    expected,
+                );
+
+                (
+                    TypedExpressionKind::Struct {
+                        fields: declared_fields
+                            .iter()
+                            .map(|(field, _)| field.clone())
+                            .zip(instance.values)
+                            .collect(),
+                        ident,
+                    },
+                    if missing_field {
+                        Type::Unknown
+                    } else {
+                        instance.ty
+                    },
+                )
+            }
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.check_expression(*condition, Some(&Type::Boolean));
+                if !condition.ty.is_compatible(&Type::Boolean) {
+                    self.error(
+                        TypeErrorKind::UnexpectedType {
+                            expected: Type::Boolean,
+                            found: condition.ty.clone(),
+                        },
+                        &condition.span,
+                    );
+                }
+
+                let then = self.check_branch(then, expected);
+                let otherwise = otherwise
+                    .map(|otherwise| self.check_branch(otherwise, expected.or(Some(&then.ty))));
+
+                let ty = match &otherwise {
+                    // Without an `else`, the body can't produce a value
+                    None if !then.ty.is_compatible(&Type::Unit) => {
+                        let span = then
+                            .expression
+                            .as_ref()
+                            .map(|expression| &expression.span)
+                            .unwrap_or(&span)
+                            .clone();
+
+                        self.error(
+                            TypeErrorKind::UnexpectedType {
+                                expected: Type::Unit,
+                                found: then.ty.clone(),
+                            },
+                            &span,
+                        );
+
+                        Type::Unit
+                    }
+                    None => Type::Unit,
+                    Some(otherwise) if !then.ty.is_compatible(&otherwise.ty) => {
+                        self.error(
+                            TypeErrorKind::MismatchedTypes {
+                                lhs: then.ty.clone(),
+                                rhs: otherwise.ty.clone(),
+                            },
+                            &span,
+                        );
+
+                        Type::Unknown
+                    }
+                    Some(otherwise) if then.ty == Type::Unknown => otherwise.ty.clone(),
+                    _ => then.ty.clone(),
+                };
+
+                (
+                    TypedExpressionKind::If {
+                        condition: Box::new(condition),
+                        then,
+                        otherwise,
+                    },
+                    ty,
+                )
+            }
+        };
+
+        TypedExpression { kind, ty, span }
+    }
+
+    /// Checks one of the branches of an `if`. Any bindings made within the branch go out of scope
+    /// at the end of it.
+    fn check_branch(&mut self, block: Block, expected: Option<&Type>) -> TypedBlock {
+        let outer_idents = self.ident_types.clone();
+        let outer_scope = std::mem::take(&mut self.scope);
+        let block = self.check_block(block, expected);
+        self.ident_types = outer_idents;
+        self.scope = outer_scope;
+
+        block
+    }
+
+    /// Checks each of the expressions, without any expected type. Used to find errors within the
+    /// arguments of something that couldn't be checked itself.
+    fn check_expressions(&mut self, expressions: Vec<Expression>) -> Vec<TypedExpression> {
+        expressions
+            .into_iter()
+            .map(|expression| self.check_expression(expression, None))
+            .collect()
+    }
+
+    /// Instantiates a (possibly generic) function, variant or struct at the site where it is
+    /// used. Type parameters are determined from any explicit type arguments, the expected type
+    /// and then the type of each provided value, before being substituted into the `result` type.
+    #[allow(clippy::too_many_arguments)]
+    fn instantiate(
+        &mut self,
+        ident: &str,
+        span: &Span,
+        type_parameters: &[String],
+        type_arguments: &[TypeAnnotation],
+        values: Vec<(&Type, Expression)>,
+        result: &Type,
+        expected: Option<&Type>,
+    ) -> Instance {
+        let mut bindings = HashMap::new();
+
+        if !type_arguments.is_empty() {
+            if type_arguments.len() == type_parameters.len() {
+                for (parameter, argument) in type_parameters.iter().zip(type_arguments) {
+                    let argument = self.resolve_annotation(argument);
+                    bindings.insert(parameter.clone(), argument);
+                }
+            } else {
+                self.error(
+                    TypeErrorKind::WrongTypeArgumentCount {
+                        ident: ident.to_string(),
+                        expected: type_parameters.len(),
+                        found: type_arguments.len(),
+                    },
+                    span,
+                );
+
+                // Avoid reporting that the type parameters can't be inferred
+                for parameter in type_parameters {
+                    bindings.insert(parameter.clone(), Type::Unknown);
                 }
             }
-            Expression::UnaryOperation { rhs, .. } => {
-                // TODO: Make sure operation can be applied to RHS
-                self.get_expression_type(*rhs)
+        }
+
+        if let Some(expected) = expected {
+            // The expected type is only a hint, so if it doesn't fit then let the caller report
+            // the mismatch
+            let mut hinted_bindings = bindings.clone();
+            if infer(result, expected, type_parameters, &mut hinted_bindings).is_ok() {
+                bindings = hinted_bindings;
             }
-            Expression::Literal(literal) => Ok(match literal {
-                Literal::Integer(_) => Type::Integer,
-                Literal::String(_) => Type::String,
-                Literal::Boolean(_) => Type::Boolean,
-            }),
+        }
+
+        let values = values
+            .into_iter()
+            .map(|(parameter_type, value)| {
+                let hint = substitute(parameter_type, &bindings);
+                let hint = (!contains_parameter(&hint, type_parameters)).then_some(hint);
+
+                let value = self.check_expression(value, hint.as_ref());
+                if let Err(error) = infer(parameter_type, &value.ty, type_parameters, &mut bindings)
+                {
+                    self.error(error, &value.span);
+                }
+
+                value
+            })
+            .collect();
+
+        let type_arguments = type_parameters
+            .iter()
+            .map(|parameter| {
+                bindings.get(parameter).cloned().unwrap_or_else(|| {
+                    self.error(
+                        TypeErrorKind::CannotInferTypeParameter {
+                            ident: ident.to_string(),
+                            parameter: parameter.clone(),
+                        },
+                        span,
+                    );
+
+                    Type::Unknown
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Make sure that any parameters that couldn't be inferred are substituted
+        let bindings = type_parameters
+            .iter()
+            .cloned()
+            .zip(type_arguments.iter().cloned())
+            .collect();
+
+        Instance {
+            ty: substitute(result, &bindings),
+            type_arguments,
+            values,
         }
     }
 }
 
+/// The result of instantiating a function, variant or struct where it is used.
+struct Instance {
+    /// The resulting type, with all type parameters substituted.
+    ty: Type,
+    /// The type that each type parameter was instantiated with.
+    type_arguments: Vec<Type>,
+    /// Each of the provided values once type checked.
+    values: Vec<TypedExpression>,
+}
+
+/// The type of a declared struct or enum, referring to its own type parameters.
+fn declared_type(ident: &str, type_parameters: &[String]) -> Type {
+    Type::Named {
+        ident: ident.to_string(),
+        arguments: type_parameters
+            .iter()
+            .map(|parameter| Type::Parameter(parameter.clone()))
+            .collect(),
+    }
+}
+
+/// Replaces each of the bound type parameters within `ty`.
+pub fn substitute(ty: &Type, bindings: &HashMap<String, Type>) -> Type {
+    match ty {
+        Type::Parameter(parameter) => bindings
+            .get(parameter)
+            .cloned()
+            .unwrap_or_else(|| ty.clone()),
+        Type::Named { ident, arguments } => Type::Named {
+            ident: ident.clone(),
+            arguments: arguments
+                .iter()
+                .map(|argument| substitute(argument, bindings))
+                .collect(),
+        },
+        _ => ty.clone(),
+    }
+}
+
+/// Determines whether any of the `type_parameters` appear within `ty`.
+fn contains_parameter(ty: &Type, type_parameters: &[String]) -> bool {
+    match ty {
+        Type::Parameter(parameter) => type_parameters.contains(parameter),
+        Type::Named { arguments, .. } => arguments
+            .iter()
+            .any(|argument| contains_parameter(argument, type_parameters)),
+        _ => false,
+    }
+}
+
+/// Matches the `actual` type against `pattern`, binding any of the `type_parameters` that appear
+/// within the pattern.
+fn infer(
+    pattern: &Type,
+    actual: &Type,
+    type_parameters: &[String],
+    bindings: &mut HashMap<String, Type>,
+) -> Result<(), TypeErrorKind> {
+    let mismatch = |bindings: &HashMap<String, Type>| TypeErrorKind::UnexpectedType {
+        expected: substitute(pattern, bindings),
+        found: actual.clone(),
+    };
+
+    match (pattern, actual) {
+        (Type::Parameter(parameter), _) if type_parameters.contains(parameter) => {
+            match bindings.get(parameter) {
+                Some(bound) if !actual.is_compatible(bound) => return Err(mismatch(bindings)),
+                // Prefer a known type over an unknown one
+                Some(Type::Unknown) | None => {
+                    bindings.insert(parameter.clone(), actual.clone());
+                }
+                Some(_) => (),
+            }
+        }
+        (
+            Type::Named { ident, arguments },
+            Type::Named {
+                ident: actual_ident,
+                arguments: actual_arguments,
+            },
+        ) if ident == actual_ident && arguments.len() == actual_arguments.len() => {
+            for (argument, actual_argument) in arguments.iter().zip(actual_arguments) {
+                infer(argument, actual_argument, type_parameters, bindings)
+                    .map_err(|_| mismatch(bindings))?;
+            }
+        }
+        _ if pattern.is_compatible(actual) => (),
+        _ => return Err(mismatch(bindings)),
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::parsers::{BinaryOperationKind, Expression, Let};
+    use crate::{
+        lexer::{cursor::Span, Lexer},
+        parser::{
+            parse,
+            parsers::{BinaryOperationKind, Expression, ExpressionKind, Let},
+        },
+        token::TokenKind,
+        token_stream::TokenStream,
+    };
+
+    /// Creates an expression with an empty span.
+    fn expression(kind: ExpressionKind) -> Expression {
+        Expression::new(kind, Span::default())
+    }
+
+    /// Lexes, parses and type checks the provided source.
+    fn check(source: &str) -> Result<TypeEnvironment, TypeErrors> {
+        let tokens = Lexer::new(source)
+            .map(|token| token.unwrap())
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace));
+
+        TypeEnvironment::from_ast(parse(TokenStream::from(tokens)).unwrap())
+            .map(|(environment, _)| environment)
+    }
+
+    /// Unwraps the kind of the first error that was found.
+    fn first_error<T>(result: Result<T, TypeErrors>) -> TypeErrorKind {
+        result.err().unwrap().0.remove(0).kind
+    }
 
     #[test]
     fn assignment() {
         assert_eq!(
             TypeEnvironment::from_ast(vec![AstNode::Let(Let {
                 ident: "a".to_string(),
-                rhs: Expression::Literal(Literal::Integer(10),)
+                ident_span: Span::default(),
+                type_annotation: None,
+                rhs: expression(ExpressionKind::Literal(Literal::Integer(10),)),
+                attributes: Vec::new(),
             })])
             .unwrap()
+            .0
             .ident_types,
             HashMap::from([("a".to_string(), Type::Integer)])
         );
@@ -115,17 +1364,23 @@ mod tests {
     #[test]
     fn duplicated_assignment() {
         assert!(matches!(
-            TypeEnvironment::from_ast(vec![
+            first_error(TypeEnvironment::from_ast(vec![
                 AstNode::Let(Let {
                     ident: "a".to_string(),
-                    rhs: Expression::Literal(Literal::Integer(10))
+                    ident_span: Span::default(),
+                    type_annotation: None,
+                    rhs: expression(ExpressionKind::Literal(Literal::Integer(10))),
+                    attributes: Vec::new(),
                 }),
                 AstNode::Let(Let {
                     ident: "a".to_string(),
-                    rhs: Expression::Literal(Literal::Integer(10))
+                    ident_span: Span::default(),
+                    type_annotation: None,
+                    rhs: expression(ExpressionKind::Literal(Literal::Integer(10))),
+                    attributes: Vec::new(),
                 })
-            ]),
-            Err(TypeError::IdentRedeclared(_))
+            ])),
+            TypeErrorKind::IdentRedeclared(_)
         ));
     }
 
@@ -135,26 +1390,38 @@ mod tests {
             TypeEnvironment::from_ast(vec![
                 AstNode::Let(Let {
                     ident: "a".to_string(),
-                    rhs: Expression::Literal(Literal::Integer(10))
+                    ident_span: Span::default(),
+                    type_annotation: None,
+                    rhs: expression(ExpressionKind::Literal(Literal::Integer(10))),
+                    attributes: Vec::new(),
                 }),
                 AstNode::Let(Let {
                     ident: "b".to_string(),
-                    rhs: Expression::Literal(Literal::Integer(10))
+                    ident_span: Span::default(),
+                    type_annotation: None,
+                    rhs: expression(ExpressionKind::Literal(Literal::Integer(10))),
+                    attributes: Vec::new(),
                 }),
                 AstNode::Let(Let {
                     ident: "c".to_string(),
-                    rhs: Expression::BinaryOperation {
+                    ident_span: Span::default(),
+                    type_annotation: None,
+                    rhs: expression(ExpressionKind::BinaryOperation {
                         operation: BinaryOperationKind::Add,
-                        lhs: Box::new(Expression::Literal(Literal::Integer(10))),
-                        rhs: Box::new(Expression::BinaryOperation {
+                        lhs: Box::new(expression(ExpressionKind::Literal(Literal::Integer(10)))),
+                        rhs: Box::new(expression(ExpressionKind::BinaryOperation {
                             operation: BinaryOperationKind::Mult,
-                            lhs: Box::new(Expression::Ident("b".to_string())),
-                            rhs: Box::new(Expression::Literal(Literal::Integer(10)))
-                        })
-                    }
+                            lhs: Box::new(expression(ExpressionKind::Ident("b".to_string()))),
+                            rhs: Box::new(expression(ExpressionKind::Literal(Literal::Integer(
+                                10
+                            ))))
+                        }))
+                    }),
+                    attributes: Vec::new(),
                 })
             ])
             .unwrap()
+            .0
             .ident_types,
             HashMap::from([
                 ("a".to_string(), Type::Integer),
@@ -167,12 +1434,358 @@ mod tests {
     #[test]
     fn boolean_and_integer() {
         assert!(matches!(
-            TypeEnvironment::from_ast(vec![AstNode::Expression(Expression::BinaryOperation {
-                operation: BinaryOperationKind::Add,
-                lhs: Box::new(Expression::Literal(Literal::Boolean(false))),
-                rhs: Box::new(Expression::Literal(Literal::Integer(10)))
-            })]),
-            Err(TypeError::MismatchedTypes { .. })
+            first_error(TypeEnvironment::from_ast(vec![AstNode::Expression(
+                expression(ExpressionKind::BinaryOperation {
+                    operation: BinaryOperationKind::Add,
+                    lhs: Box::new(expression(ExpressionKind::Literal(Literal::Boolean(false)))),
+                    rhs: Box::new(expression(ExpressionKind::Literal(Literal::Integer(10))))
+                })
+            )])),
+            TypeErrorKind::MismatchedTypes { .. }
         ))
     }
+
+    #[test]
+    fn generic_function() {
+        let environment = check(
+            "fn id<T>(x: T) -> T { x }
+            let a = id(5);
+            let b = id::<Boolean>(true);",
+        )
+        .unwrap();
+
+        assert_eq!(environment.ident_types["a"], Type::Integer);
+        assert_eq!(environment.ident_types["b"], Type::Boolean);
+    }
+
+    #[test]
+    fn integer_only_operations() {
+        let environment =
+            check("let a = 6 & 3 << 1 % 4; let b = ~a; let c = \"a\" + \"b\";").unwrap();
+        assert_eq!(environment.ident_types["a"], Type::Integer);
+        assert_eq!(environment.ident_types["b"], Type::Integer);
+        assert_eq!(environment.ident_types["c"], Type::String);
+
+        for source in [
+            "let a = \"a\" | \"b\";",
+            "let a = true >> false;",
+            "let a = ~true;",
+            "fn f<T>(x: T) -> T { x % x }",
+            "let a = -true;",
+            "let a = true + true;",
+            "let a = \"a\" * \"b\";",
+            "let a = -\"a\";",
+            "struct P {} let a = P {} - P {};",
+            "fn f<T>(x: T) -> T { x + x }",
+        ] {
+            assert!(
+                matches!(
+                    first_error(check(source)),
+                    TypeErrorKind::UnexpectedType {
+                        expected: Type::Integer,
+                        ..
+                    }
+                ),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn interpolation() {
+        let environment =
+            check("let age = 3; let a = \"{age + 1} {true} {\"text\" + \"!\"}\";").unwrap();
+        assert_eq!(environment.ident_types["a"], Type::String);
+
+        for source in [
+            "let a = \"{print(1)}\";",
+            "struct P {} let a = \"{P {}}\";",
+            "fn f<T>(x: T) -> String { \"{x}\" }",
+        ] {
+            assert!(
+                matches!(first_error(check(source)), TypeErrorKind::NotDisplayable(_)),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn generic_enum() {
+        let environment = check(
+            "enum Option<T> { Some(T), None }
+            fn unwrap_or<T>(option: Option<T>, default: T) -> T { default }
+            let a = Option::Some(5);
+            let b: Option<Boolean> = Option::None;
+            let c = unwrap_or(a, 3);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            environment.ident_types["a"],
+            Type::Named {
+                ident: "Option".to_string(),
+                arguments: vec![Type::Integer]
+            }
+        );
+        assert_eq!(environment.ident_types["b"].to_string(), "Option<Boolean>");
+        assert_eq!(environment.ident_types["c"], Type::Integer);
+    }
+
+    #[test]
+    fn generic_struct() {
+        let environment = check(
+            "struct Pair<A, B> { first: A, second: B }
+            let a = Pair { second: true, first: 1 };",
+        )
+        .unwrap();
+
+        assert_eq!(
+            environment.ident_types["a"].to_string(),
+            "Pair<Integer, Boolean>"
+        );
+
+        assert!(matches!(
+            first_error(check(
+                "struct Pair<A, B> { first: A, second: B }
+                let a = Pair { first: 1 };"
+            )),
+            TypeErrorKind::MissingField { .. }
+        ));
+    }
+
+    #[test]
+    fn duplicate_fields() {
+        let errors = check(
+            "struct P { a: Integer }
+            let p = P { a: 1, a: true + 1 };",
+        )
+        .err()
+        .unwrap()
+        .0;
+        // The repeated value is reported, and is still checked
+        assert!(matches!(
+            &errors[0].kind,
+            TypeErrorKind::DuplicateField { field, .. } if field == "a"
+        ));
+        assert!(matches!(
+            errors[1].kind,
+            TypeErrorKind::MismatchedTypes { .. }
+        ));
+        assert_eq!(errors.len(), 2);
+
+        assert!(matches!(
+            first_error(check("struct P { a: Integer, a: Boolean }")),
+            TypeErrorKind::DuplicateField { .. }
+        ));
+    }
+
+    #[test]
+    fn duplicate_declarations() {
+        for (source, expected) in [
+            ("enum E { A, B, A }", "Duplicate variant A of E"),
+            ("struct P<T, T> { a: T }", "Duplicate type parameter T of P"),
+            ("enum E<T, T> { A(T) }", "Duplicate type parameter T of E"),
+            ("fn f<T, T>(a: T) {}", "Duplicate type parameter T of f"),
+            (
+                "fn f(a: Integer, a: Boolean) {}",
+                "Duplicate parameter a of f",
+            ),
+            (
+                "struct Integer { a: Boolean }",
+                "Cannot redeclare built in type Integer",
+            ),
+            ("enum Unit { A }", "Cannot redeclare built in type Unit"),
+        ] {
+            assert_eq!(
+                check(source).err().unwrap().0[0].kind.to_string(),
+                expected,
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn generic_mismatch() {
+        assert!(matches!(
+            first_error(check(
+                "fn same<T>(a: T, b: T) -> T { a }
+                let a = same(1, false);"
+            )),
+            TypeErrorKind::UnexpectedType {
+                expected: Type::Integer,
+                found: Type::Boolean
+            }
+        ));
+    }
+
+    #[test]
+    fn wrong_type_argument_count() {
+        assert!(matches!(
+            first_error(check(
+                "enum Option<T> { Some(T), None }
+                let a: Option<Integer, Boolean> = Option::None;"
+            )),
+            TypeErrorKind::WrongTypeArgumentCount {
+                expected: 1,
+                found: 2,
+                ..
+            }
+        ));
+
+        assert!(matches!(
+            first_error(check(
+                "fn id<T>(x: T) -> T { x }
+                let a = id::<Integer, Integer>(1);"
+            )),
+            TypeErrorKind::WrongTypeArgumentCount {
+                expected: 1,
+                found: 2,
+                ..
+            }
+        ));
+
+        assert!(matches!(
+            first_error(check("let a: Integer<Boolean> = 1;")),
+            TypeErrorKind::WrongTypeArgumentCount {
+                expected: 0,
+                found: 1,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn cannot_infer_type_parameter() {
+        assert!(matches!(
+            first_error(check(
+                "enum Option<T> { Some(T), None }
+                let a = Option::None;"
+            )),
+            TypeErrorKind::CannotInferTypeParameter { .. }
+        ));
+    }
+
+    #[test]
+    fn conditionals() {
+        let environment = check(
+            "fn pick<T>(first: Boolean, a: T, b: T) -> T { if first { a } else { b } }
+            let a = if true { let b = 1; b } else if false { 2 } else { 3 };
+            let c = pick(false, a, 4);
+            if a { 1; }",
+        );
+        assert!(matches!(
+            first_error(environment),
+            TypeErrorKind::UnexpectedType {
+                expected: Type::Boolean,
+                found: Type::Integer
+            }
+        ));
+
+        let environment = check(
+            "let a = if true { 1 } else { false };
+            let b = if true { 1 };",
+        );
+        let errors = environment.err().unwrap().0;
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0].kind,
+            TypeErrorKind::MismatchedTypes { .. }
+        ));
+        assert!(matches!(
+            errors[1].kind,
+            TypeErrorKind::UnexpectedType {
+                expected: Type::Unit,
+                found: Type::Integer
+            }
+        ));
+        assert_eq!(errors[1].span.to_string(), "2:31");
+
+        // Bindings within a branch go out of scope at the end of it
+        assert!(matches!(
+            first_error(check(
+                "if true { let b = 1; }
+let c = b;"
+            )),
+            TypeErrorKind::UnknownIdent(_)
+        ));
+    }
+
+    #[test]
+    fn shadowing() {
+        // A binding within a nested scope can shadow one from outside, even with another type
+        let environment = check(
+            "fn f(x: Integer) -> Boolean { let x = true; x }
+            let a = 1;
+            let b = if true { let a = \"a\"; a } else { \"b\" };
+            let c = a + 1;",
+        )
+        .unwrap();
+        assert_eq!(environment.ident_types["a"], Type::Integer);
+        assert_eq!(environment.ident_types["b"], Type::String);
+
+        assert!(matches!(
+            first_error(check("if true { let a = 1; let a = 2; }")),
+            TypeErrorKind::IdentRedeclared(_)
+        ));
+    }
+
+    #[test]
+    fn typed_ast() {
+        let tokens = Lexer::new("fn id<T>(x: T) -> T { x }\nlet a = 1 + id(2);")
+            .map(|token| token.unwrap())
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace));
+        let (_, typed_ast) =
+            TypeEnvironment::from_ast(parse(TokenStream::from(tokens)).unwrap()).unwrap();
+
+        let TypedAstNode::Let(TypedLet { rhs, .. }) = &typed_ast[1] else {
+            panic!("expected let");
+        };
+        assert_eq!(rhs.ty, Type::Integer);
+        assert_eq!(rhs.span.start.to_string(), "2:9");
+        assert_eq!(rhs.span.end.to_string(), "2:17");
+
+        let TypedExpressionKind::BinaryOperation { rhs: call, .. } = &rhs.kind else {
+            panic!("expected binary operation");
+        };
+        let TypedExpressionKind::Call { type_arguments, .. } = &call.kind else {
+            panic!("expected call");
+        };
+        assert_eq!(type_arguments, &vec![Type::Integer]);
+        assert_eq!(call.span.start.to_string(), "2:13");
+    }
+
+    #[test]
+    fn multiple_errors() {
+        let errors = check(
+            "let a = b + 1;
+            let c = a + true;
+            let d = unknown(a) * 2;
+            let e: Integer = false;
+            let f = e + 1;",
+        )
+        .err()
+        .unwrap()
+        .0;
+
+        // `a` and `d` are unknown after their errors, so they don't cause further errors
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(&errors[0].kind, TypeErrorKind::UnknownIdent(ident) if ident == "b"));
+        assert_eq!(errors[0].span.to_string(), "1:9");
+        assert!(matches!(
+            errors[1].kind,
+            TypeErrorKind::MismatchedTypes { .. }
+        ));
+        assert_eq!(errors[1].span.to_string(), "2:21");
+        assert!(
+            matches!(&errors[2].kind, TypeErrorKind::UnknownIdent(ident) if ident == "unknown")
+        );
+        assert!(matches!(
+            errors[3].kind,
+            TypeErrorKind::UnexpectedType {
+                expected: Type::Integer,
+                found: Type::Boolean
+            }
+        ));
+        assert_eq!(errors[3].span.to_string(), "4:30");
+    }
 }
@@ -0,0 +1,96 @@
+use crate::{
+    lexer::cursor::Span,
+    parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+    token::Literal,
+};
+
+use super::Type;
+
+/// A node of the AST once it has been type checked. Struct and enum declarations are consumed by
+/// the type checker, and are available from the [super::TypeEnvironment].
+#[derive(Debug)]
+pub enum TypedAstNode {
+    Let(TypedLet),
+    Expression(TypedExpression),
+    Function(TypedFunction),
+}
+
+/// A let binding, where the bound ident takes the type of `rhs`.
+#[derive(Debug)]
+pub struct TypedLet {
+    pub ident: String,
+    /// The span of the bound ident.
+    pub span: Span,
+    pub rhs: TypedExpression,
+}
+
+/// A function declaration, with each of the parameters and return type resolved.
+#[derive(Debug)]
+pub struct TypedFunction {
+    pub ident: String,
+    /// The span of the function's ident.
+    pub span: Span,
+    pub type_parameters: Vec<String>,
+    pub parameters: Vec<(String, Type)>,
+    pub return_type: Type,
+    pub body: TypedBlock,
+}
+
+/// A block of statements, with the type that the block evaluates to.
+#[derive(Debug)]
+pub struct TypedBlock {
+    pub statements: Vec<TypedAstNode>,
+    pub expression: Option<Box<TypedExpression>>,
+    pub ty: Type,
+    /// The span from the opening brace through to the closing brace.
+    pub span: Span,
+}
+
+/// An expression annotated with its type, and the span of source that it was parsed from.
+#[derive(Debug)]
+pub struct TypedExpression {
+    pub kind: TypedExpressionKind,
+    pub ty: Type,
+    pub span: Span,
+}
+
+/// Each of the possible typed expressions, mirroring [crate::parser::parsers::ExpressionKind].
+#[derive(Debug)]
+pub enum TypedExpressionKind {
+    Ident(String),
+    BinaryOperation {
+        operation: BinaryOperationKind,
+        lhs: Box<TypedExpression>,
+        rhs: Box<TypedExpression>,
+    },
+    UnaryOperation {
+        operation: UnaryOperationKind,
+        rhs: Box<TypedExpression>,
+    },
+    Literal(Literal),
+    /// A function call. `type_arguments` holds the type that each of the function's type
+    /// parameters was instantiated with at this call site.
+    Call {
+        ident: String,
+        type_arguments: Vec<Type>,
+        arguments: Vec<TypedExpression>,
+    },
+    Variant {
+        ident: String,
+        variant: String,
+        arguments: Vec<TypedExpression>,
+    },
+    /// Construction of a struct, with the fields in the order that they were declared.
+    Struct {
+        ident: String,
+        fields: Vec<(String, TypedExpression)>,
+    },
+    /// A string with embedded expressions, which are displayed and joined in order. Text between
+    /// the expressions is kept as string literals.
+    Interpolation(Vec<TypedExpression>),
+    If {
+        condition: Box<TypedExpression>,
+        then: TypedBlock,
+        otherwise: Option<TypedBlock>,
+    },
+}
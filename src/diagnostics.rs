@@ -0,0 +1,148 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::{
+    checks::typing::{TypeError, TypeErrors},
+    lexer::{
+        cursor::{Position, Span},
+        LexerError,
+    },
+    lints::{Level, Warning},
+    optimise::{ConstantError, ConstantErrors},
+    parser::error::ParserError,
+};
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The program can't be compiled.
+    Error,
+    /// The program can be compiled, but likely contains a mistake.
+    Warning,
+}
+
+/// A problem found whilst compiling, from any stage of the compiler.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+}
+impl Diagnostic {
+    fn error(message: String, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            span,
+        }
+    }
+
+    pub fn from_lexer_error(error: LexerError) -> Self {
+        let position = error.position().clone();
+
+        Self::error(
+            without_position(&error, &position),
+            Span::new(position.clone(), position),
+        )
+    }
+
+    /// Parser errors don't all have a position, so those that don't are reported at the end of
+    /// the source.
+    pub fn from_parser_error(error: ParserError, source: &str) -> Self {
+        let position = error.position().cloned().unwrap_or_else(|| end_of(source));
+
+        Self::error(
+            without_position(&error, &position),
+            Span::new(position.clone(), position),
+        )
+    }
+}
+impl From<TypeError> for Diagnostic {
+    fn from(error: TypeError) -> Self {
+        Self::error(error.kind.to_string(), error.span)
+    }
+}
+impl From<ConstantError> for Diagnostic {
+    fn from(error: ConstantError) -> Self {
+        Self::error(error.kind.to_string(), error.span)
+    }
+}
+impl From<Warning> for Diagnostic {
+    fn from(warning: Warning) -> Self {
+        Self {
+            severity: match warning.level {
+                Level::Deny => Severity::Error,
+                Level::Allow | Level::Warn => Severity::Warning,
+            },
+            message: format!("{} [{}]", warning.message, warning.lint),
+            span: warning.span,
+        }
+    }
+}
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+/// Every diagnostic found whilst compiling a program, with any warnings first. If compilation
+/// failed, at least one of them is an error.
+#[derive(Debug, Error, Default)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+impl Diagnostics {
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+impl From<TypeErrors> for Diagnostics {
+    fn from(errors: TypeErrors) -> Self {
+        Self(errors.0.into_iter().map(Diagnostic::from).collect())
+    }
+}
+impl From<ConstantErrors> for Diagnostics {
+    fn from(errors: ConstantErrors) -> Self {
+        Self(errors.0.into_iter().map(Diagnostic::from).collect())
+    }
+}
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            if diagnostic.severity == Severity::Warning {
+                write!(f, "warning: ")?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The message of a lexer or parser error, which starts with the position that it was found at.
+/// Diagnostics keep the position separately, so it is removed.
+fn without_position(error: &impl Display, position: &Position) -> String {
+    let message = error.to_string();
+
+    match message.strip_prefix(&format!("{position}: ")) {
+        Some(rest) => rest.to_string(),
+        None => message,
+    }
+}
+
+/// The position of the last character in the source.
+fn end_of(source: &str) -> Position {
+    let line = source.lines().count().saturating_sub(1);
+    let character = source
+        .lines()
+        .last()
+        .map(|last| last.chars().count().saturating_sub(1))
+        .unwrap_or_default();
+
+    Position::at(line, character)
+}
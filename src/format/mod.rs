@@ -0,0 +1,749 @@
+use std::{collections::VecDeque, fmt::Write};
+
+use crate::{
+    lexer::cursor::{Position, Span},
+    parser::{
+        parsers::{
+            Attribute, BinaryOperationKind, Block, Enum, Expression, ExpressionKind, Function,
+            InterpolationPart, Let, Placement, Struct, TypeAnnotation, UnaryOperationKind,
+        },
+        AstNode,
+    },
+    token::Literal,
+};
+
+/// The indentation written for each level of nesting.
+const INDENT: &str = "    ";
+
+/// Formats a program canonically, with one statement per line and only the parentheses that are
+/// needed to parse it in the same way. The parser discards comments, so they are provided
+/// separately along with their spans, and are written back out next to the statement that they
+/// were found by. A single blank line is kept wherever statements were separated by any. Raw
+/// strings are written as raw strings, so `raw_strings` holds where each of them starts.
+///
+/// Formatting the output again produces the same output, and parsing it produces the same AST.
+pub fn format(
+    ast: &[AstNode],
+    comments: Vec<(String, Span)>,
+    raw_strings: Vec<Position>,
+) -> String {
+    let mut formatter = Formatter {
+        output: String::new(),
+        depth: 0,
+        comments: comments.into(),
+        raw_strings,
+        line: None,
+    };
+
+    formatter.statements(ast, None, None);
+
+    formatter.output
+}
+
+struct Formatter {
+    output: String,
+    depth: usize,
+    /// Comments that haven't been written yet, in the order that they appear in the source.
+    comments: VecDeque<(String, Span)>,
+    /// Where each raw string in the source starts.
+    raw_strings: Vec<Position>,
+    /// The line of the source that the previous item within the current block ended on, used to
+    /// keep blank lines between items.
+    line: Option<usize>,
+}
+impl Formatter {
+    /// Starts a new line for an item beginning on `line` of the source, keeping a blank line if
+    /// there was one before it.
+    fn begin(&mut self, line: usize) {
+        if self.line.is_some_and(|previous| line > previous + 1) {
+            self.output.push('\n');
+        }
+
+        self.indent();
+    }
+
+    /// Finishes the line of an item ending at `end` of the source, along with a comment following
+    /// it on the same line. `next` is where the next item starts, which the comment must be before.
+    fn finish(&mut self, end: &Position, next: Option<&Position>) {
+        if let Some((text, span)) = self.comments.front() {
+            if span.start.line() == end.line() && next.is_none_or(|next| &span.start < next) {
+                write!(self.output, " //{}", text.trim_end()).unwrap();
+                self.comments.pop_front();
+            }
+        }
+
+        self.output.push('\n');
+        self.line = Some(end.line());
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    /// Writes every comment before `position` on its own line. Every remaining comment is written
+    /// if there is no `position`.
+    fn comments_before(&mut self, position: Option<&Position>) {
+        while let Some((text, span)) = self
+            .comments
+            .pop_front_if(|(_, span)| position.is_none_or(|position| &span.start < position))
+        {
+            self.begin(span.start.line());
+            write!(self.output, "//{}", text.trim_end()).unwrap();
+            self.finish(&span.end, None);
+        }
+    }
+
+    /// Writes each of the statements followed by the final expression, one per line. `end` is
+    /// where the surrounding block ends, or [None] at the top level.
+    fn statements(
+        &mut self,
+        statements: &[AstNode],
+        expression: Option<&Expression>,
+        end: Option<&Position>,
+    ) {
+        for (i, statement) in statements.iter().enumerate() {
+            let start = statement_start(statement);
+            let next = statements.get(i + 1);
+            let next_start = next
+                .map(statement_start)
+                .or(expression.map(|expression| &expression.span.start))
+                .or(end);
+
+            self.comments_before(Some(start));
+            self.begin(start.line());
+
+            match statement {
+                AstNode::Let(let_node) => self.let_binding(let_node),
+                AstNode::Expression(statement) => {
+                    self.expression(statement, Placement::Free);
+
+                    // A block-like statement only needs a semicolon if it would otherwise become
+                    // the final expression of the block, or join onto a following negation
+                    let next_expression = match next {
+                        Some(AstNode::Expression(next)) => Some(next),
+                        Some(_) => None,
+                        None => expression,
+                    };
+                    if !statement.is_block_like()
+                        || (end.is_some() && next.is_none() && expression.is_none())
+                        || next_expression.is_some_and(starts_with_negation)
+                    {
+                        self.output.push(';');
+                    }
+                }
+                AstNode::Function(function) => self.function(function),
+                AstNode::Struct(struct_node) => self.struct_declaration(struct_node),
+                AstNode::Enum(enum_node) => self.enum_declaration(enum_node),
+            }
+
+            self.finish(statement_end(statement), next_start);
+        }
+
+        if let Some(expression) = expression {
+            self.comments_before(Some(&expression.span.start));
+            self.begin(expression.span.start.line());
+            self.expression(expression, Placement::Free);
+            self.finish(&expression.span.end, end);
+        }
+
+        self.comments_before(end);
+    }
+
+    /// Writes each attribute on its own line, leaving the line indented for the declaration.
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            write!(self.output, "#[{}", attribute.ident).unwrap();
+            if !attribute.arguments.is_empty() {
+                self.output.push('(');
+                self.list(&attribute.arguments, |formatter, (argument, _)| {
+                    formatter.output.push_str(argument)
+                });
+                self.output.push(')');
+            }
+            self.output.push_str("]\n");
+            self.indent();
+        }
+    }
+
+    fn let_binding(&mut self, let_node: &Let) {
+        self.attributes(&let_node.attributes);
+
+        write!(self.output, "let {}", let_node.ident).unwrap();
+        if let Some(annotation) = &let_node.type_annotation {
+            self.output.push_str(": ");
+            self.type_annotation(annotation);
+        }
+        self.output.push_str(" = ");
+        self.expression(&let_node.rhs, Placement::Free);
+        self.output.push(';');
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.attributes(&function.attributes);
+
+        write!(self.output, "fn {}", function.ident).unwrap();
+        self.type_parameters(&function.type_parameters);
+
+        self.output.push('(');
+        self.list(
+            &function.parameters,
+            |formatter, (parameter, annotation)| {
+                write!(formatter.output, "{parameter}: ").unwrap();
+                formatter.type_annotation(annotation);
+            },
+        );
+        self.output.push(')');
+
+        if let Some(return_type) = &function.return_type {
+            self.output.push_str(" -> ");
+            self.type_annotation(return_type);
+        }
+
+        self.output.push(' ');
+        self.block(&function.body);
+    }
+
+    fn struct_declaration(&mut self, struct_node: &Struct) {
+        write!(self.output, "struct {}", struct_node.ident).unwrap();
+        self.type_parameters(&struct_node.type_parameters);
+
+        self.declaration_body(
+            &struct_node.fields,
+            &struct_node.span.end,
+            |formatter, field| {
+                let (field, annotation) = field;
+                formatter.comments_before(Some(&annotation.span.start));
+                formatter.begin(annotation.span.start.line());
+
+                write!(formatter.output, "{field}: ").unwrap();
+                formatter.type_annotation(annotation);
+                formatter.output.push(',');
+                formatter.finish(&annotation.span.end, None);
+            },
+        );
+    }
+
+    fn enum_declaration(&mut self, enum_node: &Enum) {
+        write!(self.output, "enum {}", enum_node.ident).unwrap();
+        self.type_parameters(&enum_node.type_parameters);
+
+        self.declaration_body(
+            &enum_node.variants,
+            &enum_node.span.end,
+            |formatter, variant| {
+                let (variant, values) = variant;
+                formatter.indent();
+
+                formatter.output.push_str(variant);
+                if !values.is_empty() {
+                    formatter.output.push('(');
+                    formatter.list(values, Self::type_annotation);
+                    formatter.output.push(')');
+                }
+                formatter.output.push_str(",\n");
+            },
+        );
+    }
+
+    /// Writes the body of a struct or enum declaration, with each of its members on their own
+    /// line.
+    fn declaration_body<T>(
+        &mut self,
+        members: &[T],
+        end: &Position,
+        member: impl Fn(&mut Self, &T),
+    ) {
+        if members.is_empty() && self.comments_before_count(end) == 0 {
+            self.output.push_str(" {}");
+            return;
+        }
+
+        self.output.push_str(" {\n");
+        self.depth += 1;
+        let outer = self.line.take();
+
+        for item in members {
+            member(self, item);
+        }
+        self.comments_before(Some(end));
+
+        self.depth -= 1;
+        self.line = outer;
+        self.indent();
+        self.output.push('}');
+    }
+
+    /// The number of comments that haven't been written yet that are before `position`.
+    fn comments_before_count(&self, position: &Position) -> usize {
+        self.comments
+            .iter()
+            .take_while(|(_, span)| &span.start < position)
+            .count()
+    }
+
+    fn block(&mut self, block: &Block) {
+        if block.statements.is_empty()
+            && block.expression.is_none()
+            && self.comments_before_count(&block.span.end) == 0
+        {
+            self.output.push_str("{}");
+            return;
+        }
+
+        self.output.push_str("{\n");
+        self.depth += 1;
+        let outer = self.line.take();
+
+        self.statements(
+            &block.statements,
+            block.expression.as_deref(),
+            Some(&block.span.end),
+        );
+
+        self.depth -= 1;
+        self.line = outer;
+        self.indent();
+        self.output.push('}');
+    }
+
+    fn expression(&mut self, expression: &Expression, placement: Placement) {
+        let parenthesise = expression.requires_parentheses(placement);
+        if parenthesise {
+            self.output.push('(');
+        }
+
+        match &expression.kind {
+            ExpressionKind::Ident(ident) => self.output.push_str(ident),
+            ExpressionKind::Literal(literal) => self.literal(literal, &expression.span),
+            ExpressionKind::BinaryOperation {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                self.expression(lhs, Placement::Lhs(*operation));
+                let operator = match operation {
+                    BinaryOperationKind::Add => "+",
+                    BinaryOperationKind::Sub => "-",
+                    BinaryOperationKind::Mult => "*",
+                    BinaryOperationKind::Div => "/",
+                    BinaryOperationKind::Mod => "%",
+                    BinaryOperationKind::Exp => "^",
+                    BinaryOperationKind::And => "&",
+                    BinaryOperationKind::Or => "|",
+                    BinaryOperationKind::Xor => "~",
+                    BinaryOperationKind::Shl => "<<",
+                    BinaryOperationKind::Shr => ">>",
+                };
+                write!(self.output, " {operator} ").unwrap();
+                self.expression(rhs, Placement::Rhs(*operation));
+            }
+            ExpressionKind::UnaryOperation { operation, rhs } => {
+                match operation {
+                    UnaryOperationKind::Negative => self.output.push('-'),
+                    UnaryOperationKind::Complement => self.output.push('~'),
+                }
+                self.expression(rhs, Placement::Operand(*operation));
+            }
+            ExpressionKind::Call {
+                ident,
+                type_arguments,
+                arguments,
+            } => {
+                self.output.push_str(ident);
+                self.type_arguments(type_arguments);
+                self.arguments(arguments);
+            }
+            ExpressionKind::Variant {
+                ident,
+                type_arguments,
+                variant,
+                arguments,
+            } => {
+                self.output.push_str(ident);
+                self.type_arguments(type_arguments);
+                write!(self.output, "::{variant}").unwrap();
+                if !arguments.is_empty() {
+                    self.arguments(arguments);
+                }
+            }
+            ExpressionKind::Struct {
+                ident,
+                type_arguments,
+                fields,
+            } => {
+                self.output.push_str(ident);
+                self.type_arguments(type_arguments);
+
+                if fields.is_empty() {
+                    self.output.push_str(" {}");
+                } else {
+                    self.output.push_str(" { ");
+                    self.list(fields, |formatter, (field, value)| {
+                        write!(formatter.output, "{field}: ").unwrap();
+                        formatter.expression(value, Placement::Free);
+                    });
+                    self.output.push_str(" }");
+                }
+            }
+            ExpressionKind::Interpolation(parts) => {
+                self.output.push('"');
+                for part in parts {
+                    match part {
+                        InterpolationPart::Text(text) => self.string_text(text, &expression.span),
+                        InterpolationPart::Expression(expression) => {
+                            self.output.push('{');
+                            self.expression(expression, Placement::Free);
+                            self.output.push('}');
+                        }
+                    }
+                }
+                self.output.push('"');
+            }
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.output.push_str("if ");
+
+                // The `{` of a struct literal would be taken as the start of the body
+                if contains_struct(condition) {
+                    self.output.push('(');
+                    self.expression(condition, Placement::Free);
+                    self.output.push(')');
+                } else {
+                    self.expression(condition, Placement::Free);
+                }
+
+                self.output.push(' ');
+                self.block(then);
+
+                if let Some(otherwise) = otherwise {
+                    self.output.push_str(" else ");
+
+                    match (&otherwise.statements[..], &otherwise.expression) {
+                        ([], Some(expression))
+                            if matches!(expression.kind, ExpressionKind::If { .. }) =>
+                        {
+                            self.expression(expression, Placement::Free)
+                        }
+                        _ => self.block(otherwise),
+                    }
+                }
+            }
+        }
+
+        if parenthesise {
+            self.output.push(')');
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal, span: &Span) {
+        match literal {
+            Literal::Integer(integer) => write!(self.output, "{integer}").unwrap(),
+            Literal::Boolean(boolean) => write!(self.output, "{boolean}").unwrap(),
+            Literal::String(string) if self.raw_strings.contains(&span.start) => {
+                self.raw_string(string)
+            }
+            Literal::String(string) => {
+                self.output.push('"');
+                self.string_text(string, span);
+                self.output.push('"');
+            }
+        }
+    }
+
+    /// Writes a raw string with as few `#` as it needs, which is one more than the most that
+    /// follow a `"` within it.
+    fn raw_string(&mut self, string: &str) {
+        let hashes = string
+            .match_indices('"')
+            .map(|(i, _)| string[i + 1..].chars().take_while(|&c| c == '#').count() + 1)
+            .max()
+            .unwrap_or(0);
+        let hashes = "#".repeat(hashes);
+
+        write!(self.output, "r{hashes}\"{string}\"{hashes}").unwrap();
+    }
+
+    /// Writes text within a string spanning `span` of the source, escaping anything that
+    /// wouldn't otherwise be read back the same way.
+    fn string_text(&mut self, string: &str, span: &Span) {
+        // Line breaks are only kept in strings that were already written across multiple lines
+        let multiline = span.start.line() != span.end.line();
+
+        for c in string.chars() {
+            match c {
+                '"' | '\\' | '{' => write!(self.output, "\\{c}").unwrap(),
+                '\t' => self.output.push_str("\\t"),
+                '\r' => self.output.push_str("\\r"),
+                '\0' => self.output.push_str("\\0"),
+                '\n' if multiline => self.output.push('\n'),
+                '\n' => self.output.push_str("\\n"),
+                c if c.is_control() => write!(self.output, "\\u{{{:x}}}", c as u32).unwrap(),
+                c => self.output.push(c),
+            }
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[Expression]) {
+        self.output.push('(');
+        self.list(arguments, |formatter, argument| {
+            formatter.expression(argument, Placement::Free)
+        });
+        self.output.push(')');
+    }
+
+    fn type_parameters(&mut self, type_parameters: &[String]) {
+        if !type_parameters.is_empty() {
+            self.output.push('<');
+            self.list(type_parameters, |formatter, parameter| {
+                formatter.output.push_str(parameter)
+            });
+            self.output.push('>');
+        }
+    }
+
+    /// Writes explicit type arguments for a path, which are preceded by `::`.
+    fn type_arguments(&mut self, type_arguments: &[TypeAnnotation]) {
+        if !type_arguments.is_empty() {
+            self.output.push_str("::<");
+            self.list(type_arguments, Self::type_annotation);
+            self.output.push('>');
+        }
+    }
+
+    fn type_annotation(&mut self, annotation: &TypeAnnotation) {
+        self.output.push_str(&annotation.ident);
+
+        if !annotation.arguments.is_empty() {
+            self.output.push('<');
+            self.list(&annotation.arguments, Self::type_annotation);
+            self.output.push('>');
+        }
+    }
+
+    /// Writes each of the items separated by commas.
+    fn list<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T)) {
+        for (i, value) in items.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+
+            item(self, value);
+        }
+    }
+}
+
+/// Where a statement starts in the source, including any attributes attached to it.
+fn statement_start(statement: &AstNode) -> &Position {
+    match statement {
+        AstNode::Let(Let { attributes, .. }) | AstNode::Function(Function { attributes, .. })
+            if !attributes.is_empty() =>
+        {
+            &attributes[0].span.start
+        }
+        AstNode::Let(let_node) => &let_node.ident_span.start,
+        AstNode::Function(function) => &function.ident_span.start,
+        AstNode::Expression(expression) => &expression.span.start,
+        AstNode::Struct(struct_node) => &struct_node.span.start,
+        AstNode::Enum(enum_node) => &enum_node.span.start,
+    }
+}
+
+/// Where a statement ends in the source.
+fn statement_end(statement: &AstNode) -> &Position {
+    match statement {
+        AstNode::Let(let_node) => &let_node.rhs.span.end,
+        AstNode::Function(function) => &function.body.span.end,
+        AstNode::Expression(expression) => &expression.span.end,
+        AstNode::Struct(struct_node) => &struct_node.span.end,
+        AstNode::Enum(enum_node) => &enum_node.span.end,
+    }
+}
+
+/// Whether the expression is written starting with a `-`, which would continue a preceding
+/// expression as a subtraction.
+fn starts_with_negation(expression: &Expression) -> bool {
+    match &expression.kind {
+        ExpressionKind::UnaryOperation { .. } => true,
+        ExpressionKind::BinaryOperation { operation, lhs, .. } => {
+            !lhs.requires_parentheses(Placement::Lhs(*operation)) && starts_with_negation(lhs)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the expression contains a struct literal that wouldn't be surrounded by some other
+/// delimiter, such as the parentheses of a call.
+fn contains_struct(expression: &Expression) -> bool {
+    match &expression.kind {
+        ExpressionKind::Struct { .. } => true,
+        ExpressionKind::BinaryOperation { lhs, rhs, .. } => {
+            contains_struct(lhs) || contains_struct(rhs)
+        }
+        ExpressionKind::UnaryOperation { rhs, .. } => contains_struct(rhs),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_source, parse_source, SAMPLE};
+
+    /// Formats the source, checking that the output parses to the same AST and is unchanged by
+    /// formatting it again.
+    fn format(source: &str) -> String {
+        let formatted = format_source(source).unwrap();
+
+        assert_eq!(
+            parse_source(&formatted).unwrap(),
+            parse_source(source).unwrap()
+        );
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+
+        formatted
+    }
+
+    #[test]
+    fn canonical() {
+        assert_eq!(format(SAMPLE), SAMPLE);
+
+        assert_eq!(
+            format(
+                "// Declarations
+struct Pair<A,B>{first:A,second:B} enum Option<T>{Some(T),None}
+#[allow(unused_variable)]
+fn pick<T>(first:Boolean,a:T,b:T)->T{if first{a}else{b}} // trailing
+
+
+let p=Pair{first:1,second:\"a \\\"b\\\" \\\\c\"};let o=Option::<Integer>::None;
+let c = if (Pair { first: 1, second: 2 }) { 1 } else if false { 2 } else {
+  // inside
+  let d = 3; d
+};
+fn ends() { if true { print(1); }; }"
+            ),
+            "// Declarations
+struct Pair<A, B> {
+    first: A,
+    second: B,
+}
+enum Option<T> {
+    Some(T),
+    None,
+}
+#[allow(unused_variable)]
+fn pick<T>(first: Boolean, a: T, b: T) -> T {
+    if first {
+        a
+    } else {
+        b
+    }
+} // trailing
+
+let p = Pair { first: 1, second: \"a \\\"b\\\" \\\\c\" };
+let o = Option::<Integer>::None;
+let c = if (Pair { first: 1, second: 2 }) {
+    1
+} else if false {
+    2
+} else {
+    // inside
+    let d = 3;
+    d
+};
+fn ends() {
+    if true {
+        print(1);
+    };
+}
+"
+        );
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        assert_eq!(
+            format(
+                "let a = ((1 + 2)) * 3;
+let b = -(a * 2) - (-a) * 2 + (a + 1) - (a - 1);
+let c = a ^ (b ^ c) + (a ^ b) ^ c + (-a) ^ 2 + a * (-b);
+let d = if (f(Pair {}) + -(Pair {})) { (1) } else { 2 } + (if true { 1 } else { 2 });
+let e = -(a ^ 2) + 2 ^ (-1) - (-2 * 3);
+let f = (a & (b << 1)) | ((a ~ b) & ~(c % 2)) | (a | b) >> (1 + 1);"
+            ),
+            "let a = (1 + 2) * 3;
+let b = -(a * 2) - -a * 2 + (a + 1) - (a - 1);
+let c = a ^ b ^ c + (a ^ b) ^ c + (-a) ^ 2 + a * -b;
+let d = if (f(Pair {}) + -Pair {}) {
+    1
+} else {
+    2
+} + if true {
+    1
+} else {
+    2
+};
+let e = -a ^ 2 + 2 ^ -1 - -2 * 3;
+let f = a & b << 1 | (a ~ b) & ~(c % 2) | (a | b) >> 1 + 1;
+"
+        );
+    }
+
+    #[test]
+    fn strings() {
+        // Only the characters that can't be written directly are escaped
+        assert_eq!(
+            format(
+                r####"let a = r"C:\dir";
+let b = "tab\t\u{7}\u{e9}\"\n";
+let c = "two
+lines \
+    joined\n";
+let d = r###"a "# "## b"###;
+let e = "{b}\n";"####
+            ),
+            r####"let a = r"C:\dir";
+let b = "tab\t\u{7}é\"\n";
+let c = "two
+lines joined
+";
+let d = r###"a "# "## b"###;
+let e = "{b}\n";
+"####
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(
+            format(r#"let a = "{ name }: {(1 + 2) * 3} \{{"{ b }"}\}";"#),
+            "let a = \"{name}: {(1 + 2) * 3} \\{{\"{b}\"}}\";\n"
+        );
+    }
+
+    #[test]
+    fn semicolons() {
+        // A block-like statement keeps its semicolon wherever removing it would change the AST
+        assert_eq!(
+            format("if a { b; }; -c; if d { e; }; f; fn g() { if h { i; }; }"),
+            "if a {
+    b;
+};
+-c;
+if d {
+    e;
+}
+f;
+fn g() {
+    if h {
+        i;
+    };
+}
+"
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Functions provided by the application embedding the language, which scripts can call like any
+//! other function.
+
+use std::{collections::HashMap, rc::Rc};
+
+use thiserror::Error;
+
+use crate::{bytecode::Value, checks::typing::Type};
+
+/// The reasons that a function couldn't be registered.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HostError {
+    #[error("{0} is built into the language, so can't be replaced by a host function")]
+    Reserved(String),
+}
+
+/// A Rust function that can be called from scripts, along with the signature that the type
+/// checker sees.
+#[derive(Clone)]
+pub struct HostFunction {
+    pub parameters: Vec<Type>,
+    pub return_type: Type,
+    function: Rc<dyn Fn(Vec<Value>) -> Result<Value, String>>,
+}
+impl HostFunction {
+    /// Calls the function with values matching its parameters. An error message is returned if
+    /// the function fails.
+    pub fn call(&self, arguments: Vec<Value>) -> Result<Value, String> {
+        (self.function)(arguments)
+    }
+}
+
+/// Every function that the embedding application makes available to scripts.
+#[derive(Clone, Default)]
+pub struct Host {
+    functions: HashMap<String, HostFunction>,
+}
+impl Host {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a function that works directly with runtime values. The type checker ensures
+    /// that it is only called with arguments of the given types, and the function must return a
+    /// value of the return type. Registering `print` fails, as it is built into the language.
+    pub fn register(
+        &mut self,
+        name: &str,
+        parameters: Vec<Type>,
+        return_type: Type,
+        function: impl Fn(Vec<Value>) -> Result<Value, String> + 'static,
+    ) -> Result<&mut Self, HostError> {
+        self.insert(
+            name,
+            HostFunction {
+                parameters,
+                return_type,
+                function: Rc::new(function),
+            },
+        )
+    }
+
+    /// Registers a Rust closure, with the signature determined from the types of its parameters
+    /// and return value. Eg `host.register_fn("double", |x: isize| x * 2)`. Registering `print`
+    /// fails, as it is built into the language.
+    pub fn register_fn<Args>(
+        &mut self,
+        name: &str,
+        function: impl IntoHostFunction<Args>,
+    ) -> Result<&mut Self, HostError> {
+        self.insert(name, function.into_host_function())
+    }
+
+    fn insert(&mut self, name: &str, function: HostFunction) -> Result<&mut Self, HostError> {
+        if name == "print" {
+            return Err(HostError::Reserved(name.to_string()));
+        }
+
+        self.functions.insert(name.to_string(), function);
+        Ok(self)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&HostFunction> {
+        self.functions.get(name)
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = (&String, &HostFunction)> {
+        self.functions.iter()
+    }
+}
+
+/// A Rust type that can be passed to or returned from a host function, converting to and from the
+/// runtime value of a language type.
+pub trait HostValue: Sized {
+    fn ty() -> Type;
+
+    /// Converts a runtime value, if it is of the right type.
+    fn from_value(value: Value) -> Option<Self>;
+
+    fn into_value(self) -> Value;
+}
+impl HostValue for isize {
+    fn ty() -> Type {
+        Type::Integer
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Integer(integer) => Some(integer),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Integer(self)
+    }
+}
+impl HostValue for bool {
+    fn ty() -> Type {
+        Type::Boolean
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Boolean(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+impl HostValue for String {
+    fn ty() -> Type {
+        Type::String
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::String(string) => Some(string.to_string()),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::String(self.into())
+    }
+}
+impl HostValue for () {
+    fn ty() -> Type {
+        Type::Unit
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Unit => Some(()),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Unit
+    }
+}
+
+/// A Rust closure whose parameters and return value can all be converted to and from runtime
+/// values. `Args` is a tuple of the parameter types, which distinguishes closures of each arity.
+pub trait IntoHostFunction<Args> {
+    fn into_host_function(self) -> HostFunction;
+}
+
+macro_rules! impl_into_host_function {
+    ($($argument:ident),*) => {
+        impl<Function, Return, $($argument),*> IntoHostFunction<($($argument,)*)> for Function
+        where
+            Function: Fn($($argument),*) -> Return + 'static,
+            Return: HostValue,
+            $($argument: HostValue,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_host_function(self) -> HostFunction {
+                HostFunction {
+                    parameters: vec![$($argument::ty()),*],
+                    return_type: Return::ty(),
+                    function: Rc::new(move |arguments: Vec<Value>| {
+                        let mut arguments = arguments.into_iter();
+                        $(
+                            let $argument = arguments
+                                .next()
+                                .and_then($argument::from_value)
+                                .ok_or_else(|| format!("expected {} argument", $argument::ty()))?;
+                        )*
+
+                        Ok(self($($argument),*).into_value())
+                    }),
+                }
+            }
+        }
+    };
+}
+
+impl_into_host_function!();
+impl_into_host_function!(A);
+impl_into_host_function!(A, B);
+impl_into_host_function!(A, B, C);
+impl_into_host_function!(A, B, C, D);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion() {
+        let mut host = Host::new();
+        host.register_fn("repeat", |string: String, count: isize| {
+            string.repeat(count as usize)
+        })
+        .unwrap();
+
+        let repeat = host.get("repeat").unwrap();
+        assert_eq!(repeat.parameters, vec![Type::String, Type::Integer]);
+        assert_eq!(repeat.return_type, Type::String);
+        assert_eq!(
+            repeat.call(vec![Value::String("ab".into()), Value::Integer(3)]),
+            Ok(Value::String("ababab".into()))
+        );
+        assert_eq!(
+            repeat.call(vec![Value::Boolean(true), Value::Integer(3)]),
+            Err("expected String argument".to_string())
+        );
+    }
+
+    #[test]
+    fn reserved_names() {
+        assert_eq!(
+            Host::new().register_fn("print", |_: isize| ()).err(),
+            Some(HostError::Reserved("print".to_string()))
+        );
+    }
+}
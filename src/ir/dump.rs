@@ -0,0 +1,175 @@
+use std::fmt::{Display, Formatter, Result};
+
+use crate::{
+    parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+    token::Literal,
+};
+
+use super::{BlockId, Function, Instruction, InstructionKind, Module, Terminator, Value};
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+/// Writes a human readable listing of every function in the module, for debugging.
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{function}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "fn {}", self.name)?;
+        if !self.type_parameters.is_empty() {
+            write!(f, "<{}>", self.type_parameters.join(", "))?;
+        }
+
+        write!(f, "(")?;
+        for (i, (value, ty)) in self.parameters.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{value}: {ty}")?;
+        }
+        writeln!(f, ") -> {} {{", self.return_type)?;
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "{}:", BlockId(i))?;
+
+            for instruction in &block.instructions {
+                writeln!(f, "    {instruction}")?;
+            }
+
+            if let Some(terminator) = &block.terminator {
+                writeln!(f, "    {terminator}")?;
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}: {} = ", self.value, self.ty)?;
+
+        match &self.kind {
+            InstructionKind::Constant(literal) => match literal {
+                Literal::Integer(integer) => write!(f, "const {integer}"),
+                Literal::Boolean(boolean) => write!(f, "const {boolean}"),
+                Literal::String(string) => write!(f, "const {string:?}"),
+            },
+            InstructionKind::Unit => write!(f, "unit"),
+            InstructionKind::Binary {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                let operation = match operation {
+                    BinaryOperationKind::Add => "add",
+                    BinaryOperationKind::Sub => "sub",
+                    BinaryOperationKind::Mult => "mul",
+                    BinaryOperationKind::Div => "div",
+                    BinaryOperationKind::Mod => "rem",
+                    BinaryOperationKind::Exp => "exp",
+                    BinaryOperationKind::And => "and",
+                    BinaryOperationKind::Or => "or",
+                    BinaryOperationKind::Xor => "xor",
+                    BinaryOperationKind::Shl => "shl",
+                    BinaryOperationKind::Shr => "shr",
+                };
+
+                write!(f, "{operation} {lhs}, {rhs}")
+            }
+            InstructionKind::Unary { operation, rhs } => match operation {
+                UnaryOperationKind::Negative => write!(f, "neg {rhs}"),
+                UnaryOperationKind::Complement => write!(f, "not {rhs}"),
+            },
+            InstructionKind::Call {
+                function,
+                type_arguments,
+                arguments,
+            } => {
+                write!(f, "call {function}")?;
+                if !type_arguments.is_empty() {
+                    let type_arguments = type_arguments
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>();
+                    write!(f, "<{}>", type_arguments.join(", "))?;
+                }
+
+                write!(f, "({})", join(arguments))
+            }
+            InstructionKind::Variant {
+                ident,
+                variant,
+                arguments,
+            } => {
+                write!(f, "variant {ident}::{variant}")?;
+                if !arguments.is_empty() {
+                    write!(f, "({})", join(arguments))?;
+                }
+
+                Ok(())
+            }
+            InstructionKind::Struct { ident, fields } => {
+                let fields = fields
+                    .iter()
+                    .map(|(field, value)| format!("{field}: {value}"))
+                    .collect::<Vec<_>>();
+
+                write!(f, "struct {ident} {{ {} }}", fields.join(", "))
+            }
+            InstructionKind::Interpolate(parts) => write!(f, "interpolate {}", join(parts)),
+            InstructionKind::Phi(incoming) => {
+                let incoming = incoming
+                    .iter()
+                    .map(|(block, value)| format!("{block}: {value}"))
+                    .collect::<Vec<_>>();
+
+                write!(f, "phi [{}]", incoming.join(", "))
+            }
+        }
+    }
+}
+
+impl Display for Terminator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jump {target}"),
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => write!(f, "branch {condition}, {then}, {otherwise}"),
+            Terminator::Return(value) => write!(f, "return {value}"),
+        }
+    }
+}
+
+fn join(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
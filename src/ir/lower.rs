@@ -0,0 +1,315 @@
+use crate::{
+    checks::typing::{Type, TypedAstNode, TypedBlock, TypedExpression, TypedExpressionKind},
+    lexer::cursor::Span,
+};
+
+use super::{Block, BlockId, Function, Instruction, InstructionKind, Module, Terminator, Value};
+
+/// Lowers a type checked program into SSA form. Each function is lowered into its own
+/// [Function], and the top level statements are lowered into a final `<main>` function.
+pub fn lower(typed_ast: &[TypedAstNode]) -> Module {
+    let mut functions = Vec::new();
+
+    for node in typed_ast {
+        if let TypedAstNode::Function(function) = node {
+            let mut lowerer = FunctionLowerer::new(
+                function.ident.clone(),
+                function.span.clone(),
+                function.type_parameters.clone(),
+                &function.parameters,
+                function.return_type.clone(),
+            );
+
+            let result = lowerer.lower_block(&function.body);
+            functions.push(lowerer.finish(result));
+        }
+    }
+
+    let mut main = FunctionLowerer::new(
+        "<main>".to_string(),
+        Span::default(),
+        Vec::new(),
+        &[],
+        Type::Unit,
+    );
+    for node in typed_ast {
+        match node {
+            TypedAstNode::Let(_) | TypedAstNode::Expression(_) => main.lower_statement(node),
+            TypedAstNode::Function(_) => (),
+        }
+    }
+    let result = main.define(Type::Unit, InstructionKind::Unit, Span::default());
+    functions.push(main.finish(result));
+
+    Module { functions }
+}
+
+/// Lowers the body of a single function, appending instructions to the current block. As
+/// bindings can't be reassigned, each local simply refers to the value it was bound to.
+struct FunctionLowerer {
+    function: Function,
+    current: BlockId,
+    /// The ident and value of each local in scope, in the order that they were declared.
+    locals: Vec<(String, Value)>,
+    /// The number of values that have been defined.
+    values: usize,
+}
+impl FunctionLowerer {
+    fn new(
+        name: String,
+        span: Span,
+        type_parameters: Vec<String>,
+        parameters: &[(String, Type)],
+        return_type: Type,
+    ) -> Self {
+        let mut lowerer = Self {
+            function: Function {
+                name,
+                span,
+                type_parameters,
+                parameters: Vec::new(),
+                return_type,
+                blocks: vec![Block::default()],
+            },
+            current: Function::ENTRY,
+            locals: Vec::new(),
+            values: 0,
+        };
+
+        for (ident, ty) in parameters {
+            let value = lowerer.value();
+            lowerer.function.parameters.push((value, ty.clone()));
+            lowerer.locals.push((ident.clone(), value));
+        }
+
+        lowerer
+    }
+
+    /// Returns the function once its final block has been terminated by returning `result`.
+    fn finish(mut self, result: Value) -> Function {
+        self.terminate(Terminator::Return(result));
+        self.function
+    }
+
+    fn value(&mut self) -> Value {
+        self.values += 1;
+        Value(self.values - 1)
+    }
+
+    /// Appends an instruction to the current block, returning the value that it defines.
+    fn define(&mut self, ty: Type, kind: InstructionKind, span: Span) -> Value {
+        let value = self.value();
+
+        self.function.blocks[self.current.0]
+            .instructions
+            .push(Instruction {
+                value,
+                ty,
+                kind,
+                span,
+            });
+
+        value
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.function.blocks.push(Block::default());
+        BlockId(self.function.blocks.len() - 1)
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        self.function.blocks[self.current.0].terminator = Some(terminator);
+    }
+
+    fn lower_statement(&mut self, statement: &TypedAstNode) {
+        match statement {
+            TypedAstNode::Let(let_node) => {
+                let value = self.lower_expression(&let_node.rhs);
+                self.locals.push((let_node.ident.clone(), value));
+            }
+            TypedAstNode::Expression(expression) => {
+                self.lower_expression(expression);
+            }
+            // Functions can only be declared at the top level
+            TypedAstNode::Function(_) => unreachable!(),
+        }
+    }
+
+    /// Lowers each of the statements in a block, returning the value that it evaluates to.
+    fn lower_block(&mut self, block: &TypedBlock) -> Value {
+        let scope = self.locals.len();
+
+        for statement in &block.statements {
+            self.lower_statement(statement);
+        }
+
+        let value = match &block.expression {
+            Some(expression) => self.lower_expression(expression),
+            None => self.define(Type::Unit, InstructionKind::Unit, Span::default()),
+        };
+
+        self.locals.truncate(scope);
+
+        value
+    }
+
+    fn lower_expression(&mut self, expression: &TypedExpression) -> Value {
+        let span = expression.span.clone();
+        let ty = expression.ty.clone();
+
+        let kind = match &expression.kind {
+            TypedExpressionKind::Ident(ident) => {
+                return self
+                    .locals
+                    .iter()
+                    .rfind(|(local, _)| local == ident)
+                    .map(|(_, value)| *value)
+                    .expect("type checker to reject unknown idents")
+            }
+            TypedExpressionKind::Literal(literal) => InstructionKind::Constant(literal.clone()),
+            TypedExpressionKind::BinaryOperation {
+                operation,
+                lhs,
+                rhs,
+            } => InstructionKind::Binary {
+                operation: *operation,
+                lhs: self.lower_expression(lhs),
+                rhs: self.lower_expression(rhs),
+            },
+            TypedExpressionKind::UnaryOperation { operation, rhs } => InstructionKind::Unary {
+                operation: *operation,
+                rhs: self.lower_expression(rhs),
+            },
+            TypedExpressionKind::Call {
+                ident,
+                type_arguments,
+                arguments,
+            } => InstructionKind::Call {
+                function: ident.clone(),
+                type_arguments: type_arguments.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|argument| self.lower_expression(argument))
+                    .collect(),
+            },
+            TypedExpressionKind::Variant {
+                ident,
+                variant,
+                arguments,
+            } => InstructionKind::Variant {
+                ident: ident.clone(),
+                variant: variant.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|argument| self.lower_expression(argument))
+                    .collect(),
+            },
+            TypedExpressionKind::Struct { ident, fields } => InstructionKind::Struct {
+                ident: ident.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), self.lower_expression(value)))
+                    .collect(),
+            },
+            TypedExpressionKind::Interpolation(parts) => InstructionKind::Interpolate(
+                parts
+                    .iter()
+                    .map(|part| self.lower_expression(part))
+                    .collect(),
+            ),
+            TypedExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.lower_expression(condition);
+
+                let then_block = self.new_block();
+                let otherwise_block = self.new_block();
+                let join = self.new_block();
+                self.terminate(Terminator::Branch {
+                    condition,
+                    then: then_block,
+                    otherwise: otherwise_block,
+                });
+
+                // Lowering a branch may add further blocks, so the one that jumps to the join
+                // isn't necessarily the one that the branch started in
+                let mut incoming = Vec::new();
+                for (block, branch) in [
+                    (then_block, Some(then)),
+                    (otherwise_block, otherwise.as_ref()),
+                ] {
+                    self.current = block;
+                    let value = match branch {
+                        Some(branch) => self.lower_block(branch),
+                        None => self.define(Type::Unit, InstructionKind::Unit, span.clone()),
+                    };
+
+                    incoming.push((self.current, value));
+                    self.terminate(Terminator::Jump(join));
+                }
+
+                self.current = join;
+                InstructionKind::Phi(incoming)
+            }
+        };
+
+        self.define(ty, kind, span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_end;
+
+    #[test]
+    fn dump() {
+        let (_, typed_ast) = front_end(
+            "fn abs(x: Integer, negative: Boolean) -> Integer {
+                if negative { let y = -x; y } else { x }
+            }
+            let a = abs(2, true);
+            if false { print(a); }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            lower(&typed_ast).to_string(),
+            "fn abs(%0: Integer, %1: Boolean) -> Integer {
+bb0:
+    branch %1, bb1, bb2
+bb1:
+    %2: Integer = neg %0
+    jump bb3
+bb2:
+    jump bb3
+bb3:
+    %3: Integer = phi [bb1: %2, bb2: %0]
+    return %3
+}
+
+fn <main>() -> Unit {
+bb0:
+    %0: Integer = const 2
+    %1: Boolean = const true
+    %2: Integer = call abs(%0, %1)
+    %3: Boolean = const false
+    branch %3, bb1, bb2
+bb1:
+    %4: Unit = call print<Integer>(%2)
+    %5: Unit = unit
+    jump bb3
+bb2:
+    %6: Unit = unit
+    jump bb3
+bb3:
+    %7: Unit = phi [bb1: %5, bb2: %6]
+    %8: Unit = unit
+    return %8
+}
+"
+        );
+    }
+}
@@ -0,0 +1,371 @@
+//! An intermediate representation in static single assignment form. Each function is made up of
+//! basic blocks, where every value is defined exactly once, and values that depend on the path
+//! taken through the function are merged by phi nodes.
+
+use std::mem;
+
+use crate::{
+    checks::typing::Type,
+    lexer::cursor::Span,
+    parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+    token::Literal,
+};
+
+pub use self::{lower::*, verify::*};
+
+mod dump;
+mod lower;
+mod verify;
+
+/// A value defined by an instruction or parameter, written as `%n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Value(pub usize);
+
+/// The index of a basic block within its function, written as `bbn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+/// Each of the operations that an instruction can perform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstructionKind {
+    Constant(Literal),
+    Unit,
+    Binary {
+        operation: BinaryOperationKind,
+        lhs: Value,
+        rhs: Value,
+    },
+    Unary {
+        operation: UnaryOperationKind,
+        rhs: Value,
+    },
+    /// A call to either a declared function or an intrinsic.
+    Call {
+        function: String,
+        type_arguments: Vec<Type>,
+        arguments: Vec<Value>,
+    },
+    Variant {
+        ident: String,
+        variant: String,
+        arguments: Vec<Value>,
+    },
+    Struct {
+        ident: String,
+        fields: Vec<(String, Value)>,
+    },
+    /// Displays each value in order, joining them into a string.
+    Interpolate(Vec<Value>),
+    /// Takes the value paired with the predecessor that control arrived from. Phis must come
+    /// before every other instruction in their block.
+    Phi(Vec<(BlockId, Value)>),
+}
+impl InstructionKind {
+    /// Every value that the instruction uses.
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            InstructionKind::Constant(_) | InstructionKind::Unit => Vec::new(),
+            InstructionKind::Binary { lhs, rhs, .. } => vec![*lhs, *rhs],
+            InstructionKind::Unary { rhs, .. } => vec![*rhs],
+            InstructionKind::Call { arguments, .. }
+            | InstructionKind::Variant { arguments, .. }
+            | InstructionKind::Interpolate(arguments) => arguments.clone(),
+            InstructionKind::Struct { fields, .. } => {
+                fields.iter().map(|(_, value)| *value).collect()
+            }
+            InstructionKind::Phi(incoming) => incoming.iter().map(|(_, value)| *value).collect(),
+        }
+    }
+
+    /// Every value that the instruction uses, so that they can be replaced.
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            InstructionKind::Constant(_) | InstructionKind::Unit => Vec::new(),
+            InstructionKind::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            InstructionKind::Unary { rhs, .. } => vec![rhs],
+            InstructionKind::Call { arguments, .. }
+            | InstructionKind::Variant { arguments, .. }
+            | InstructionKind::Interpolate(arguments) => arguments.iter_mut().collect(),
+            InstructionKind::Struct { fields, .. } => {
+                fields.iter_mut().map(|(_, value)| value).collect()
+            }
+            InstructionKind::Phi(incoming) => incoming.iter_mut().map(|(_, value)| value).collect(),
+        }
+    }
+}
+
+/// An instruction that defines `value`, along with the span of source it was lowered from.
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub value: Value,
+    pub ty: Type,
+    pub kind: InstructionKind,
+    pub span: Span,
+}
+
+/// The instruction that ends a basic block, transferring control elsewhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: Value,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(Value),
+}
+impl Terminator {
+    /// The blocks that control may be transferred to.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![*then, *otherwise],
+            Terminator::Return(_) => Vec::new(),
+        }
+    }
+
+    /// Every value that the terminator uses.
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Terminator::Jump(_) => Vec::new(),
+            Terminator::Branch { condition, .. } => vec![*condition],
+            Terminator::Return(value) => vec![*value],
+        }
+    }
+
+    /// Every value that the terminator uses, so that they can be replaced.
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Terminator::Jump(_) => Vec::new(),
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Return(value) => vec![value],
+        }
+    }
+}
+
+/// A sequence of instructions that always execute together. The terminator is only missing
+/// whilst the block is being built.
+#[derive(Debug, Clone, Default)]
+pub struct Block {
+    pub instructions: Vec<Instruction>,
+    pub terminator: Option<Terminator>,
+}
+
+/// A function made up of basic blocks, starting from [Function::ENTRY].
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    /// The span of the function's ident, which is empty for the top level statements.
+    pub span: Span,
+    pub type_parameters: Vec<String>,
+    pub parameters: Vec<(Value, Type)>,
+    pub return_type: Type,
+    pub blocks: Vec<Block>,
+}
+impl Function {
+    pub const ENTRY: BlockId = BlockId(0);
+
+    /// The successors of a block, ignoring any that don't exist.
+    fn successors(&self, block: BlockId) -> Vec<BlockId> {
+        self.blocks[block.0]
+            .terminator
+            .iter()
+            .flat_map(Terminator::successors)
+            .filter(|successor| successor.0 < self.blocks.len())
+            .collect()
+    }
+
+    /// The predecessors of every block, indexed by block. A block that branches to the same
+    /// successor twice is listed twice.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+
+        for block in 0..self.blocks.len() {
+            for successor in self.successors(BlockId(block)) {
+                predecessors[successor.0].push(BlockId(block));
+            }
+        }
+
+        predecessors
+    }
+
+    /// Every block that is reachable from the entry, ordered so that each block comes before its
+    /// successors, ignoring back edges.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::new();
+        // Each block on the stack, along with whether its successors have been visited yet
+        let mut stack = vec![(Self::ENTRY, false)];
+
+        while let Some((block, finished)) = stack.pop() {
+            if finished {
+                postorder.push(block);
+                continue;
+            }
+            if block.0 >= self.blocks.len() || visited[block.0] {
+                continue;
+            }
+
+            visited[block.0] = true;
+            stack.push((block, true));
+            // The last successor is visited first, so that the first comes earliest in the order
+            for successor in self.successors(block) {
+                stack.push((successor, false));
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    /// Removes every block that can't be reached from the entry, along with the incoming values
+    /// that phis had from them. The remaining blocks keep their order, but are renumbered.
+    pub fn remove_unreachable_blocks(&mut self) {
+        let mut reachable = vec![false; self.blocks.len()];
+        for block in self.reverse_postorder() {
+            reachable[block.0] = true;
+        }
+
+        let mut next = 0;
+        let renumbered = reachable
+            .iter()
+            .map(|reachable| {
+                reachable.then(|| {
+                    next += 1;
+                    BlockId(next - 1)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        self.blocks = mem::take(&mut self.blocks)
+            .into_iter()
+            .zip(reachable)
+            .filter_map(|(block, reachable)| reachable.then_some(block))
+            .collect();
+
+        let renumber = |block: &mut BlockId| {
+            *block = renumbered[block.0].expect("reachable blocks to only follow reachable blocks")
+        };
+        for block in &mut self.blocks {
+            match &mut block.terminator {
+                Some(Terminator::Jump(target)) => renumber(target),
+                Some(Terminator::Branch {
+                    then, otherwise, ..
+                }) => {
+                    renumber(then);
+                    renumber(otherwise);
+                }
+                Some(Terminator::Return(_)) | None => (),
+            }
+        }
+
+        // A phi may also have arrived from a block that is still reachable, but no longer
+        // branches to it
+        let predecessors = self.predecessors();
+        for (block, predecessors) in self.blocks.iter_mut().zip(predecessors) {
+            for instruction in &mut block.instructions {
+                if let InstructionKind::Phi(incoming) = &mut instruction.kind {
+                    incoming.retain_mut(|(block, _)| match renumbered[block.0] {
+                        Some(renumbered) if predecessors.contains(&renumbered) => {
+                            *block = renumbered;
+                            true
+                        }
+                        _ => false,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Finds the immediate dominator of every block.
+    pub fn dominators(&self) -> Dominators {
+        let order = self.reverse_postorder();
+        let predecessors = self.predecessors();
+
+        let mut position = vec![usize::MAX; self.blocks.len()];
+        for (i, block) in order.iter().enumerate() {
+            position[block.0] = i;
+        }
+
+        let mut immediate = vec![None; self.blocks.len()];
+        if !self.blocks.is_empty() {
+            immediate[Self::ENTRY.0] = Some(Self::ENTRY);
+        }
+
+        // Walks up the dominator tree from both blocks until they meet
+        let intersect = |immediate: &[Option<BlockId>], mut a: BlockId, mut b: BlockId| {
+            while a != b {
+                while position[a.0] > position[b.0] {
+                    a = immediate[a.0].expect("processed blocks to have a dominator");
+                }
+                while position[b.0] > position[a.0] {
+                    b = immediate[b.0].expect("processed blocks to have a dominator");
+                }
+            }
+
+            a
+        };
+
+        // Iterate until a fixed point, as loops may require multiple passes
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for block in order.iter().skip(1) {
+                let dominator = predecessors[block.0]
+                    .iter()
+                    .filter(|predecessor| immediate[predecessor.0].is_some())
+                    .fold(None, |dominator, predecessor| {
+                        Some(match dominator {
+                            None => *predecessor,
+                            Some(dominator) => intersect(&immediate, *predecessor, dominator),
+                        })
+                    });
+
+                if immediate[block.0] != dominator {
+                    immediate[block.0] = dominator;
+                    changed = true;
+                }
+            }
+        }
+
+        Dominators { immediate }
+    }
+}
+
+/// The dominator tree of a function. A block dominates another if every path from the entry to
+/// the other block passes through it.
+#[derive(Debug)]
+pub struct Dominators {
+    /// The immediate dominator of each block, which is [None] for unreachable blocks. The entry
+    /// is its own immediate dominator.
+    immediate: Vec<Option<BlockId>>,
+}
+impl Dominators {
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.immediate[block.0].is_some()
+    }
+
+    /// Whether `a` dominates `b`. Every block dominates itself.
+    pub fn dominates(&self, a: BlockId, mut b: BlockId) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+
+            match self.immediate[b.0] {
+                Some(dominator) if dominator != b => b = dominator,
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// A program lowered into SSA form, with a function for each function declaration followed by
+/// one for the top level statements.
+#[derive(Debug, Clone, Default)]
+pub struct Module {
+    pub functions: Vec<Function>,
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use super::{BlockId, Function, InstructionKind, Module, Value};
+
+/// All of the ways that a function can violate the invariants of SSA form.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VerifyErrorKind {
+    #[error("{0} has no terminator")]
+    MissingTerminator(BlockId),
+    #[error("{0} branches to {1}, which doesn't exist")]
+    InvalidTarget(BlockId, BlockId),
+    #[error("{0} is unreachable")]
+    UnreachableBlock(BlockId),
+    #[error("{0} is defined more than once")]
+    Redefined(Value),
+    #[error("{value} is used in {block} but never defined")]
+    Undefined { value: Value, block: BlockId },
+    #[error("{value} is used in {block} before it is defined")]
+    UsedBeforeDefinition { value: Value, block: BlockId },
+    #[error("{value} is used in {block}, which its definition doesn't dominate")]
+    NotDominated { value: Value, block: BlockId },
+    #[error("phi {0} follows an instruction that isn't a phi")]
+    MisplacedPhi(Value),
+    #[error("the incoming blocks of phi {value} don't match the predecessors of {block}")]
+    PhiPredecessors { value: Value, block: BlockId },
+}
+
+/// A verification error, along with the name of the function that it was found in.
+#[derive(Debug, Error)]
+#[error("{function}: {kind}")]
+pub struct VerifyError {
+    pub function: String,
+    pub kind: VerifyErrorKind,
+}
+
+/// Checks that every function within the module is well formed SSA. Every value must be defined
+/// exactly once, and its definition must dominate each of its uses.
+pub fn verify(module: &Module) -> Result<(), VerifyError> {
+    for function in &module.functions {
+        verify_function(function).map_err(|kind| VerifyError {
+            function: function.name.clone(),
+            kind,
+        })?;
+    }
+
+    Ok(())
+}
+
+fn verify_function(function: &Function) -> Result<(), VerifyErrorKind> {
+    for (i, block) in function.blocks.iter().enumerate() {
+        let terminator = block
+            .terminator
+            .as_ref()
+            .ok_or(VerifyErrorKind::MissingTerminator(BlockId(i)))?;
+
+        for successor in terminator.successors() {
+            if successor.0 >= function.blocks.len() {
+                return Err(VerifyErrorKind::InvalidTarget(BlockId(i), successor));
+            }
+        }
+    }
+
+    let dominators = function.dominators();
+    for block in 0..function.blocks.len() {
+        if !dominators.is_reachable(BlockId(block)) {
+            return Err(VerifyErrorKind::UnreachableBlock(BlockId(block)));
+        }
+    }
+
+    // The block and position of each definition. Parameters come before every instruction of the
+    // entry block, and the terminator comes after every instruction of its block.
+    let mut definitions = HashMap::new();
+    for (value, _) in &function.parameters {
+        if definitions.insert(*value, (Function::ENTRY, 0)).is_some() {
+            return Err(VerifyErrorKind::Redefined(*value));
+        }
+    }
+    for (i, block) in function.blocks.iter().enumerate() {
+        for (position, instruction) in block.instructions.iter().enumerate() {
+            if definitions
+                .insert(instruction.value, (BlockId(i), position + 1))
+                .is_some()
+            {
+                return Err(VerifyErrorKind::Redefined(instruction.value));
+            }
+        }
+    }
+
+    // Checks that a value is available at a position within a block
+    let available = |value: Value, block: BlockId, position: usize| {
+        let (definition, defined_at) = *definitions
+            .get(&value)
+            .ok_or(VerifyErrorKind::Undefined { value, block })?;
+
+        if definition == block && defined_at >= position {
+            Err(VerifyErrorKind::UsedBeforeDefinition { value, block })
+        } else if !dominators.dominates(definition, block) {
+            Err(VerifyErrorKind::NotDominated { value, block })
+        } else {
+            Ok(())
+        }
+    };
+
+    let predecessors = function.predecessors();
+    for (i, block) in function.blocks.iter().enumerate() {
+        let id = BlockId(i);
+        let mut phis = true;
+
+        for (position, instruction) in block.instructions.iter().enumerate() {
+            let InstructionKind::Phi(incoming) = &instruction.kind else {
+                phis = false;
+
+                for operand in instruction.kind.operands() {
+                    available(operand, id, position + 1)?;
+                }
+                continue;
+            };
+
+            if !phis {
+                return Err(VerifyErrorKind::MisplacedPhi(instruction.value));
+            }
+
+            let mut incoming_blocks = incoming.iter().map(|(block, _)| *block).collect::<Vec<_>>();
+            let mut expected_blocks = predecessors[i].clone();
+            incoming_blocks.sort();
+            expected_blocks.sort();
+            if incoming_blocks != expected_blocks {
+                return Err(VerifyErrorKind::PhiPredecessors {
+                    value: instruction.value,
+                    block: id,
+                });
+            }
+
+            // Each incoming value is used at the end of the predecessor it arrives from
+            for (predecessor, value) in incoming {
+                let end = function.blocks[predecessor.0].instructions.len() + 1;
+                available(*value, *predecessor, end)?;
+            }
+        }
+
+        let terminator = block
+            .terminator
+            .as_ref()
+            .expect("terminators to be checked");
+        for operand in terminator.operands() {
+            available(operand, id, block.instructions.len() + 1)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        checks::typing::Type,
+        front_end,
+        ir::{lower, Block, Instruction, Terminator},
+        lexer::cursor::Span,
+    };
+
+    fn instruction(value: usize, kind: InstructionKind) -> Instruction {
+        Instruction {
+            value: Value(value),
+            ty: Type::Boolean,
+            kind,
+            span: Span::default(),
+        }
+    }
+
+    /// A function with a single parameter, that branches on it from the entry to two blocks which
+    /// both jump to a final block.
+    fn diamond(blocks: [Vec<Instruction>; 4]) -> Function {
+        let [entry, then, otherwise, join] = blocks;
+
+        Function {
+            name: "diamond".to_string(),
+            span: Span::default(),
+            type_parameters: Vec::new(),
+            parameters: vec![(Value(0), Type::Boolean)],
+            return_type: Type::Boolean,
+            blocks: vec![
+                Block {
+                    instructions: entry,
+                    terminator: Some(Terminator::Branch {
+                        condition: Value(0),
+                        then: BlockId(1),
+                        otherwise: BlockId(2),
+                    }),
+                },
+                Block {
+                    instructions: then,
+                    terminator: Some(Terminator::Jump(BlockId(3))),
+                },
+                Block {
+                    instructions: otherwise,
+                    terminator: Some(Terminator::Jump(BlockId(3))),
+                },
+                Block {
+                    instructions: join,
+                    terminator: Some(Terminator::Return(Value(0))),
+                },
+            ],
+        }
+    }
+
+    fn not(value: usize, rhs: usize) -> Instruction {
+        instruction(
+            value,
+            InstructionKind::Call {
+                function: "not".to_string(),
+                type_arguments: Vec::new(),
+                arguments: vec![Value(rhs)],
+            },
+        )
+    }
+
+    fn phi(value: usize, incoming: &[(usize, usize)]) -> Instruction {
+        instruction(
+            value,
+            InstructionKind::Phi(
+                incoming
+                    .iter()
+                    .map(|(block, value)| (BlockId(*block), Value(*value)))
+                    .collect(),
+            ),
+        )
+    }
+
+    #[test]
+    fn lowered_programs() {
+        let (_, typed_ast) = front_end(
+            "fn pick<T>(a: Boolean, b: Boolean, x: T, y: T, z: T) -> T {
+                if a { if b { x } else { y } } else if b { let w = z; w } else { z }
+            }
+            if true { print(pick(false, true, 1, 2, 3)); }",
+        )
+        .unwrap();
+
+        verify(&lower(&typed_ast)).unwrap();
+    }
+
+    #[test]
+    fn valid_diamond() {
+        verify_function(&diamond([
+            vec![not(1, 0)],
+            vec![not(2, 1)],
+            Vec::new(),
+            vec![phi(3, &[(1, 2), (2, 1)]), not(4, 3)],
+        ]))
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_functions() {
+        let check = |blocks| verify_function(&diamond(blocks)).unwrap_err();
+
+        assert_eq!(
+            check([vec![not(1, 1)], Vec::new(), Vec::new(), Vec::new()]),
+            VerifyErrorKind::UsedBeforeDefinition {
+                value: Value(1),
+                block: BlockId(0)
+            }
+        );
+        // Neither branch dominates the join
+        assert_eq!(
+            check([Vec::new(), vec![not(1, 0)], Vec::new(), vec![not(2, 1)]]),
+            VerifyErrorKind::NotDominated {
+                value: Value(1),
+                block: BlockId(3)
+            }
+        );
+        assert_eq!(
+            check([Vec::new(), vec![not(1, 0)], vec![not(1, 0)], Vec::new()]),
+            VerifyErrorKind::Redefined(Value(1))
+        );
+        assert_eq!(
+            check([Vec::new(), Vec::new(), Vec::new(), vec![not(1, 5)]]),
+            VerifyErrorKind::Undefined {
+                value: Value(5),
+                block: BlockId(3)
+            }
+        );
+        assert_eq!(
+            check([Vec::new(), Vec::new(), Vec::new(), vec![phi(1, &[(1, 0)])]]),
+            VerifyErrorKind::PhiPredecessors {
+                value: Value(1),
+                block: BlockId(3)
+            }
+        );
+        // The incoming value from the first branch is defined in the second
+        assert_eq!(
+            check([
+                Vec::new(),
+                Vec::new(),
+                vec![not(1, 0)],
+                vec![phi(2, &[(1, 1), (2, 1)])]
+            ]),
+            VerifyErrorKind::NotDominated {
+                value: Value(1),
+                block: BlockId(1)
+            }
+        );
+        assert_eq!(
+            check([
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+                vec![not(1, 0), phi(2, &[(1, 0), (2, 0)])]
+            ]),
+            VerifyErrorKind::MisplacedPhi(Value(2))
+        );
+
+        let mut function = diamond(Default::default());
+        function.blocks[0].terminator = Some(Terminator::Jump(BlockId(1)));
+        assert_eq!(
+            verify_function(&function).unwrap_err(),
+            VerifyErrorKind::UnreachableBlock(BlockId(2))
+        );
+
+        function.blocks[1].terminator = Some(Terminator::Jump(BlockId(7)));
+        assert_eq!(
+            verify_function(&function).unwrap_err(),
+            VerifyErrorKind::InvalidTarget(BlockId(1), BlockId(7))
+        );
+
+        function.blocks[1].terminator = None;
+        assert_eq!(
+            verify_function(&function).unwrap_err(),
+            VerifyErrorKind::MissingTerminator(BlockId(1))
+        );
+    }
+}
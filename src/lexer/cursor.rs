@@ -1,6 +1,7 @@
 use std::{fmt::Display, iter::Peekable, str::Chars};
 
-#[derive(Debug, Clone)]
+/// Positions are ordered by where they appear within the source.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Position {
     line: usize,
     character: usize,
@@ -13,6 +14,19 @@ impl Position {
         }
     }
 
+    /// Creates a position at a zero-indexed line and character.
+    pub fn at(line: usize, character: usize) -> Self {
+        Self { line, character }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn character(&self) -> usize {
+        self.character
+    }
+
     pub fn next_line(&mut self) {
         self.line += 1;
         self.character = 0;
@@ -28,10 +42,41 @@ impl Display for Position {
     }
 }
 
+/// A range within the source, from the position of its first character through to the position of
+/// its last character.
+#[derive(Debug, Clone, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// Creates a span that covers both `self` and `other`, assuming that `other` follows `self`.
+    pub fn to(&self, other: &Span) -> Self {
+        Self::new(self.start.clone(), other.end.clone())
+    }
+
+    /// Whether the position falls within the span, including its first and last characters.
+    pub fn contains(&self, position: &Position) -> bool {
+        &self.start <= position && position <= &self.end
+    }
+}
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
 pub struct Cursor<'a> {
     chars: Peekable<Chars<'a>>,
     current: Option<char>,
+    /// The position of the `current` character.
     position: Position,
+    /// The position of the character that will be consumed next.
+    next_position: Position,
 }
 
 #[allow(unused)]
@@ -58,25 +103,38 @@ impl<'a> Cursor<'a> {
             chars: source.chars().peekable(),
             current: None,
             position: Position::new(),
+            next_position: Position::new(),
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<(char, Position)> {
         self.current = self.chars.next();
+        self.position = self.next_position.clone();
 
         if self.current == Some('\n') {
-            self.position.next_line();
+            self.next_position.next_line();
         } else {
-            self.position.next_character();
+            self.next_position.next_character();
         }
 
         self.current.map(|c| (c, self.position.clone()))
     }
 
+    /// The position of the most recently consumed character.
+    pub fn position(&self) -> Position {
+        self.position.clone()
+    }
+
     pub fn peek_next(&mut self) -> Option<char> {
         self.chars.peek().cloned()
     }
 
+    /// Every character after the most recently consumed one, without consuming any of them.
+    pub fn lookahead(&self) -> impl Iterator<Item = char> + 'a {
+        self.chars.clone()
+    }
+
     pub fn take_while_config<F, S>(&mut self, mut state: S, retake: bool, f: F) -> Vec<char>
     where
         F: Fn(char, S) -> (TakeOption, S),
@@ -3,12 +3,10 @@ use std::num::ParseIntError;
 use thiserror::Error;
 
 use crate::{
-    lexer::cursor::Cursor,
+    lexer::cursor::{Cursor, Position, Span},
     token::{Keyword, Literal, Token, TokenKind},
 };
 
-use self::cursor::TakeOption;
-
 pub mod cursor;
 
 fn is_ident_char(c: char) -> bool {
@@ -17,35 +15,197 @@ fn is_ident_char(c: char) -> bool {
 
 #[derive(Debug, Error)]
 pub enum LexerError {
-    #[error("unable to parse int: {0}")]
-    ParseIntError(#[from] ParseIntError),
+    #[error("{position}: unable to parse int: {error}")]
+    ParseIntError {
+        error: ParseIntError,
+        position: Position,
+    },
+    #[error("{position}: invalid escape sequence \\{escape}")]
+    InvalidEscape { escape: char, position: Position },
+    #[error("{position}: invalid unicode escape, expected up to 6 hex digits within braces")]
+    InvalidUnicodeEscape { position: Position },
+    #[error("{position}: invalid unicode escape, {value:X} isn't a unicode scalar value")]
+    InvalidUnicodeScalar { value: u32, position: Position },
+    #[error("{position}: unterminated string")]
+    UnterminatedString { position: Position },
+}
+impl LexerError {
+    /// Where the error was found. For an unterminated string, this is where the string began.
+    pub fn position(&self) -> &Position {
+        match self {
+            LexerError::ParseIntError { position, .. }
+            | LexerError::InvalidEscape { position, .. }
+            | LexerError::InvalidUnicodeEscape { position }
+            | LexerError::InvalidUnicodeScalar { position, .. }
+            | LexerError::UnterminatedString { position } => position,
+        }
+    }
 }
 
 pub struct Lexer<'a> {
     cursor: Cursor<'a>,
+    /// For each interpolated string whose embedded expression is being lexed, where the string
+    /// began and how many `{` within the expression are yet to be closed.
+    interpolations: Vec<(Position, usize)>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a str) -> Self {
         Self {
             cursor: Cursor::new(source),
+            interpolations: Vec::new(),
         }
     }
+
+    /// Lexes the rest of a string after its opening `"`, replacing each escape sequence with the
+    /// character it stands for. Strings can span multiple lines, and a `\` at the end of a line
+    /// skips over the line break along with the indentation of the next line.
+    ///
+    /// Lexing stops at either the closing `"` or a `{` that begins an embedded expression, and
+    /// the flag returned alongside the text is whether it was the latter. Literal braces are
+    /// written as `\{` and `\}`.
+    fn string(&mut self, start: &Position) -> Result<(String, bool), LexerError> {
+        let mut string = String::new();
+
+        while let Some((c, position)) = self.cursor.next() {
+            match c {
+                '"' => return Ok((string, false)),
+                '{' => return Ok((string, true)),
+                '\\' => match self.cursor.next() {
+                    Some(('n', _)) => string.push('\n'),
+                    Some(('t', _)) => string.push('\t'),
+                    Some(('r', _)) => string.push('\r'),
+                    Some(('0', _)) => string.push('\0'),
+                    Some((c @ ('\\' | '"' | '\'' | '{' | '}'), _)) => string.push(c),
+                    Some(('u', _)) => string.push(self.unicode_escape(&position)?),
+                    Some(('\n' | '\r', _)) => self.cursor.skip_while(|c| c.is_whitespace()),
+                    Some((escape, _)) => {
+                        return Err(LexerError::InvalidEscape { escape, position })
+                    }
+                    None => break,
+                },
+                c => string.push(c),
+            }
+        }
+
+        Err(LexerError::UnterminatedString {
+            position: start.clone(),
+        })
+    }
+
+    /// Lexes the braces of a `\u{...}` escape, which contain the hexadecimal value of a unicode
+    /// scalar value. Surrogates and values above `10FFFF` aren't scalar values, so are rejected.
+    fn unicode_escape(&mut self, position: &Position) -> Result<char, LexerError> {
+        let error = || LexerError::InvalidUnicodeEscape {
+            position: position.clone(),
+        };
+
+        if self.cursor.peek_next() != Some('{') {
+            return Err(error());
+        }
+        self.cursor.next();
+
+        let digits = String::from_iter(self.cursor.take_while(|c| c.is_ascii_hexdigit()));
+        if digits.is_empty() || digits.len() > 6 || self.cursor.peek_next() != Some('}') {
+            return Err(error());
+        }
+        self.cursor.next();
+
+        // Six hex digits always fit in a u32
+        let value = u32::from_str_radix(&digits, 16).unwrap();
+        char::from_u32(value).ok_or_else(|| LexerError::InvalidUnicodeScalar {
+            value,
+            position: position.clone(),
+        })
+    }
+
+    /// Whether the `r` that was just consumed begins a raw string, rather than an identifier.
+    fn is_raw_string(&self) -> bool {
+        self.cursor.lookahead().find(|&c| c != '#') == Some('"')
+    }
+
+    /// Lexes the rest of a raw string after its `r`, in which escape sequences aren't replaced.
+    /// The opening `"` can be preceded by any number of `#`, in which case the string only
+    /// finishes at a `"` followed by as many `#`, so that it can contain quotes
+    /// (eg `r#"a "quoted" word"#`).
+    fn raw_string(&mut self, start: &Position) -> Result<String, LexerError> {
+        let hashes = self.cursor.take_while(|c| c == '#').len();
+        // Skip the opening `"`
+        self.cursor.next();
+
+        let mut string = String::new();
+        while let Some((c, _)) = self.cursor.next() {
+            if c == '"' && self.cursor.lookahead().take_while(|&c| c == '#').count() >= hashes {
+                for _ in 0..hashes {
+                    self.cursor.next();
+                }
+
+                return Ok(string);
+            }
+
+            string.push(c);
+        }
+
+        Err(LexerError::UnterminatedString {
+            position: start.clone(),
+        })
+    }
 }
 
 impl Iterator for Lexer<'_> {
     type Item = Result<Token, LexerError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // The input ended within an embedded expression, so its string was never finished
+        if self.cursor.peek_next().is_none() {
+            if let Some((start, _)) = self.interpolations.pop() {
+                return Some(Err(LexerError::UnterminatedString { position: start }));
+            }
+        }
+
         self.cursor.next().map(|(c, position)| {
             Ok(Token::new(
                 match c {
                     '=' => TokenKind::Equals,
                     '+' => TokenKind::Plus,
+                    '-' if self
+                        .cursor
+                        .peek_next()
+                        .map(|c| c == '>')
+                        .unwrap_or_default() =>
+                    {
+                        // Skip next `>`
+                        self.cursor.next();
+
+                        TokenKind::Arrow
+                    }
                     '-' => TokenKind::Minus,
                     '*' => TokenKind::Asterix,
                     '^' => TokenKind::Hat,
+                    '%' => TokenKind::Percent,
+                    '&' => TokenKind::Ampersand,
+                    '|' => TokenKind::Pipe,
+                    '~' => TokenKind::Tilde,
+                    // Only a block can contain a statement, so an embedded expression that ends
+                    // one outside of a block must have been left open
+                    ';' if matches!(self.interpolations.last(), Some((_, 0))) => {
+                        let (start, _) = self.interpolations.pop().unwrap();
+                        return Err(LexerError::UnterminatedString { position: start });
+                    }
                     ';' => TokenKind::Semi,
+                    ':' if self
+                        .cursor
+                        .peek_next()
+                        .map(|c| c == ':')
+                        .unwrap_or_default() =>
+                    {
+                        // Skip next `:`
+                        self.cursor.next();
+
+                        TokenKind::DoubleColon
+                    }
+                    ':' => TokenKind::Colon,
+                    ',' => TokenKind::Comma,
                     '/' if self
                         .cursor
                         .peek_next()
@@ -60,6 +220,60 @@ impl Iterator for Lexer<'_> {
                     '/' => TokenKind::Slash,
                     '(' => TokenKind::LSmooth,
                     ')' => TokenKind::RSmooth,
+                    '[' => TokenKind::LSquare,
+                    ']' => TokenKind::RSquare,
+                    '#' => TokenKind::Hash,
+                    '{' => {
+                        if let Some((_, depth)) = self.interpolations.last_mut() {
+                            *depth += 1;
+                        }
+
+                        TokenKind::LCurly
+                    }
+                    '}' => match self.interpolations.last_mut() {
+                        // This closes an embedded expression, so the string carries on
+                        Some((start, 0)) => {
+                            let start = start.clone();
+
+                            match self.string(&start)? {
+                                (string, true) => TokenKind::StringMiddle(string),
+                                (string, false) => {
+                                    self.interpolations.pop();
+                                    TokenKind::StringEnd(string)
+                                }
+                            }
+                        }
+                        Some((_, depth)) => {
+                            *depth -= 1;
+                            TokenKind::RCurly
+                        }
+                        None => TokenKind::RCurly,
+                    },
+                    '<' if self
+                        .cursor
+                        .peek_next()
+                        .map(|c| c == '<')
+                        .unwrap_or_default() =>
+                    {
+                        // Skip next `<`
+                        self.cursor.next();
+
+                        TokenKind::DoubleLAngle
+                    }
+                    '<' => TokenKind::LAngle,
+                    '>' if self
+                        .cursor
+                        .peek_next()
+                        .map(|c| c == '>')
+                        .unwrap_or_default() =>
+                    {
+                        // Skip next `>`, which the parser splits back up when it closes two
+                        // lists of type arguments at once
+                        self.cursor.next();
+
+                        TokenKind::DoubleRAngle
+                    }
+                    '>' => TokenKind::RAngle,
                     c if c.is_ascii_whitespace() => {
                         // Consume through to the end of whitespace
                         self.cursor.skip_while(|c| c.is_ascii_whitespace());
@@ -71,8 +285,15 @@ impl Iterator for Lexer<'_> {
                             .retake_while(|c| c.is_ascii_digit())
                             .into_iter()
                             .collect::<String>()
-                            .parse()?,
+                            .parse()
+                            .map_err(|error| LexerError::ParseIntError {
+                                error,
+                                position: position.clone(),
+                            })?,
                     )),
+                    'r' if self.is_raw_string() => {
+                        TokenKind::Literal(Literal::String(self.raw_string(&position)?))
+                    }
                     c if is_ident_char(c) => {
                         let ident_str = String::from_iter(self.cursor.retake_while(is_ident_char));
 
@@ -88,29 +309,17 @@ impl Iterator for Lexer<'_> {
                             _ => TokenKind::Identifier(ident_str),
                         }
                     }
-                    '"' => {
-                        let chars = self.cursor.take_while_config(false, false, |c, escaped| {
-                            if c == '\\' {
-                                if !escaped {
-                                    // Don't emit this back slash, as it is causing an escape
-                                    (TakeOption::Skip, true)
-                                } else {
-                                    // Backslash can escape itself
-                                    (TakeOption::Take, false)
-                                }
-                            } else if c == '"' && !escaped {
-                                // End of string reached with no escape
-                                (TakeOption::SkipAndStop, false)
-                            } else {
-                                (TakeOption::Take, false)
-                            }
-                        });
-
-                        TokenKind::Literal(Literal::String(chars.into_iter().collect()))
-                    }
+                    '"' => match self.string(&position)? {
+                        (string, true) => {
+                            self.interpolations.push((position.clone(), 0));
+                            TokenKind::StringStart(string)
+                        }
+                        (string, false) => TokenKind::Literal(Literal::String(string)),
+                    },
                     _ => TokenKind::Unknown,
                 },
-                position,
+                // The cursor will now be resting on the final character of the token
+                Span::new(position, self.cursor.position()),
             ))
         })
     }
@@ -118,8 +327,6 @@ impl Iterator for Lexer<'_> {
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::cursor::Position;
-
     use super::*;
 
     #[test]
@@ -131,11 +338,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::Minus,
-                    position: Position::new()
+                    span: Span::default()
                 },
                 Token {
                     kind: TokenKind::Literal(Literal::Integer(90)),
-                    position: Position::new()
+                    span: Span::default()
                 }
             ]
         );
@@ -151,11 +358,112 @@ mod tests {
                 kind: TokenKind::Literal(Literal::String(
                     r#"this is a \very\ cool "string"\"#.to_string()
                 )),
-                position: Position::new()
+                span: Span::default()
             }]
         )
     }
 
+    /// Lexes a single string literal, returning its contents.
+    fn string_literal(source: &str) -> Result<String, LexerError> {
+        match Lexer::new(source).next().unwrap()?.kind {
+            TokenKind::Literal(Literal::String(string)) => Ok(string),
+            kind => panic!("expected a string, found {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            string_literal(r#""a\tb\r\n\0 \'\" \u{48}\u{1F600} \u{e9}""#).unwrap(),
+            "a\tb\r\n\0 '\" H😀 é"
+        );
+
+        // Line breaks can be written directly, or skipped along with the following indentation
+        assert_eq!(
+            string_literal("\"first\n  second \\\n    third\"").unwrap(),
+            "first\n  second third"
+        );
+    }
+
+    #[test]
+    fn raw_strings() {
+        assert_eq!(string_literal(r#"r"C:\path\n""#).unwrap(), r"C:\path\n");
+        assert_eq!(
+            string_literal(r###"r##"a "# "quoted"# word"##"###).unwrap(),
+            r##"a "# "quoted"# word"##
+        );
+        assert_eq!(string_literal("r\"two\nlines\"").unwrap(), "two\nlines");
+
+        // Without a quote, `r` is still an identifier
+        assert_eq!(
+            Lexer::new("r #")
+                .map(|token| token.unwrap().kind)
+                .collect::<Vec<_>>(),
+            vec![
+                TokenKind::Identifier("r".to_string()),
+                TokenKind::Whitespace,
+                TokenKind::Hash
+            ]
+        );
+    }
+
+    #[test]
+    fn string_errors() {
+        for (source, expected) in [
+            (r#""a\qb""#, "1:3: invalid escape sequence \\q"),
+            (
+                r#""\u{110000}""#,
+                "1:2: invalid unicode escape, 110000 isn't a unicode scalar value",
+            ),
+            (
+                r#""\u{d800}""#,
+                "1:2: invalid unicode escape, D800 isn't a unicode scalar value",
+            ),
+            (
+                r#""\u48""#,
+                "1:2: invalid unicode escape, expected up to 6 hex digits within braces",
+            ),
+            ("let a = 1;\nlet b = \"open\n", "2:9: unterminated string"),
+            (r#""escaped end\""#, "1:1: unterminated string"),
+            (r##"r#"raw"""##, "1:1: unterminated string"),
+            (r#"print("a{1 + 2);"#, "1:7: unterminated string"),
+            (r#"let a = "a{"b{1 + 2"#, "1:12: unterminated string"),
+        ] {
+            let error = Lexer::new(source)
+                .find_map(Result::err)
+                .unwrap_or_else(|| panic!("{source} should fail to lex"));
+
+            assert_eq!(error.to_string(), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(
+            Lexer::new(r#""a {b} c {{ d } + "{e}"} \{f\}""#)
+                .map(|token| token.unwrap().kind)
+                .filter(|kind| !matches!(kind, TokenKind::Whitespace))
+                .collect::<Vec<_>>(),
+            vec![
+                TokenKind::StringStart("a ".to_string()),
+                TokenKind::Identifier("b".to_string()),
+                TokenKind::StringMiddle(" c ".to_string()),
+                TokenKind::LCurly,
+                TokenKind::Identifier("d".to_string()),
+                TokenKind::RCurly,
+                TokenKind::Plus,
+                TokenKind::StringStart("".to_string()),
+                TokenKind::Identifier("e".to_string()),
+                TokenKind::StringEnd("".to_string()),
+                TokenKind::StringEnd(" {f}".to_string()),
+            ]
+        );
+
+        // The string carries on after each embedded expression, so is only unterminated at the end
+        let error = Lexer::new("\"a {b} c\n").find_map(Result::err).unwrap();
+        assert_eq!(error.to_string(), "1:1: unterminated string");
+    }
+
     #[test]
     fn boolean() {
         assert_eq!(
@@ -164,8 +472,82 @@ mod tests {
                 .unwrap(),
             vec![Token {
                 kind: TokenKind::Literal(Literal::Boolean(true)),
-                position: Position::new()
+                span: Span::default()
             }]
         )
     }
+
+    #[test]
+    fn generic_function() {
+        assert_eq!(
+            Lexer::new("fn id<T>(x: T) -> T { x } id::<T>")
+                .map(|token| token.unwrap().kind)
+                .filter(|kind| !matches!(kind, TokenKind::Whitespace))
+                .collect::<Vec<_>>(),
+            vec![
+                TokenKind::Keyword(Keyword::Fn),
+                TokenKind::Identifier("id".to_string()),
+                TokenKind::LAngle,
+                TokenKind::Identifier("T".to_string()),
+                TokenKind::RAngle,
+                TokenKind::LSmooth,
+                TokenKind::Identifier("x".to_string()),
+                TokenKind::Colon,
+                TokenKind::Identifier("T".to_string()),
+                TokenKind::RSmooth,
+                TokenKind::Arrow,
+                TokenKind::Identifier("T".to_string()),
+                TokenKind::LCurly,
+                TokenKind::Identifier("x".to_string()),
+                TokenKind::RCurly,
+                TokenKind::Identifier("id".to_string()),
+                TokenKind::DoubleColon,
+                TokenKind::LAngle,
+                TokenKind::Identifier("T".to_string()),
+                TokenKind::RAngle,
+            ]
+        );
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(
+            Lexer::new("a<<b>>c&d|e~~f%g<h>>")
+                .map(|token| token.unwrap().kind)
+                .filter(|kind| !matches!(kind, TokenKind::Identifier(_)))
+                .collect::<Vec<_>>(),
+            vec![
+                TokenKind::DoubleLAngle,
+                TokenKind::DoubleRAngle,
+                TokenKind::Ampersand,
+                TokenKind::Pipe,
+                TokenKind::Tilde,
+                TokenKind::Tilde,
+                TokenKind::Percent,
+                TokenKind::LAngle,
+                TokenKind::DoubleRAngle,
+            ]
+        );
+    }
+
+    #[test]
+    fn attribute() {
+        assert_eq!(
+            Lexer::new("#[deny(a, b)]")
+                .map(|token| token.unwrap().kind)
+                .collect::<Vec<_>>(),
+            vec![
+                TokenKind::Hash,
+                TokenKind::LSquare,
+                TokenKind::Identifier("deny".to_string()),
+                TokenKind::LSmooth,
+                TokenKind::Identifier("a".to_string()),
+                TokenKind::Comma,
+                TokenKind::Whitespace,
+                TokenKind::Identifier("b".to_string()),
+                TokenKind::RSmooth,
+                TokenKind::RSquare,
+            ]
+        );
+    }
 }
@@ -0,0 +1,384 @@
+//! A small statically typed language, along with its compiler and virtual machine.
+//!
+//! The items at the root of the crate are the API for embedding the language: [compile] checks a
+//! program and produces a [Program] that can be [run](Program::run), whilst [lex],
+//! [parse_source] and [Program::types] give access to each stage along the way. Any problems are
+//! reported as [Diagnostics]. Applications can make their own functions available to scripts by
+//! registering them with a [Host] and compiling with [compile_with_host], and choose which
+//! [lints] are reported with [compile_with_lints].
+
+use std::io::{self, Write};
+
+use checks::typing::{TypeEnvironment, TypedAstNode};
+use lexer::Lexer;
+use lints::Levels;
+use parser::{parse, AstNode};
+use syntax::SyntaxNode;
+use token::{Literal, Token, TokenKind};
+use token_stream::TokenStream;
+
+pub use self::{
+    bytecode::{Cancellation, Limits, RuntimeError, Value},
+    diagnostics::{Diagnostic, Diagnostics, Severity},
+    host::{Host, HostError, HostValue},
+};
+
+// The stages of the compiler are public for the command line interface and the integration
+// tests, but aren't part of the API for embedding the language
+#[doc(hidden)]
+pub mod backend;
+#[doc(hidden)]
+pub mod bytecode;
+#[doc(hidden)]
+pub mod checks;
+mod diagnostics;
+pub(crate) mod format;
+pub mod host;
+#[doc(hidden)]
+pub mod ir;
+#[doc(hidden)]
+pub mod lexer;
+pub mod lints;
+#[doc(hidden)]
+pub mod lsp;
+pub(crate) mod optimise;
+#[doc(hidden)]
+pub mod parser;
+#[doc(hidden)]
+pub mod syntax;
+#[doc(hidden)]
+pub mod token;
+pub(crate) mod token_stream;
+
+/// The program that is used when no source file is provided.
+pub const SAMPLE: &str = r#"let a = 3;
+let b = 5;
+
+// The result
+let c = a + b;
+
+let some_bool = true;
+let another_bool = false;
+
+print(c);
+"#;
+
+/// A program that has been type checked and compiled to bytecode, ready to be run, along with its
+/// optimised [IR](ir::Module).
+pub struct Program {
+    environment: TypeEnvironment,
+    typed_ast: Vec<TypedAstNode>,
+    ir: ir::Module,
+    bytecode: bytecode::Program,
+    warnings: Vec<Diagnostic>,
+    host: Host,
+}
+impl Program {
+    /// Runs the program within the [default limits](Limits::default), writing anything that it
+    /// prints to stdout. Returns the value that the program evaluated to.
+    pub fn run(&self) -> Result<Value, RuntimeError> {
+        self.run_with_output(io::stdout())
+    }
+
+    /// Runs the program within the [default limits](Limits::default), writing anything that it
+    /// prints to `output`.
+    pub fn run_with_output(&self, output: impl Write) -> Result<Value, RuntimeError> {
+        self.run_with_limits(output, Limits::default())
+    }
+
+    /// Runs the program, writing anything that it prints to `output`, stopping it with an error if
+    /// it exceeds any of the limits or is cancelled. Pass [Limits::unbounded] to run a trusted
+    /// program without any limits.
+    pub fn run_with_limits(
+        &self,
+        output: impl Write,
+        limits: Limits,
+    ) -> Result<Value, RuntimeError> {
+        bytecode::Vm::new(&self.bytecode, output)
+            .with_host(&self.host)
+            .with_limits(limits)
+            .run()
+    }
+
+    /// The types of every binding, function and type declared at the top level of the program.
+    pub fn types(&self) -> &TypeEnvironment {
+        &self.environment
+    }
+
+    /// The program as it was type checked, with every expression annotated with its type.
+    pub fn typed_ast(&self) -> &[TypedAstNode] {
+        &self.typed_ast
+    }
+
+    /// The program lowered into SSA form, once it has been optimised.
+    pub fn ir(&self) -> &ir::Module {
+        &self.ir
+    }
+
+    /// The bytecode that the virtual machine runs, which can be disassembled or written to a file
+    /// to be run later.
+    pub fn bytecode(&self) -> &bytecode::Program {
+        &self.bytecode
+    }
+
+    /// Any lints that were reported as warnings whilst compiling.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+}
+
+/// Compiles the source with every lint at its default level.
+pub fn compile(source: &str) -> Result<Program, Diagnostics> {
+    compile_with_lints(source, &Levels::default())
+}
+
+/// Lexes, parses, lints and type checks the source, then lowers it into SSA form to be optimised,
+/// and compiles it to bytecode.
+/// Warnings are returned alongside the errors if compilation fails, or are available from
+/// [Program::warnings] otherwise.
+pub fn compile_with_lints(source: &str, levels: &Levels) -> Result<Program, Diagnostics> {
+    compile_with(source, levels, &Host::default())
+}
+
+/// Compiles the source with every lint at its default level, where the functions registered with
+/// the host can be called. The program keeps a copy of the host to call them when it is run.
+pub fn compile_with_host(source: &str, host: &Host) -> Result<Program, Diagnostics> {
+    compile_with(source, &Levels::default(), host)
+}
+
+fn compile_with(source: &str, levels: &Levels, host: &Host) -> Result<Program, Diagnostics> {
+    let ast = parse_source(source)?;
+
+    let (warnings, denied) = lints::lint(&ast, levels)
+        .into_iter()
+        .map(Diagnostic::from)
+        .partition::<Vec<_>, _>(|diagnostic| diagnostic.severity == Severity::Warning);
+
+    // Denied lints are only reported if the program type checks, as type errors are more
+    // important
+    let errors = match TypeEnvironment::from_ast_with_host(ast, host) {
+        Ok((environment, typed_ast)) if denied.is_empty() => {
+            let mut ir = ir::lower(&typed_ast);
+
+            match optimise::fold_constants(&mut ir) {
+                Ok(()) => {
+                    optimise::eliminate_dead_code(&mut ir);
+
+                    return Ok(Program {
+                        environment,
+                        bytecode: bytecode::compile(&typed_ast),
+                        typed_ast,
+                        ir,
+                        warnings,
+                        host: host.clone(),
+                    });
+                }
+                Err(errors) => Diagnostics::from(errors),
+            }
+        }
+        Ok(_) => Diagnostics(denied),
+        Err(errors) => Diagnostics::from(errors),
+    };
+
+    Err(Diagnostics(warnings.into_iter().chain(errors.0).collect()))
+}
+
+/// Lexes the source, dropping any whitespace.
+pub fn lex(source: &str) -> Result<Vec<Token>, Diagnostics> {
+    Lexer::new(source)
+        .filter(|token| {
+            // Keep any errors, so that they can be reported
+            !matches!(token, Ok(token) if token.kind == TokenKind::Whitespace)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| Diagnostics(vec![Diagnostic::from_lexer_error(error)]))
+}
+
+/// Lexes and parses the source, producing an AST.
+pub fn parse_source(source: &str) -> Result<Vec<AstNode>, Diagnostics> {
+    let tokens = lex(source)?;
+
+    parse(TokenStream::from(tokens.into_iter()))
+        .map_err(|error| Diagnostics(vec![Diagnostic::from_parser_error(error, source)]))
+}
+
+/// Lexes, parses and formats the source. The parser discards comments, so they are taken out of
+/// the tokens first to be written back out by the formatter. It also doesn't keep whether a
+/// string was raw, so the formatter is told where each raw string starts.
+pub fn format_source(source: &str) -> Result<String, Diagnostics> {
+    let (comments, tokens): (Vec<_>, Vec<_>) = lex(source)?
+        .into_iter()
+        .partition(|token| matches!(token.kind, TokenKind::Comment(_)));
+
+    let comments = comments
+        .into_iter()
+        .filter_map(|token| match token.kind {
+            TokenKind::Comment(text) => Some((text, token.span)),
+            _ => None,
+        })
+        .collect();
+    let raw_strings = tokens
+        .iter()
+        .filter(|token| {
+            matches!(token.kind, TokenKind::Literal(Literal::String(_)))
+                && source
+                    .split('\n')
+                    .nth(token.span.start.line())
+                    .and_then(|line| line.chars().nth(token.span.start.character()))
+                    == Some('r')
+        })
+        .map(|token| token.span.start.clone())
+        .collect();
+    let ast = parse(TokenStream::from(tokens.into_iter()))
+        .map_err(|error| Diagnostics(vec![Diagnostic::from_parser_error(error, source)]))?;
+
+    Ok(format::format(&ast, comments, raw_strings))
+}
+
+/// Lexes and parses the source into a lossless syntax tree, keeping every token.
+pub fn syntax_tree(source: &str) -> Result<SyntaxNode, Diagnostics> {
+    let tokens = Lexer::new(source)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| Diagnostics(vec![Diagnostic::from_lexer_error(error)]))?;
+    let (tree, _) = syntax::parse_tree(source, tokens)
+        .map_err(|error| Diagnostics(vec![Diagnostic::from_parser_error(error, source)]))?;
+
+    Ok(tree)
+}
+
+/// Lexes, parses and type checks the source, producing a typed AST.
+#[cfg(test)]
+fn front_end(source: &str) -> Result<(TypeEnvironment, Vec<TypedAstNode>), Diagnostics> {
+    Ok(TypeEnvironment::from_ast(parse_source(source)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bytecode::RuntimeErrorKind,
+        checks::typing::Type,
+        compile, compile_with_host, compile_with_lints,
+        lints::{Level, Levels, Lint},
+        Host, Severity, Value, SAMPLE,
+    };
+
+    #[test]
+    fn embedding() {
+        let program = compile(SAMPLE).unwrap();
+
+        let mut output = Vec::new();
+        assert_eq!(program.run_with_output(&mut output).unwrap(), Value::Unit);
+        assert_eq!(String::from_utf8(output).unwrap(), "8\n");
+
+        let (ident, signature) = program
+            .types()
+            .functions()
+            .find(|(ident, _)| *ident == "print")
+            .unwrap();
+        assert_eq!(
+            (ident.as_str(), signature.as_str()),
+            ("print", "fn<T>(T) -> Unit")
+        );
+
+        // `c` is folded, and the unused bindings are removed as dead code
+        assert_eq!(program.ir().to_string().matches("const").count(), 1);
+        assert_eq!(
+            program
+                .warnings()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "7:5: unused variable some_bool [unused_variable]",
+                "8:5: unused variable another_bool [unused_variable]"
+            ]
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        let diagnostics = compile("let a = 1 +").err().unwrap();
+        assert_eq!(
+            diagnostics.to_string(),
+            "1:11: expected token to follow, but found none"
+        );
+
+        // The position is kept separately from the message, rather than repeated within it
+        for (source, message) in [
+            ("let a = \"\\q\";", "invalid escape sequence \\q"),
+            ("let = 1;", "expected Identifier(\"\")"),
+        ] {
+            let diagnostics = compile(source).err().unwrap();
+            assert_eq!(diagnostics.errors().next().unwrap().message, message);
+        }
+
+        let diagnostics = compile("let a = true + 1;\nlet b = 1 / 0;").err().unwrap();
+        assert_eq!(
+            diagnostics.to_string(),
+            "warning: 1:5: unused variable a [unused_variable]
+warning: 2:5: unused variable b [unused_variable]
+1:9: Mismatched types: Boolean and Integer"
+        );
+        assert_eq!(diagnostics.errors().count(), 1);
+
+        // Constant evaluation errors are only found once the program type checks
+        let diagnostics = compile("print(1 / 0);").err().unwrap();
+        assert_eq!(diagnostics.to_string(), "1:7: attempted to divide by zero");
+
+        let mut levels = Levels::default();
+        levels.set(Lint::UnusedVariable, Level::Deny);
+        let diagnostics = compile_with_lints("let a = 1;", &levels).err().unwrap();
+        assert_eq!(diagnostics.0.len(), 1);
+        assert_eq!(diagnostics.0[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn host_functions() {
+        let mut host = Host::new();
+        host.register_fn("double", |x: isize| x * 2).unwrap();
+        host.register_fn("greet", |name: String| format!("hello {name}"))
+            .unwrap();
+        host.register("fail", vec![], Type::Unit, |_| {
+            Err("something went wrong".to_string())
+        })
+        .unwrap();
+        host.register("flag", vec![], Type::Boolean, |_| Ok(Value::Integer(5)))
+            .unwrap();
+
+        let program =
+            compile_with_host("print(double(21)); print(greet(\"world\"));", &host).unwrap();
+        let mut output = Vec::new();
+        program.run_with_output(&mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "42\nhello world\n");
+
+        // Host functions are checked like any other function
+        let diagnostics = compile_with_host("print(double(true));", &host)
+            .err()
+            .unwrap();
+        assert_eq!(
+            diagnostics.to_string(),
+            "1:14: Expected type Integer, found Boolean"
+        );
+        assert!(compile("print(double(1));").is_err());
+        assert!(compile_with_host("fn double(x: Integer) -> Integer { x }", &host).is_err());
+
+        let error = compile_with_host("fail();", &host)
+            .unwrap()
+            .run_with_output(Vec::new())
+            .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::Host { ref message, .. } if message == "something went wrong"
+        ));
+
+        // The value returned by the host is checked, rather than failing where it is used
+        let error = compile_with_host("if flag() { print(1); }", &host)
+            .unwrap()
+            .run_with_output(Vec::new())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "1:4: host function flag failed: returned integer instead of Boolean"
+        );
+    }
+}
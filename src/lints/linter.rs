@@ -0,0 +1,289 @@
+use std::mem;
+
+use crate::{
+    lexer::cursor::Span,
+    parser::{
+        parsers::{
+            Attribute, Block, Expression, ExpressionKind, Function, InterpolationPart, Let,
+            Placement,
+        },
+        AstNode,
+    },
+    token::Literal,
+};
+
+use super::{Level, Levels, Lint, Warning};
+
+/// A binding that is in scope, tracking whether it has been used.
+struct Binding {
+    ident: String,
+    span: Span,
+    used: bool,
+    /// The level of [Lint::UnusedVariable] where the binding was declared, as attributes no
+    /// longer apply by the time the binding goes out of scope.
+    unused: Level,
+}
+
+/// Walks the parsed program, checking every lint as it goes.
+pub(super) struct Linter {
+    levels: Levels,
+    /// Each binding in scope in the order that they were declared.
+    bindings: Vec<Binding>,
+    /// The index of the first binding declared within the current scope.
+    scope: usize,
+    /// Whether the expression being checked is within the condition of an `if`, where struct
+    /// literals must be surrounded by parentheses.
+    in_condition: bool,
+    pub(super) warnings: Vec<Warning>,
+}
+impl Linter {
+    pub(super) fn new(levels: Levels) -> Self {
+        Self {
+            levels,
+            bindings: Vec::new(),
+            scope: 0,
+            in_condition: false,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub(super) fn program(&mut self, ast: &[AstNode]) {
+        for node in ast {
+            match node {
+                AstNode::Function(function) => self.function(function),
+                node => self.statement(node),
+            }
+        }
+
+        self.end_scope(0);
+    }
+
+    fn report(&mut self, lint: Lint, message: String, span: &Span) {
+        self.report_at(lint, self.levels.get(lint), message, span);
+    }
+
+    fn report_at(&mut self, lint: Lint, level: Level, message: String, span: &Span) {
+        if level != Level::Allow {
+            self.warnings.push(Warning {
+                lint,
+                level,
+                message,
+                span: span.clone(),
+            });
+        }
+    }
+
+    /// Applies the levels from each attribute, returning the levels from before so that they can
+    /// be restored once the declaration has been checked.
+    fn apply(&mut self, attributes: &[Attribute]) -> Levels {
+        let outer = self.levels.clone();
+
+        for attribute in attributes {
+            let Ok(level) = Level::try_from(attribute.ident.as_str()) else {
+                self.report(
+                    Lint::Unknown,
+                    format!("unknown attribute {}", attribute.ident),
+                    &attribute.span,
+                );
+                continue;
+            };
+
+            for (name, span) in &attribute.arguments {
+                match Lint::try_from(name.as_str()) {
+                    Ok(lint) => self.levels.set(lint, level),
+                    Err(()) => self.report(Lint::Unknown, format!("unknown lint {name}"), span),
+                }
+            }
+        }
+
+        outer
+    }
+
+    /// Removes every binding declared since `scope`, reporting any that weren't used.
+    fn end_scope(&mut self, scope: usize) {
+        for binding in self.bindings.drain(scope..).collect::<Vec<_>>() {
+            if !binding.used && !binding.ident.starts_with('_') {
+                self.report_at(
+                    Lint::UnusedVariable,
+                    binding.unused,
+                    format!("unused variable {}", binding.ident),
+                    &binding.span,
+                );
+            }
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        let outer_levels = self.apply(&function.attributes);
+
+        // Functions can only refer to their own parameters and bindings. Parameters can be
+        // shadowed, but aren't reported when unused
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|(ident, _)| Binding {
+                ident: ident.clone(),
+                span: function.ident_span.clone(),
+                used: true,
+                unused: Level::Allow,
+            })
+            .collect();
+        let outer_bindings = mem::replace(&mut self.bindings, parameters);
+        let outer_scope = mem::replace(&mut self.scope, self.bindings.len());
+
+        self.block(&function.body);
+
+        self.bindings = outer_bindings;
+        self.scope = outer_scope;
+        self.levels = outer_levels;
+    }
+
+    fn statement(&mut self, statement: &AstNode) {
+        match statement {
+            AstNode::Let(let_node) => self.let_binding(let_node),
+            AstNode::Expression(expression) => self.expression(expression, Placement::Free),
+            AstNode::Function(function) => self.function(function),
+            AstNode::Struct(_) | AstNode::Enum(_) => (),
+        }
+    }
+
+    fn let_binding(&mut self, let_node: &Let) {
+        let outer_levels = self.apply(&let_node.attributes);
+
+        self.expression(&let_node.rhs, Placement::Free);
+
+        // Redeclaring a binding within the same scope is a type error rather than shadowing
+        if self.bindings[..self.scope]
+            .iter()
+            .any(|binding| binding.ident == let_node.ident)
+        {
+            self.report(
+                Lint::ShadowedBinding,
+                format!("{} shadows an existing binding", let_node.ident),
+                &let_node.ident_span,
+            );
+        }
+
+        self.bindings.push(Binding {
+            ident: let_node.ident.clone(),
+            span: let_node.ident_span.clone(),
+            used: false,
+            unused: self.levels.get(Lint::UnusedVariable),
+        });
+
+        self.levels = outer_levels;
+    }
+
+    fn block(&mut self, block: &Block) {
+        let outer_scope = mem::replace(&mut self.scope, self.bindings.len());
+        // The braces allow struct literals again
+        let in_condition = mem::replace(&mut self.in_condition, false);
+
+        for statement in &block.statements {
+            self.statement(statement);
+        }
+        if let Some(expression) = &block.expression {
+            self.expression(expression, Placement::Free);
+        }
+
+        self.end_scope(self.scope);
+        self.scope = outer_scope;
+        self.in_condition = in_condition;
+    }
+
+    fn expression(&mut self, expression: &Expression, placement: Placement) {
+        // Struct literals within a condition have to be surrounded by parentheses, regardless of
+        // precedence
+        let required = expression.requires_parentheses(placement)
+            || (self.in_condition && contains_struct(expression));
+        if expression.parentheses > 1 || (expression.parentheses == 1 && !required) {
+            self.report(
+                Lint::RedundantParentheses,
+                "redundant parentheses around expression".to_string(),
+                &expression.span,
+            );
+        }
+
+        match &expression.kind {
+            ExpressionKind::Ident(ident) => {
+                if let Some(binding) = self
+                    .bindings
+                    .iter_mut()
+                    .rfind(|binding| &binding.ident == ident)
+                {
+                    binding.used = true;
+                }
+            }
+            ExpressionKind::Literal(_) => (),
+            ExpressionKind::BinaryOperation {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                self.expression(lhs, Placement::Lhs(*operation));
+                self.expression(rhs, Placement::Rhs(*operation));
+            }
+            ExpressionKind::UnaryOperation { operation, rhs } => {
+                self.expression(rhs, Placement::Operand(*operation))
+            }
+            ExpressionKind::Call { arguments, .. } | ExpressionKind::Variant { arguments, .. } => {
+                let in_condition = mem::replace(&mut self.in_condition, false);
+                for argument in arguments {
+                    self.expression(argument, Placement::Free);
+                }
+                self.in_condition = in_condition;
+            }
+            ExpressionKind::Struct { fields, .. } => {
+                let in_condition = mem::replace(&mut self.in_condition, false);
+                for (_, value) in fields {
+                    self.expression(value, Placement::Free);
+                }
+                self.in_condition = in_condition;
+            }
+            ExpressionKind::Interpolation(parts) => {
+                let in_condition = mem::replace(&mut self.in_condition, false);
+                for part in parts {
+                    if let InterpolationPart::Expression(expression) = part {
+                        self.expression(expression, Placement::Free);
+                    }
+                }
+                self.in_condition = in_condition;
+            }
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                if let ExpressionKind::Literal(Literal::Boolean(value)) = condition.kind {
+                    self.report(
+                        Lint::ConstantCondition,
+                        format!("condition is always {value}"),
+                        &condition.span,
+                    );
+                }
+
+                let in_condition = mem::replace(&mut self.in_condition, true);
+                self.expression(condition, Placement::Free);
+                self.in_condition = in_condition;
+
+                self.block(then);
+                if let Some(otherwise) = otherwise {
+                    self.block(otherwise);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the expression contains a struct literal that isn't already surrounded by parentheses
+/// or some other delimiter.
+fn contains_struct(expression: &Expression) -> bool {
+    match &expression.kind {
+        ExpressionKind::Struct { .. } => true,
+        ExpressionKind::BinaryOperation { lhs, rhs, .. } => [lhs, rhs]
+            .into_iter()
+            .any(|operand| operand.parentheses == 0 && contains_struct(operand)),
+        ExpressionKind::UnaryOperation { rhs, .. } => rhs.parentheses == 0 && contains_struct(rhs),
+        _ => false,
+    }
+}
@@ -0,0 +1,220 @@
+use std::{collections::HashMap, fmt::Display};
+
+use thiserror::Error;
+
+use crate::{lexer::cursor::Span, parser::AstNode};
+
+use self::linter::Linter;
+
+mod linter;
+
+/// Each of the lints that can be reported. Lints point out code that is valid, but is likely to be
+/// a mistake or could be written more simply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A binding that is never used. Bindings starting with `_` are expected to be unused.
+    UnusedVariable,
+    /// A binding with the same ident as one from an enclosing scope.
+    ShadowedBinding,
+    /// Parentheses that don't change how an expression is parsed.
+    RedundantParentheses,
+    /// The condition of an `if` that is a literal, so the same branch is always taken.
+    ConstantCondition,
+    /// An attribute or lint name that isn't recognised.
+    Unknown,
+}
+impl Lint {
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused_variable",
+            Lint::ShadowedBinding => "shadowed_binding",
+            Lint::RedundantParentheses => "redundant_parentheses",
+            Lint::ConstantCondition => "constant_condition",
+            Lint::Unknown => "unknown_lint",
+        }
+    }
+}
+impl TryFrom<&str> for Lint {
+    type Error = ();
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "unused_variable" => Ok(Lint::UnusedVariable),
+            "shadowed_binding" => Ok(Lint::ShadowedBinding),
+            "redundant_parentheses" => Ok(Lint::RedundantParentheses),
+            "constant_condition" => Ok(Lint::ConstantCondition),
+            "unknown_lint" => Ok(Lint::Unknown),
+            _ => Err(()),
+        }
+    }
+}
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How a lint is reported when it is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// The lint isn't reported.
+    Allow,
+    /// The lint is reported, but compilation continues.
+    Warn,
+    /// The lint is reported as an error, stopping compilation.
+    Deny,
+}
+impl TryFrom<&str> for Level {
+    type Error = ();
+
+    fn try_from(level: &str) -> Result<Self, Self::Error> {
+        match level {
+            "allow" => Ok(Level::Allow),
+            "warn" => Ok(Level::Warn),
+            "deny" => Ok(Level::Deny),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The level of every lint. Each lint is a warning unless configured otherwise, either from the
+/// command line or by an attribute such as `#[allow(unused_variable)]`.
+#[derive(Debug, Clone, Default)]
+pub struct Levels(HashMap<Lint, Level>);
+impl Levels {
+    pub fn get(&self, lint: Lint) -> Level {
+        self.0.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.0.insert(lint, level);
+    }
+}
+
+/// A lint that was found, along with the level that it was found at. Only lints at the
+/// [Level::Deny] level stop compilation.
+#[derive(Debug, Error)]
+#[error("{span}: {message} [{lint}]")]
+pub struct Warning {
+    pub lint: Lint,
+    pub level: Level,
+    pub message: String,
+    pub span: Span,
+}
+
+/// Checks the program for every lint that isn't allowed, returning them in the order that they
+/// appear in the source. Lints are checked before type checking, so that the program is still as
+/// it was written.
+pub fn lint(ast: &[AstNode], levels: &Levels) -> Vec<Warning> {
+    let mut linter = Linter::new(levels.clone());
+    linter.program(ast);
+
+    let mut warnings = linter.warnings;
+    warnings.sort_by_key(|warning| (warning.span.start.line(), warning.span.start.character()));
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    /// Lints the source, returning each warning as it would be displayed.
+    fn warnings(source: &str, levels: &Levels) -> Vec<String> {
+        lint(&parse_source(source).unwrap(), levels)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn unused_variable() {
+        assert_eq!(
+            warnings(
+                "fn f(x: Integer) -> Integer { let y = x; let z = 2; y }
+                let a = 1;
+                let _b = 2;
+                let c = if a { let d = a; 3 } else { 4 };",
+                &Levels::default()
+            ),
+            [
+                "1:46: unused variable z [unused_variable]",
+                "4:21: unused variable c [unused_variable]",
+                "4:36: unused variable d [unused_variable]",
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowed_binding() {
+        assert_eq!(
+            warnings(
+                "fn f(x: Integer) -> Integer { if true { let x = 1; x } else { x } }
+                let a = 1;
+                let b = if a { let a = 2; a } else { 3 };
+                print(b);",
+                &Levels::default()
+            ),
+            [
+                "1:34: condition is always true [constant_condition]",
+                "1:45: x shadows an existing binding [shadowed_binding]",
+                "3:36: a shadows an existing binding [shadowed_binding]",
+            ]
+        );
+    }
+
+    #[test]
+    fn redundant_parentheses() {
+        assert_eq!(
+            warnings(
+                "let a = (1);
+                let b = (a + 1) * 2 + (a * 2) - (a - 1);
+                let c = -(a * 2) - (-a) * 2 + ((a));
+                let d = a ^ (b ^ c) + (a ^ b) ^ c;
+                if (Flag { set: true }) { (print(d)); }",
+                &Levels::default()
+            ),
+            [
+                "1:10: redundant parentheses around expression [redundant_parentheses]",
+                "2:40: redundant parentheses around expression [redundant_parentheses]",
+                "3:37: redundant parentheses around expression [redundant_parentheses]",
+                "3:49: redundant parentheses around expression [redundant_parentheses]",
+                "4:30: redundant parentheses around expression [redundant_parentheses]",
+                "5:44: redundant parentheses around expression [redundant_parentheses]",
+            ]
+        );
+    }
+
+    #[test]
+    fn levels() {
+        let source = "#[allow(unused_variable)]
+            fn f() { let a = 1; }
+            let f = 0;
+            #[deny(shadowed_binding, unknown)]
+            let b = if false { let f = 1; f } else { 2 };
+            #[forbid(constant_condition)]
+            let c = 3;";
+
+        let mut levels = Levels::default();
+        levels.set(Lint::ConstantCondition, Level::Allow);
+        levels.set(Lint::UnusedVariable, Level::Deny);
+
+        let found = lint(&parse_source(source).unwrap(), &levels);
+        assert_eq!(
+            found
+                .iter()
+                .map(|warning| (warning.lint, warning.level))
+                .collect::<Vec<_>>(),
+            [
+                (Lint::UnusedVariable, Level::Deny),
+                (Lint::Unknown, Level::Warn),
+                (Lint::UnusedVariable, Level::Deny),
+                (Lint::ShadowedBinding, Level::Deny),
+                (Lint::Unknown, Level::Warn),
+                (Lint::UnusedVariable, Level::Deny),
+            ]
+        );
+        assert_eq!(found[1].message, "unknown lint unknown");
+        assert_eq!(found[4].message, "unknown attribute forbid");
+    }
+}
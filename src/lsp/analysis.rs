@@ -0,0 +1,291 @@
+use crate::{
+    checks::typing::{
+        Type, TypeEnvironment, TypedAstNode, TypedBlock, TypedExpression, TypedExpressionKind,
+        TypedFunction, TypedLet,
+    },
+    lexer::{
+        cursor::{Position, Span},
+        Lexer,
+    },
+    lints::{self, Levels},
+    parser::parse,
+    token_stream::TokenStream,
+    Diagnostic, Host,
+};
+
+/// A name that can be completed, along with its kind as numbered in the protocol.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Completion {
+    pub label: String,
+    pub kind: u8,
+    pub detail: String,
+}
+
+const FUNCTION: u8 = 3;
+const VARIABLE: u8 = 6;
+const ENUM: u8 = 13;
+const STRUCT: u8 = 22;
+
+/// Everything known about an open document, found by running the front end over its source.
+pub struct Analysis {
+    pub source: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The type checked program, if the source could be parsed. This is kept even if type checking
+    /// failed, with anything that failed to check having the type [Type::Unknown].
+    checked: Option<(TypeEnvironment, Vec<TypedAstNode>)>,
+}
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let tokens = match Lexer::new(source).collect::<Result<Vec<_>, _>>() {
+            Ok(tokens) => tokens,
+            Err(error) => return Self::failed(source, Diagnostic::from_lexer_error(error)),
+        };
+
+        let ast = match parse(TokenStream::from(tokens.into_iter())) {
+            Ok(ast) => ast,
+            Err(error) => {
+                return Self::failed(source, Diagnostic::from_parser_error(error, source))
+            }
+        };
+
+        let mut diagnostics = lints::lint(&ast, &Levels::default())
+            .into_iter()
+            .map(Diagnostic::from)
+            .collect::<Vec<_>>();
+
+        let (environment, typed_ast, errors) = TypeEnvironment::check(ast, &Host::default());
+        diagnostics.extend(errors.into_iter().map(Diagnostic::from));
+
+        Self {
+            source: source.to_string(),
+            diagnostics,
+            checked: Some((environment, typed_ast)),
+        }
+    }
+
+    /// An analysis of source that couldn't be parsed, so only has the one diagnostic.
+    fn failed(source: &str, diagnostic: Diagnostic) -> Self {
+        Self {
+            source: source.to_string(),
+            diagnostics: vec![diagnostic],
+            checked: None,
+        }
+    }
+
+    fn find<'a>(&self, position: &'a Position) -> Option<Finder<'a>> {
+        let (_, typed_ast) = self.checked.as_ref()?;
+
+        let mut finder = Finder::new(position);
+        finder.program(typed_ast);
+        Some(finder)
+    }
+
+    /// The identifier at the position along with its type, eg `a: Integer`.
+    pub fn hover(&self, position: &Position) -> Option<(Span, String)> {
+        let found = self.find(position)?.found?;
+
+        Some((found.span, format!("{}: {}", found.ident, found.ty)))
+    }
+
+    /// Where the binding referred to by the identifier at the position was declared.
+    pub fn definition(&self, position: &Position) -> Option<Span> {
+        self.find(position)?.found?.definition
+    }
+
+    /// Every name that is in scope at the position, sorted by name.
+    pub fn completions(&self, position: &Position) -> Vec<Completion> {
+        let (Some((environment, _)), Some(finder)) = (&self.checked, self.find(position)) else {
+            return Vec::new();
+        };
+
+        let mut completions = Vec::new();
+
+        // Shadowed bindings can't be referred to, so only the last of each is kept
+        for (i, binding) in finder.in_scope.iter().enumerate() {
+            if finder.in_scope[i + 1..]
+                .iter()
+                .all(|later| later.ident != binding.ident)
+            {
+                completions.push(Completion {
+                    label: binding.ident.clone(),
+                    kind: VARIABLE,
+                    detail: binding.ty.to_string(),
+                });
+            }
+        }
+        completions.extend(
+            environment
+                .functions()
+                .map(|(ident, signature)| Completion {
+                    label: ident.clone(),
+                    kind: FUNCTION,
+                    detail: signature,
+                }),
+        );
+        completions.extend(environment.types().map(|(ident, is_struct)| Completion {
+            label: ident.clone(),
+            kind: if is_struct { STRUCT } else { ENUM },
+            detail: if is_struct { "struct" } else { "enum" }.to_string(),
+        }));
+
+        completions.sort();
+        completions
+    }
+}
+
+#[derive(Clone)]
+struct Binding {
+    ident: String,
+    ty: Type,
+    /// The span of the ident where it was bound, which parameters don't have.
+    span: Option<Span>,
+}
+
+/// An identifier found at the position being looked up.
+struct Found {
+    ident: String,
+    span: Span,
+    ty: Type,
+    definition: Option<Span>,
+}
+
+/// Walks the typed AST, finding the innermost identifier at a position and every binding that is
+/// in scope there.
+struct Finder<'a> {
+    position: &'a Position,
+    /// Each binding in scope at the current point of the walk, in the order they were declared.
+    bindings: Vec<Binding>,
+    /// The bindings that are in scope at the position.
+    in_scope: Vec<Binding>,
+    found: Option<Found>,
+}
+impl<'a> Finder<'a> {
+    fn new(position: &'a Position) -> Self {
+        Self {
+            position,
+            bindings: Vec::new(),
+            in_scope: Vec::new(),
+            found: None,
+        }
+    }
+
+    fn program(&mut self, ast: &[TypedAstNode]) {
+        // The top level contains every position
+        self.statements(ast, true);
+    }
+
+    fn statements(&mut self, statements: &[TypedAstNode], contains: bool) {
+        for statement in statements {
+            match statement {
+                TypedAstNode::Let(let_node) => self.let_binding(let_node, contains),
+                TypedAstNode::Expression(expression) => self.expression(expression),
+                TypedAstNode::Function(function) => self.function(function),
+            }
+        }
+    }
+
+    fn let_binding(&mut self, let_node: &TypedLet, contains: bool) {
+        self.expression(&let_node.rhs);
+
+        if let_node.span.contains(self.position) {
+            self.found = Some(Found {
+                ident: let_node.ident.clone(),
+                span: let_node.span.clone(),
+                ty: let_node.rhs.ty.clone(),
+                definition: Some(let_node.span.clone()),
+            });
+        }
+
+        self.bindings.push(Binding {
+            ident: let_node.ident.clone(),
+            ty: let_node.rhs.ty.clone(),
+            span: Some(let_node.span.clone()),
+        });
+
+        // Bindings are in scope from the end of their ident, within the same block
+        if contains && &let_node.span.end < self.position {
+            self.in_scope = self.bindings.clone();
+        }
+    }
+
+    fn function(&mut self, function: &TypedFunction) {
+        // Functions can only refer to their own parameters and bindings
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|(ident, ty)| Binding {
+                ident: ident.clone(),
+                ty: ty.clone(),
+                span: None,
+            })
+            .collect();
+        let outer = std::mem::replace(&mut self.bindings, parameters);
+
+        self.block(&function.body);
+
+        self.bindings = outer;
+    }
+
+    fn block(&mut self, block: &TypedBlock) {
+        let scope = self.bindings.len();
+        let contains = block.span.contains(self.position);
+        if contains {
+            self.in_scope = self.bindings.clone();
+        }
+
+        self.statements(&block.statements, contains);
+        if let Some(expression) = &block.expression {
+            self.expression(expression);
+        }
+
+        self.bindings.truncate(scope);
+    }
+
+    fn expression(&mut self, expression: &TypedExpression) {
+        match &expression.kind {
+            TypedExpressionKind::Ident(ident) => {
+                if expression.span.contains(self.position) {
+                    self.found = Some(Found {
+                        ident: ident.clone(),
+                        span: expression.span.clone(),
+                        ty: expression.ty.clone(),
+                        definition: self
+                            .bindings
+                            .iter()
+                            .rfind(|binding| &binding.ident == ident)
+                            .and_then(|binding| binding.span.clone()),
+                    });
+                }
+            }
+            TypedExpressionKind::Literal(_) => (),
+            TypedExpressionKind::BinaryOperation { lhs, rhs, .. } => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            TypedExpressionKind::UnaryOperation { rhs, .. } => self.expression(rhs),
+            TypedExpressionKind::Call { arguments, .. }
+            | TypedExpressionKind::Variant { arguments, .. }
+            | TypedExpressionKind::Interpolation(arguments) => {
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            TypedExpressionKind::Struct { fields, .. } => {
+                for (_, value) in fields {
+                    self.expression(value);
+                }
+            }
+            TypedExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.expression(condition);
+                self.block(then);
+                if let Some(otherwise) = otherwise {
+                    self.block(otherwise);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+use thiserror::Error;
+
+/// A JSON value, as sent between the client and the server. Objects keep their keys in the order
+/// that they were written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+impl Json {
+    /// Creates an object from each of the keys and values.
+    pub fn object<'a>(entries: impl IntoIterator<Item = (&'a str, Json)>) -> Self {
+        Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Looks up the value at `key` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries
+                .iter()
+                .find(|(entry, _)| entry == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Follows each key in turn through nested objects. Eg `["textDocument", "uri"]`.
+    pub fn at(&self, path: &[&str]) -> Option<&Json> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// The value as an index, if it is a non-negative whole number.
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(number) if *number >= 0.0 && number.fract() == 0.0 => {
+                Some(*number as usize)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Parses a complete JSON document.
+    pub fn parse(source: &str) -> Result<Json, JsonError> {
+        let mut parser = JsonParser {
+            chars: source.chars().peekable(),
+        };
+
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(JsonError::UnexpectedCharacter(c)),
+        }
+    }
+}
+impl From<&str> for Json {
+    fn from(string: &str) -> Self {
+        Json::String(string.to_string())
+    }
+}
+impl From<String> for Json {
+    fn from(string: String) -> Self {
+        Json::String(string)
+    }
+}
+impl From<bool> for Json {
+    fn from(boolean: bool) -> Self {
+        Json::Boolean(boolean)
+    }
+}
+impl From<usize> for Json {
+    fn from(number: usize) -> Self {
+        Json::Number(number as f64)
+    }
+}
+impl From<Vec<Json>> for Json {
+    fn from(values: Vec<Json>) -> Self {
+        Json::Array(values)
+    }
+}
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Boolean(boolean) => write!(f, "{boolean}"),
+            Json::Number(number) => write!(f, "{number}"),
+            Json::String(string) => write_string(f, string),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Writes the string surrounded by quotes, escaping anything that can't appear within it.
+fn write_string(f: &mut std::fmt::Formatter<'_>, string: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+
+    for c in string.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+#[derive(Debug, Error)]
+pub enum JsonError {
+    #[error("unexpected end of JSON")]
+    UnexpectedEnd,
+    #[error("unexpected character {0:?} in JSON")]
+    UnexpectedCharacter(char),
+    #[error("invalid number {0} in JSON")]
+    InvalidNumber(String),
+    #[error("invalid escape in JSON string")]
+    InvalidEscape,
+}
+
+struct JsonParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn next(&mut self) -> Result<char, JsonError> {
+        self.chars.next().ok_or(JsonError::UnexpectedEnd)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(JsonError::UnexpectedCharacter(c)),
+        }
+    }
+
+    /// Consumes the rest of a keyword, after its first character.
+    fn keyword(&mut self, rest: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in rest.chars() {
+            self.expect(expected)?;
+        }
+
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+
+        match self.next()? {
+            'n' => self.keyword("ull", Json::Null),
+            't' => self.keyword("rue", Json::Boolean(true)),
+            'f' => self.keyword("alse", Json::Boolean(false)),
+            '"' => self.string().map(Json::String),
+            '[' => {
+                let mut values = Vec::new();
+
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&']').is_none() {
+                    loop {
+                        values.push(self.value()?);
+
+                        self.skip_whitespace();
+                        match self.next()? {
+                            ',' => continue,
+                            ']' => break,
+                            c => return Err(JsonError::UnexpectedCharacter(c)),
+                        }
+                    }
+                }
+
+                Ok(Json::Array(values))
+            }
+            '{' => {
+                let mut entries = Vec::new();
+
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&'}').is_none() {
+                    loop {
+                        self.skip_whitespace();
+                        self.expect('"')?;
+                        let key = self.string()?;
+
+                        self.skip_whitespace();
+                        self.expect(':')?;
+                        entries.push((key, self.value()?));
+
+                        self.skip_whitespace();
+                        match self.next()? {
+                            ',' => continue,
+                            '}' => break,
+                            c => return Err(JsonError::UnexpectedCharacter(c)),
+                        }
+                    }
+                }
+
+                Ok(Json::Object(entries))
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+                {
+                    number.push(c);
+                }
+
+                number
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| JsonError::InvalidNumber(number))
+            }
+            c => Err(JsonError::UnexpectedCharacter(c)),
+        }
+    }
+
+    /// Parses the rest of a string, after the opening quote.
+    fn string(&mut self) -> Result<String, JsonError> {
+        let mut string = String::new();
+
+        loop {
+            match self.next()? {
+                '"' => return Ok(string),
+                '\\' => string.push(match self.next()? {
+                    '"' => '"',
+                    '\\' => '\\',
+                    '/' => '/',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let unit = self.code_unit()?;
+
+                        // Characters outside of the basic plane are written as a surrogate pair
+                        let code = if (0xd800..0xdc00).contains(&unit) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.code_unit()?;
+
+                            0x10000 + ((unit - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+                        } else {
+                            unit
+                        };
+
+                        char::from_u32(code).ok_or(JsonError::InvalidEscape)?
+                    }
+                    _ => return Err(JsonError::InvalidEscape),
+                }),
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// Parses the four hex digits of a `\u` escape.
+    fn code_unit(&mut self) -> Result<u32, JsonError> {
+        (0..4).try_fold(0, |unit, _| {
+            let digit = self.next()?.to_digit(16).ok_or(JsonError::InvalidEscape)?;
+            Ok(unit * 16 + digit)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn round_trip() {
+        let source = r#"{"jsonrpc":"2.0","id":1,"params":{"list":[true,false,null,-1.5,2e3],"empty":{},"none":[]}}"#;
+        let json = Json::parse(source).unwrap();
+
+        assert_eq!(
+            json.at(&["params", "list"])
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            5
+        );
+        assert_eq!(json.get("id").unwrap().as_usize(), Some(1));
+        assert_eq!(
+            json.to_string(),
+            r#"{"jsonrpc":"2.0","id":1,"params":{"list":[true,false,null,-1.5,2000],"empty":{},"none":[]}}"#
+        );
+        assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+    }
+
+    #[test]
+    fn strings() {
+        let json = Json::parse(r#" "a \"b\" \\ \n é \ud83d\ude00 c" "#).unwrap();
+
+        assert_eq!(json.as_str(), Some("a \"b\" \\ \n é 😀 c"));
+        assert_eq!(json.to_string(), "\"a \\\"b\\\" \\\\ \\n é 😀 c\"");
+
+        assert!(Json::parse(r#""unterminated"#).is_err());
+        assert!(Json::parse(r#"{"a": 1,}"#).is_err());
+        assert!(Json::parse("[1] 2").is_err());
+    }
+}
@@ -0,0 +1,762 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Read, Write},
+};
+
+use thiserror::Error;
+
+use crate::{
+    lexer::cursor::{Position, Span},
+    Diagnostic, Severity,
+};
+
+use self::{analysis::Analysis, json::Json};
+
+mod analysis;
+mod json;
+
+/// The JSON-RPC error code for a message that couldn't be parsed.
+const PARSE_ERROR: i32 = -32700;
+/// The JSON-RPC error code for a request that can't be handled in the server's current state.
+const INVALID_REQUEST: i32 = -32600;
+/// The JSON-RPC error code for a request that the server doesn't support.
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// The length in bytes of the longest message that will be read, so that a bogus
+/// `Content-Length` can't exhaust memory. Longer messages are skipped.
+const MAX_MESSAGE_LENGTH: usize = 64 << 20;
+
+#[derive(Debug, Error)]
+pub enum LspError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    #[error("message is missing a Content-Length header")]
+    MissingContentLength,
+    #[error("invalid header {0:?}")]
+    InvalidHeader(String),
+    #[error("message of {0} bytes is too long")]
+    MessageTooLong(usize),
+}
+
+/// Runs a language server, reading messages from `input` and writing responses and notifications
+/// to `output` until the client asks it to exit or closes the input.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> Result<(), LspError> {
+    let mut server = Server::default();
+
+    loop {
+        let body = match read_message(&mut input) {
+            Ok(Some(body)) => body,
+            Ok(None) => break,
+            // The message has been skipped, so the next can still be read
+            Err(error @ LspError::MessageTooLong(_)) => {
+                let response = error_response(Json::Null, INVALID_REQUEST, &error.to_string());
+                write_message(&mut output, &response)?;
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+
+        let message = match String::from_utf8(body)
+            .ok()
+            .and_then(|body| Json::parse(&body).ok())
+        {
+            Some(message) => message,
+            None => {
+                let response = error_response(Json::Null, PARSE_ERROR, "invalid message");
+                write_message(&mut output, &response)?;
+                continue;
+            }
+        };
+
+        let Some(method) = message.get("method").and_then(Json::as_str) else {
+            // Responses to requests from the server aren't needed, as it doesn't make any
+            continue;
+        };
+        let params = message.get("params").unwrap_or(&Json::Null);
+
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.request(method, params) {
+                    Ok(result) => Json::object([
+                        ("jsonrpc", "2.0".into()),
+                        ("id", id.clone()),
+                        ("result", result),
+                    ]),
+                    Err((code, message)) => error_response(id.clone(), code, &message),
+                };
+                write_message(&mut output, &response)?;
+            }
+            None if method == "exit" => break,
+            None => {
+                for notification in server.notify(method, params) {
+                    write_message(&mut output, &notification)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the body of the next message, which is preceded by a `Content-Length` header. Returns
+/// [None] once the input has been closed, or an error if the message is longer than
+/// [MAX_MESSAGE_LENGTH], once it has been skipped.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Vec<u8>>, LspError> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| LspError::InvalidHeader(header.to_string()))?;
+        if name.eq_ignore_ascii_case("Content-Length") {
+            length = Some(
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| LspError::InvalidHeader(header.to_string()))?,
+            );
+        }
+    }
+
+    let length = length.ok_or(LspError::MissingContentLength)?;
+    let mut body = input.take(length as u64);
+    if length > MAX_MESSAGE_LENGTH {
+        io::copy(&mut body, &mut io::sink())?;
+        return Err(LspError::MessageTooLong(length));
+    }
+
+    // The buffer grows as the body arrives, rather than trusting the length up front
+    let mut buffer = Vec::new();
+    if body.read_to_end(&mut buffer)? < length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(Some(buffer))
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> Result<(), LspError> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()?;
+
+    Ok(())
+}
+
+fn error_response(id: Json, code: i32, message: &str) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(code.into())),
+                ("message", message.into()),
+            ]),
+        ),
+    ])
+}
+
+/// How the characters of a position are counted, which is agreed with the client when it
+/// initialises the server.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Encoding {
+    /// The protocol's default, which every client supports.
+    #[default]
+    Utf16,
+    /// Unicode scalar values, which is how positions are counted within the language.
+    Utf32,
+}
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf16 => "utf-16",
+            Encoding::Utf32 => "utf-32",
+        }
+    }
+
+    /// Converts a position from the client into one within the source.
+    fn position(self, source: &str, line: usize, character: usize) -> Position {
+        if self == Encoding::Utf32 {
+            return Position::at(line, character);
+        }
+        let text = source.split('\n').nth(line).unwrap_or_default();
+
+        let mut units = 0;
+        for (characters, c) in text.chars().enumerate() {
+            units += c.len_utf16();
+            if units > character {
+                return Position::at(line, characters);
+            }
+        }
+
+        // Past the end of the line, each unit is counted as a character
+        let characters = text.chars().count() + character - units;
+        Position::at(line, characters)
+    }
+
+    /// The number of units that the client counts before the first `characters` characters of
+    /// the line. Characters past the end of the line count as one unit each.
+    fn units(self, source: &str, line: usize, characters: usize) -> usize {
+        if self == Encoding::Utf32 {
+            return characters;
+        }
+        let text = source.split('\n').nth(line).unwrap_or_default();
+
+        let within = text.chars().take(characters);
+        characters - within.clone().count() + within.map(char::len_utf16).sum::<usize>()
+    }
+
+    /// Converts a span within the source to a range, which unlike a span ends after its last
+    /// character.
+    fn range(self, source: &str, span: &Span) -> Json {
+        let position = |line: usize, characters: usize| {
+            Json::object([
+                ("line", line.into()),
+                ("character", self.units(source, line, characters).into()),
+            ])
+        };
+
+        Json::object([
+            ("start", position(span.start.line(), span.start.character())),
+            ("end", position(span.end.line(), span.end.character() + 1)),
+        ])
+    }
+}
+
+/// Each of the documents that the client has opened, analysed whenever they change.
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, Analysis>,
+    encoding: Encoding,
+    shut_down: bool,
+}
+impl Server {
+    /// Handles a request, returning either its result or an error code and message.
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i32, String)> {
+        if self.shut_down {
+            return Err((INVALID_REQUEST, "server has been shut down".to_string()));
+        }
+
+        match method {
+            "initialize" => {
+                let encodings = params
+                    .at(&["capabilities", "general", "positionEncodings"])
+                    .and_then(Json::as_array)
+                    .unwrap_or_default();
+                // Counting scalar values avoids converting every position, so is preferred
+                if encodings
+                    .iter()
+                    .any(|encoding| encoding.as_str() == Some("utf-32"))
+                {
+                    self.encoding = Encoding::Utf32;
+                }
+
+                Ok(Json::object([
+                    (
+                        "capabilities",
+                        Json::object([
+                            ("positionEncoding", self.encoding.name().into()),
+                            // The whole document is sent whenever it changes
+                            ("textDocumentSync", 1.into()),
+                            ("hoverProvider", true.into()),
+                            ("definitionProvider", true.into()),
+                            ("completionProvider", Json::object([])),
+                        ]),
+                    ),
+                    ("serverInfo", Json::object([("name", "lang".into())])),
+                ]))
+            }
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => Ok(self
+                .lookup(params)
+                .and_then(|(_, analysis, position)| Some((analysis, analysis.hover(&position)?)))
+                .map(|(analysis, (span, text))| {
+                    Json::object([
+                        (
+                            "contents",
+                            Json::object([("kind", "plaintext".into()), ("value", text.into())]),
+                        ),
+                        ("range", self.encoding.range(&analysis.source, &span)),
+                    ])
+                })
+                .unwrap_or(Json::Null)),
+            "textDocument/definition" => Ok(self
+                .lookup(params)
+                .and_then(|(uri, analysis, position)| {
+                    let span = analysis.definition(&position)?;
+                    Some(Json::object([
+                        ("uri", uri.into()),
+                        ("range", self.encoding.range(&analysis.source, &span)),
+                    ]))
+                })
+                .unwrap_or(Json::Null)),
+            "textDocument/completion" => Ok(self
+                .lookup(params)
+                .map(|(_, analysis, position)| {
+                    analysis
+                        .completions(&position)
+                        .into_iter()
+                        .map(|completion| {
+                            Json::object([
+                                ("label", completion.label.into()),
+                                ("kind", usize::from(completion.kind).into()),
+                                ("detail", completion.detail.into()),
+                            ])
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+                .into()),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method {method}"))),
+        }
+    }
+
+    /// Finds the document and position that a request refers to.
+    fn lookup<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a Analysis, Position)> {
+        let uri = params.at(&["textDocument", "uri"])?.as_str()?;
+        let line = params.at(&["position", "line"])?.as_usize()?;
+        let character = params.at(&["position", "character"])?.as_usize()?;
+
+        let analysis = self.documents.get(uri)?;
+
+        Some((
+            uri,
+            analysis,
+            self.encoding.position(&analysis.source, line, character),
+        ))
+    }
+
+    /// Handles a notification, returning any notifications to send back to the client.
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let Some(uri) = params.at(&["textDocument", "uri"]).and_then(Json::as_str) else {
+            return Vec::new();
+        };
+
+        let source = match method {
+            "textDocument/didOpen" => params.at(&["textDocument", "text"]),
+            // Only full documents are synchronised, so the last change holds the whole source
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text")),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![diagnostics(uri, &[], |_| Json::Null)];
+            }
+            _ => return Vec::new(),
+        };
+        let Some(source) = source.and_then(Json::as_str) else {
+            return Vec::new();
+        };
+
+        let analysis = Analysis::new(source);
+        let notification = diagnostics(uri, &analysis.diagnostics, |span| {
+            self.encoding.range(&analysis.source, span)
+        });
+        self.documents.insert(uri.to_string(), analysis);
+
+        vec![notification]
+    }
+}
+
+/// A `textDocument/publishDiagnostics` notification, replacing any previous diagnostics for the
+/// document. Each span is converted to a range by `range`.
+fn diagnostics(uri: &str, diagnostics: &[Diagnostic], range: impl Fn(&Span) -> Json) -> Json {
+    let diagnostics = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            Json::object([
+                ("range", range(&diagnostic.span)),
+                (
+                    "severity",
+                    match diagnostic.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                    }
+                    .into(),
+                ),
+                ("source", "lang".into()),
+                ("message", diagnostic.message.as_str().into()),
+            ])
+        })
+        .collect::<Vec<_>>();
+
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json::Json, serve};
+
+    const URI: &str = "file:///test.lang";
+
+    /// Frames each of the messages as a client would, then runs the server over them, returning
+    /// every message that it sent back.
+    fn run(messages: &[Json]) -> Vec<Json> {
+        let mut input = Vec::new();
+        for message in messages {
+            let body = message.to_string();
+            input.extend(format!("Content-Length: {}\r\n\r\n{body}", body.len()).bytes());
+        }
+
+        run_input(&input)
+    }
+
+    /// Runs the server over input that has already been framed.
+    fn run_input(input: &[u8]) -> Vec<Json> {
+        let mut output = Vec::new();
+        serve(input, &mut output).unwrap();
+
+        let mut output = String::from_utf8(output).unwrap();
+        let mut responses = Vec::new();
+        while let Some((header, rest)) = output.split_once("\r\n\r\n") {
+            let length = header
+                .strip_prefix("Content-Length: ")
+                .unwrap()
+                .parse()
+                .unwrap();
+            responses.push(Json::parse(&rest[..length]).unwrap());
+            output = rest[length..].to_string();
+        }
+
+        responses
+    }
+
+    fn request(id: usize, method: &str, params: Json) -> Json {
+        Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+
+    fn notification(method: &str, params: Json) -> Json {
+        Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+
+    fn open(source: &str) -> Json {
+        notification(
+            "textDocument/didOpen",
+            Json::object([(
+                "textDocument",
+                Json::object([
+                    ("uri", URI.into()),
+                    ("languageId", "lang".into()),
+                    ("version", 1.into()),
+                    ("text", source.into()),
+                ]),
+            )]),
+        )
+    }
+
+    /// A request about a position within the document.
+    fn at(id: usize, method: &str, line: usize, character: usize) -> Json {
+        request(
+            id,
+            method,
+            Json::object([
+                ("textDocument", Json::object([("uri", URI.into())])),
+                (
+                    "position",
+                    Json::object([("line", line.into()), ("character", character.into())]),
+                ),
+            ]),
+        )
+    }
+
+    #[test]
+    fn lifecycle() {
+        let responses = run(&[
+            request(1, "initialize", Json::object([])),
+            notification("initialized", Json::object([])),
+            request(2, "workspace/symbol", Json::object([])),
+            request(3, "shutdown", Json::Null),
+            request(4, "textDocument/hover", Json::object([])),
+            notification("exit", Json::Null),
+            request(5, "shutdown", Json::Null),
+        ]);
+
+        assert_eq!(responses.len(), 4);
+        assert_eq!(
+            responses[0].at(&["result", "capabilities", "hoverProvider"]),
+            Some(&Json::Boolean(true))
+        );
+        assert_eq!(
+            responses[1].at(&["error", "code"]),
+            Some(&Json::Number(-32601.0))
+        );
+        assert_eq!(responses[2].get("result"), Some(&Json::Null));
+        assert_eq!(
+            responses[3].at(&["error", "code"]),
+            Some(&Json::Number(-32600.0))
+        );
+    }
+
+    #[test]
+    fn oversized_messages() {
+        // Nothing is allocated for the claimed length, and the client is told that the message was
+        // skipped
+        let input = format!("Content-Length: {}\r\n\r\n{{}}", usize::MAX);
+        let responses = run_input(input.as_bytes());
+
+        assert_eq!(responses.len(), 1);
+        assert_eq!(
+            responses[0].at(&["error", "code"]),
+            Some(&Json::Number(-32600.0))
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        let responses = run(&[
+            open("let a = 1 +;"),
+            notification(
+                "textDocument/didChange",
+                Json::object([
+                    ("textDocument", Json::object([("uri", URI.into())])),
+                    (
+                        "contentChanges",
+                        vec![Json::object([(
+                            "text",
+                            "let a = 1 + true;\nlet b = 2;".into(),
+                        )])]
+                        .into(),
+                    ),
+                ]),
+            ),
+            notification(
+                "textDocument/didClose",
+                Json::object([("textDocument", Json::object([("uri", URI.into())]))]),
+            ),
+        ]);
+
+        assert_eq!(
+            responses[0].to_string(),
+            r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///test.lang","diagnostics":[{"range":{"start":{"line":0,"character":11},"end":{"line":0,"character":12}},"severity":1,"source":"lang","message":"unexpected Semi"}]}}"#
+        );
+
+        let diagnostics = responses[1].at(&["params", "diagnostics"]).unwrap();
+        let messages = diagnostics
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.get("severity").unwrap().as_usize().unwrap(),
+                    diagnostic.get("message").unwrap().as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                (2, "unused variable a [unused_variable]"),
+                (2, "unused variable b [unused_variable]"),
+                (1, "Mismatched types: Integer and Boolean"),
+            ]
+        );
+
+        assert_eq!(
+            responses[2].at(&["params", "diagnostics"]),
+            Some(&Json::Array(Vec::new()))
+        );
+    }
+
+    #[test]
+    fn hover_and_definition() {
+        let source = "let a = 1;
+fn f(x: Integer) -> Integer {
+    let a = true;
+    if a { x } else { 2 }
+}
+print(a + f(a));";
+
+        let responses = run(&[
+            open(source),
+            at(1, "textDocument/hover", 3, 7),
+            at(2, "textDocument/definition", 3, 7),
+            at(3, "textDocument/hover", 5, 6),
+            at(4, "textDocument/definition", 5, 12),
+            at(5, "textDocument/hover", 3, 11),
+            at(6, "textDocument/definition", 3, 11),
+            at(7, "textDocument/hover", 1, 0),
+        ]);
+
+        let hover = |response: &Json| {
+            response
+                .at(&["result", "contents", "value"])
+                .and_then(Json::as_str)
+                .map(str::to_string)
+        };
+        let definition = |response: &Json| {
+            let start = response.at(&["result", "range", "start"])?;
+            Some((
+                start.get("line")?.as_usize()?,
+                start.get("character")?.as_usize()?,
+            ))
+        };
+
+        assert_eq!(hover(&responses[1]).as_deref(), Some("a: Boolean"));
+        assert_eq!(definition(&responses[2]), Some((2, 8)));
+        assert_eq!(hover(&responses[3]).as_deref(), Some("a: Integer"));
+        assert_eq!(definition(&responses[4]), Some((0, 4)));
+        // Parameters can be hovered, but aren't bound by a let
+        assert_eq!(hover(&responses[5]).as_deref(), Some("x: Integer"));
+        assert_eq!(responses[6].get("result"), Some(&Json::Null));
+        assert_eq!(responses[7].get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn position_encodings() {
+        // The emoji is one character, but two UTF-16 code units
+        let source = "let a = \"\u{1f600}\"; let b = a + a;";
+        let initialize = |encodings: Vec<Json>| {
+            request(
+                1,
+                "initialize",
+                Json::object([(
+                    "capabilities",
+                    Json::object([(
+                        "general",
+                        Json::object([("positionEncodings", encodings.into())]),
+                    )]),
+                )]),
+            )
+        };
+        let character = |response: &Json, path: &[&str]| {
+            response
+                .at(&[path, &["character"]].concat())
+                .and_then(Json::as_usize)
+        };
+
+        let responses = run(&[
+            initialize(vec!["utf-16".into()]),
+            open(source),
+            at(2, "textDocument/hover", 0, 22),
+        ]);
+        assert_eq!(
+            responses[0].at(&["result", "capabilities", "positionEncoding"]),
+            Some(&Json::from("utf-16"))
+        );
+        let diagnostic = &responses[1]
+            .at(&["params", "diagnostics"])
+            .unwrap()
+            .as_array()
+            .unwrap()[0];
+        assert_eq!(character(diagnostic, &["range", "start"]), Some(18));
+        assert_eq!(
+            character(&responses[2], &["result", "range", "start"]),
+            Some(22)
+        );
+        assert_eq!(
+            character(&responses[2], &["result", "range", "end"]),
+            Some(23)
+        );
+
+        let responses = run(&[
+            initialize(vec!["utf-8".into(), "utf-32".into()]),
+            open(source),
+            at(2, "textDocument/hover", 0, 21),
+        ]);
+        assert_eq!(
+            responses[0].at(&["result", "capabilities", "positionEncoding"]),
+            Some(&Json::from("utf-32"))
+        );
+        let diagnostic = &responses[1]
+            .at(&["params", "diagnostics"])
+            .unwrap()
+            .as_array()
+            .unwrap()[0];
+        assert_eq!(character(diagnostic, &["range", "start"]), Some(17));
+        assert_eq!(
+            character(&responses[2], &["result", "range", "start"]),
+            Some(21)
+        );
+        assert_eq!(
+            character(&responses[2], &["result", "range", "end"]),
+            Some(22)
+        );
+    }
+
+    #[test]
+    fn completion() {
+        let source = "struct Pair { first: Integer, second: Integer }
+let a = 1;
+fn f(x: Integer) -> Integer {
+    let y = true;
+
+    x
+}
+let b = 2;
+";
+
+        let responses = run(&[
+            open(source),
+            at(1, "textDocument/completion", 4, 4),
+            at(2, "textDocument/completion", 8, 0),
+        ]);
+
+        let labels = |response: &Json| {
+            response
+                .get("result")
+                .and_then(Json::as_array)
+                .unwrap()
+                .iter()
+                .map(|completion| {
+                    format!(
+                        "{} {}",
+                        completion.get("label").unwrap().as_str().unwrap(),
+                        completion.get("detail").unwrap().as_str().unwrap()
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            labels(&responses[1]),
+            vec![
+                "Pair struct",
+                "f fn(Integer) -> Integer",
+                "print fn<T>(T) -> Unit",
+                "x Integer",
+                "y Boolean",
+            ]
+        );
+        assert_eq!(
+            labels(&responses[2]),
+            vec![
+                "Pair struct",
+                "a Integer",
+                "b Integer",
+                "f fn(Integer) -> Integer",
+                "print fn<T>(T) -> Unit",
+            ]
+        );
+    }
+}
@@ -1,48 +1,190 @@
-use checks::typing::{TypeEnvironment, TypeError};
-use lexer::LexerError;
-use parser::error::ParserError;
-use thiserror::Error;
-use token_stream::TokenStream;
-
-use crate::{lexer::Lexer, parser::parse, token::TokenKind};
+use std::{fs, io, path::Path, process::ExitCode};
 
-mod checks;
-mod lexer;
-mod parser;
-mod token;
-mod token_stream;
+use lang::{
+    backend::{self, BackendError, WasmError},
+    bytecode::{self, file::BytecodeFileError, RuntimeError, Vm},
+    compile_with_lints, format_source, ir,
+    ir::VerifyError,
+    lints::{Level, Levels, Lint},
+    lsp::{self, LspError},
+    syntax_tree, Diagnostics, SAMPLE,
+};
+use thiserror::Error;
 
 #[derive(Debug, Error)]
-#[error(transparent)]
+#[allow(clippy::enum_variant_names)]
 enum CompilerError {
-    LexerError(#[from] LexerError),
-    ParserError(#[from] ParserError),
-    TypeError(#[from] TypeError),
+    #[error(transparent)]
+    Diagnostics(#[from] Diagnostics),
+    #[error(transparent)]
+    RuntimeError(#[from] RuntimeError),
+    #[error(transparent)]
+    BackendError(#[from] BackendError),
+    #[error(transparent)]
+    WasmError(#[from] WasmError),
+    #[error(transparent)]
+    BytecodeFileError(#[from] BytecodeFileError),
+    #[error(transparent)]
+    VerifyError(#[from] VerifyError),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    #[error(transparent)]
+    LspError(#[from] LspError),
+    #[error("unknown lint {0}")]
+    UnknownLint(String),
+    #[error(
+        "usage: lang [-A | -W | -D <lint>]... [check | run | disasm | ir | emit-c | emit-asm | fmt | cst] [file] | [build | native | emit-wasm] <file> [output] | [exec | validate-wasm] <file> | lsp"
+    )]
+    UsageError,
 }
 
-fn main() -> Result<(), CompilerError> {
-    let source = r#"let a = 3;
-let b = 5;
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), CompilerError> {
+    let (levels, args) = lint_levels(args)?;
+
+    let (command, path, output) = match args.as_slice() {
+        [] => ("check", None, None),
+        [command] => (command.as_str(), None, None),
+        [command, path] => (command.as_str(), Some(path), None),
+        [command, path, output] if matches!(command.as_str(), "build" | "native" | "emit-wasm") => {
+            (command.as_str(), Some(path), Some(output))
+        }
+        _ => return Err(CompilerError::UsageError),
+    };
+
+    // The language server reads each document from the client instead
+    if command == "lsp" {
+        lsp::serve(io::stdin().lock(), io::stdout().lock())?;
+
+        return Ok(());
+    }
+
+    // Compiled programs are loaded directly, without going through the front end
+    if command == "exec" {
+        let path = path.ok_or(CompilerError::UsageError)?;
+        let program = bytecode::file::deserialise(&fs::read(path)?)?;
+        Vm::new(&program, io::stdout()).run()?;
+
+        return Ok(());
+    }
+
+    if command == "validate-wasm" {
+        let path = path.ok_or(CompilerError::UsageError)?;
+        let module = backend::validate_wasm(&fs::read(path)?)?;
+        println!(
+            "valid module with {} functions, exporting {}",
+            module.functions,
+            module.exports.join(", ")
+        );
+
+        return Ok(());
+    }
 
-// The result
-let c = a + b;
+    let source = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None => SAMPLE.to_string(),
+    };
 
-let some_bool = true;
-let another_bool = false;
-"#;
+    // Formatting only needs the program to parse, and rewrites the file in place
+    if command == "fmt" {
+        let formatted = format_source(&source)?;
+        match path {
+            Some(path) => fs::write(path, formatted)?,
+            None => print!("{formatted}"),
+        }
 
-    let tokens = Lexer::new(source)
-        .filter(|token| {
-            token
-                .as_ref()
-                .map(|token| !matches!(token.kind, TokenKind::Whitespace))
-                .unwrap_or_default()
-        })
-        // Don't like that the iterator is consumed here just to get the errors out
-        .collect::<Result<Vec<_>, _>>()?;
+        return Ok(());
+    }
 
-    let ast = parse(TokenStream::from(tokens.into_iter()))?;
-    let type_environment = TypeEnvironment::from_ast(ast)?;
+    if command == "cst" {
+        print!("{}", syntax_tree(&source)?.dump());
+
+        return Ok(());
+    }
+
+    let program = compile_with_lints(&source, &levels)?;
+    for warning in program.warnings() {
+        eprintln!("warning: {warning}");
+    }
+    let typed_ast = program.typed_ast();
+
+    match command {
+        "check" => (),
+        "run" => {
+            program.run()?;
+        }
+        "disasm" => print!("{}", program.bytecode()),
+        "ir" => {
+            ir::verify(program.ir())?;
+
+            print!("{}", program.ir());
+        }
+        "emit-c" => print!("{}", backend::emit_c(program.ir())?),
+        "emit-asm" => print!("{}", backend::emit_x86_64(typed_ast)?),
+        "emit-wasm" => {
+            let path = path.ok_or(CompilerError::UsageError)?;
+            let output = match output {
+                Some(output) => output.into(),
+                None => Path::new(path).with_extension("wasm"),
+            };
+
+            fs::write(output, backend::emit_wasm(typed_ast)?)?;
+        }
+        "native" => {
+            let path = path.ok_or(CompilerError::UsageError)?;
+            let output = match output {
+                Some(output) => output.into(),
+                None => Path::new(path).with_extension("out"),
+            };
+
+            backend::link_x86_64(&backend::emit_x86_64(typed_ast)?, &output)?;
+        }
+        "build" => {
+            let path = path.ok_or(CompilerError::UsageError)?;
+            let output = match output {
+                Some(output) => output.into(),
+                None => Path::new(path).with_extension("lbc"),
+            };
+
+            fs::write(output, bytecode::file::serialise(program.bytecode()))?;
+        }
+        _ => return Err(CompilerError::UsageError),
+    }
 
     Ok(())
 }
+
+/// Takes the `-A`, `-W` and `-D` flags out of the arguments, which allow, warn on or deny the
+/// lint that follows them. Later flags override earlier ones.
+fn lint_levels(args: Vec<String>) -> Result<(Levels, Vec<String>), CompilerError> {
+    let mut levels = Levels::default();
+    let mut rest = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let level = match arg.as_str() {
+            "-A" => Level::Allow,
+            "-W" => Level::Warn,
+            "-D" => Level::Deny,
+            _ => {
+                rest.push(arg);
+                continue;
+            }
+        };
+
+        let name = args.next().ok_or(CompilerError::UsageError)?;
+        let lint = Lint::try_from(name.as_str()).map_err(|()| CompilerError::UnknownLint(name))?;
+        levels.set(lint, level);
+    }
+
+    Ok((levels, rest))
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use crate::ir::{InstructionKind, Module, Terminator};
+
+/// Removes every instruction whose value is never used, where evaluating it can't have any
+/// effect. Removing an instruction may leave the values that it used unused, which are then
+/// removed too.
+///
+/// Calls may print, and operations may fail at runtime, so they are always kept.
+pub fn eliminate_dead_code(module: &mut Module) {
+    for function in &mut module.functions {
+        let mut used = HashSet::new();
+
+        // Every use of a value comes after its definition in reverse postorder, so visiting the
+        // blocks and their instructions from last to first sees every use before the definition
+        for block in function.reverse_postorder().into_iter().rev() {
+            let block = &mut function.blocks[block.0];
+            used.extend(block.terminator.iter().flat_map(Terminator::operands));
+
+            let mut live = Vec::new();
+            for instruction in block.instructions.drain(..).rev() {
+                if used.contains(&instruction.value) || !is_pure(&instruction.kind) {
+                    used.extend(instruction.kind.operands());
+                    live.push(instruction);
+                }
+            }
+
+            live.reverse();
+            block.instructions = live;
+        }
+    }
+}
+
+/// Whether executing the instruction can't have any effect, other than defining its value.
+fn is_pure(kind: &InstructionKind) -> bool {
+    match kind {
+        InstructionKind::Constant(_)
+        | InstructionKind::Unit
+        | InstructionKind::Variant { .. }
+        | InstructionKind::Struct { .. }
+        | InstructionKind::Phi(_) => true,
+        InstructionKind::Binary { .. }
+        | InstructionKind::Unary { .. }
+        | InstructionKind::Call { .. }
+        // The result could be too long to fit within a string
+        | InstructionKind::Interpolate(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{front_end, ir::lower, optimise::fold_constants};
+
+    #[test]
+    fn eliminates_dead_code() {
+        let (_, typed_ast) = front_end(
+            "fn f(x: Integer) -> Integer { let unused = x; let kept = x + 1; x }
+            let a = 1;
+            let b = a;
+            let c = f(a);
+            let d = if true { let e = 1; b } else { 2 };
+            print(a);
+            a;",
+        )
+        .unwrap();
+        let mut module = lower(&typed_ast);
+        fold_constants(&mut module).unwrap();
+        eliminate_dead_code(&mut module);
+
+        // Only the addition that may fail at runtime remains, along with the calls. `c` has to
+        // be kept despite being unused, as it calls a function
+        assert_eq!(
+            module.to_string(),
+            "fn f(%0: Integer) -> Integer {
+bb0:
+    %1: Integer = const 1
+    %2: Integer = add %0, %1
+    return %0
+}
+
+fn <main>() -> Unit {
+bb0:
+    %0: Integer = const 1
+    %1: Integer = call f(%0)
+    jump bb1
+bb1:
+    jump bb2
+bb2:
+    %6: Unit = call print<Integer>(%0)
+    %7: Unit = unit
+    return %7
+}
+"
+        );
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use thiserror::Error;
+
+use crate::{
+    bytecode::checked_pow,
+    ir::{BlockId, Function, Instruction, InstructionKind, Module, Terminator, Value},
+    lexer::cursor::Span,
+    parser::parsers::{BinaryOperationKind, UnaryOperationKind},
+    token::Literal,
+};
+
+/// All of the errors that can be found whilst evaluating constant expressions. These would
+/// otherwise happen at runtime.
+#[derive(Debug, Error)]
+pub enum ConstantErrorKind {
+    #[error("attempted to divide by zero")]
+    DivisionByZero,
+    #[error("integer overflow")]
+    Overflow,
+    #[error("attempted to raise to a negative exponent")]
+    NegativeExponent,
+    #[error("attempted to shift by a negative amount")]
+    NegativeShift,
+}
+
+/// A constant evaluation error, along with the span of the expression that caused it.
+#[derive(Debug, Error)]
+#[error("{span}: {kind}")]
+pub struct ConstantError {
+    pub kind: ConstantErrorKind,
+    pub span: Span,
+}
+
+/// Every error found whilst folding a program, in the order that they appear in the source.
+#[derive(Debug, Error)]
+pub struct ConstantErrors(pub Vec<ConstantError>);
+impl Display for ConstantErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Evaluates every instruction whose operands are known at compile time, replacing it with its
+/// result, and turns each branch on a constant condition into a jump. Blocks that can then no
+/// longer be reached are removed.
+///
+/// Operations that would fail at runtime are left in place and reported instead, including those
+/// within blocks that are removed.
+pub fn fold_constants(module: &mut Module) -> Result<(), ConstantErrors> {
+    let mut errors = Vec::new();
+
+    for function in &mut module.functions {
+        let mut folder = Folder {
+            constants: HashMap::new(),
+            replacements: HashMap::new(),
+            edges: HashSet::new(),
+            errors: &mut errors,
+        };
+
+        // Every value is defined before any of its uses in reverse postorder, and so is every
+        // edge into a block
+        for block in function.reverse_postorder() {
+            let reachable = block == Function::ENTRY
+                || folder
+                    .edges
+                    .iter()
+                    .any(|(_, successor)| *successor == block);
+            let block_id = block;
+            let block = &mut function.blocks[block.0];
+
+            for instruction in &mut block.instructions {
+                folder.fold_instruction(block_id, instruction);
+            }
+            block
+                .instructions
+                .retain(|instruction| !folder.replacements.contains_key(&instruction.value));
+
+            if let Some(terminator) = &mut block.terminator {
+                folder.fold_terminator(terminator);
+
+                if reachable {
+                    for successor in terminator.successors() {
+                        folder.edges.insert((block_id, successor));
+                    }
+                }
+            }
+        }
+
+        function.remove_unreachable_blocks();
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        // Functions are lowered before the top level statements, which may come before them
+        errors.sort_by(|a, b| a.span.start.cmp(&b.span.start));
+        Err(ConstantErrors(errors))
+    }
+}
+
+/// Folds a single function. As values are only defined once, a value that is known at compile
+/// time is known wherever it is used.
+struct Folder<'a> {
+    /// The literal that each value is known to be.
+    constants: HashMap<Value, Literal>,
+    /// Phis that were found to always take the same value, and the value that replaces them.
+    replacements: HashMap<Value, Value>,
+    /// Each edge between blocks that control may still take.
+    edges: HashSet<(BlockId, BlockId)>,
+    errors: &'a mut Vec<ConstantError>,
+}
+impl Folder<'_> {
+    fn fold_instruction(&mut self, block: BlockId, instruction: &mut Instruction) {
+        for operand in instruction.kind.operands_mut() {
+            self.replace(operand);
+        }
+        let span = &instruction.span;
+
+        let folded = match &instruction.kind {
+            InstructionKind::Constant(_)
+            | InstructionKind::Unit
+            | InstructionKind::Call { .. }
+            | InstructionKind::Variant { .. }
+            | InstructionKind::Struct { .. } => None,
+            InstructionKind::Binary {
+                operation,
+                lhs,
+                rhs,
+            } => match (self.constants.get(lhs), self.constants.get(rhs)) {
+                (Some(lhs), Some(rhs)) => {
+                    let (lhs, rhs) = (lhs.clone(), rhs.clone());
+                    self.binary_operation(*operation, &lhs, &rhs, span)
+                }
+                // Some divisors fail regardless of what they're applied to
+                (_, Some(&Literal::Integer(rhs))) => {
+                    match operation {
+                        BinaryOperationKind::Div | BinaryOperationKind::Mod if rhs == 0 => {
+                            self.error(ConstantErrorKind::DivisionByZero, span)
+                        }
+                        BinaryOperationKind::Exp if rhs < 0 => {
+                            self.error(ConstantErrorKind::NegativeExponent, span)
+                        }
+                        BinaryOperationKind::Shl | BinaryOperationKind::Shr if rhs < 0 => {
+                            self.error(ConstantErrorKind::NegativeShift, span)
+                        }
+                        _ => (),
+                    }
+
+                    None
+                }
+                _ => None,
+            },
+            InstructionKind::Unary { operation, rhs } => match (operation, self.constants.get(rhs))
+            {
+                (UnaryOperationKind::Negative, Some(&Literal::Integer(integer))) => {
+                    self.check(integer.checked_neg(), span)
+                }
+                (UnaryOperationKind::Complement, Some(Literal::Integer(integer))) => {
+                    Some(Literal::Integer(!integer))
+                }
+                _ => None,
+            },
+            InstructionKind::Interpolate(parts) => parts
+                .iter()
+                .map(|part| match self.constants.get(part)? {
+                    Literal::Integer(integer) => Some(integer.to_string()),
+                    Literal::Boolean(boolean) => Some(boolean.to_string()),
+                    Literal::String(string) => Some(string.clone()),
+                })
+                .collect::<Option<String>>()
+                .map(Literal::String),
+            InstructionKind::Phi(incoming) => {
+                // Only the values arriving along edges that can still be taken matter
+                let values = incoming
+                    .iter()
+                    .filter(|(predecessor, _)| self.edges.contains(&(*predecessor, block)))
+                    .map(|(_, value)| *value)
+                    .collect::<Vec<_>>();
+
+                match values.split_first() {
+                    Some((first, rest)) if rest.iter().all(|value| value == first) => {
+                        self.replacements.insert(instruction.value, *first);
+                        None
+                    }
+                    Some((first, rest)) => self
+                        .constants
+                        .get(first)
+                        .filter(|literal| {
+                            rest.iter()
+                                .all(|value| self.constants.get(value) == Some(literal))
+                        })
+                        .cloned(),
+                    None => None,
+                }
+            }
+        };
+
+        if let Some(literal) = folded {
+            instruction.kind = InstructionKind::Constant(literal);
+        }
+        if let InstructionKind::Constant(literal) = &instruction.kind {
+            self.constants.insert(instruction.value, literal.clone());
+        }
+    }
+
+    fn fold_terminator(&mut self, terminator: &mut Terminator) {
+        for operand in terminator.operands_mut() {
+            self.replace(operand);
+        }
+
+        if let Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        } = terminator
+        {
+            match self.constants.get(condition) {
+                Some(Literal::Boolean(true)) => *terminator = Terminator::Jump(*then),
+                Some(Literal::Boolean(false)) => *terminator = Terminator::Jump(*otherwise),
+                _ => (),
+            }
+        }
+    }
+
+    /// Replaces a use of a phi that always takes the same value with that value.
+    fn replace(&self, value: &mut Value) {
+        while let Some(replacement) = self.replacements.get(value) {
+            *value = *replacement;
+        }
+    }
+
+    /// Evaluates a binary operation on two literals in the same way as the interpreter, returning
+    /// [None] if it can't be evaluated.
+    fn binary_operation(
+        &mut self,
+        operation: BinaryOperationKind,
+        lhs: &Literal,
+        rhs: &Literal,
+        span: &Span,
+    ) -> Option<Literal> {
+        match (operation, lhs, rhs) {
+            (BinaryOperationKind::Add, Literal::String(lhs), Literal::String(rhs)) => {
+                Some(Literal::String(format!("{lhs}{rhs}")))
+            }
+            (operation, Literal::Integer(lhs), Literal::Integer(rhs)) => {
+                let result = match operation {
+                    BinaryOperationKind::Add => lhs.checked_add(*rhs),
+                    BinaryOperationKind::Sub => lhs.checked_sub(*rhs),
+                    BinaryOperationKind::Mult => lhs.checked_mul(*rhs),
+                    BinaryOperationKind::Div if *rhs == 0 => {
+                        self.error(ConstantErrorKind::DivisionByZero, span);
+                        return None;
+                    }
+                    BinaryOperationKind::Div => lhs.checked_div(*rhs),
+                    BinaryOperationKind::Mod if *rhs == 0 => {
+                        self.error(ConstantErrorKind::DivisionByZero, span);
+                        return None;
+                    }
+                    BinaryOperationKind::Mod => Some(lhs.wrapping_rem(*rhs)),
+                    BinaryOperationKind::Exp if *rhs < 0 => {
+                        self.error(ConstantErrorKind::NegativeExponent, span);
+                        return None;
+                    }
+                    BinaryOperationKind::Exp => checked_pow(*lhs, *rhs),
+                    BinaryOperationKind::And => Some(lhs & rhs),
+                    BinaryOperationKind::Or => Some(lhs | rhs),
+                    BinaryOperationKind::Xor => Some(lhs ^ rhs),
+                    BinaryOperationKind::Shl | BinaryOperationKind::Shr if *rhs < 0 => {
+                        self.error(ConstantErrorKind::NegativeShift, span);
+                        return None;
+                    }
+                    BinaryOperationKind::Shl if *rhs >= isize::BITS as isize => Some(0),
+                    BinaryOperationKind::Shl => Some(lhs << rhs),
+                    BinaryOperationKind::Shr => Some(lhs >> (*rhs).min(isize::BITS as isize - 1)),
+                };
+
+                self.check(result, span)
+            }
+            _ => None,
+        }
+    }
+
+    /// Wraps the result of a checked integer operation, reporting an overflow if there isn't one.
+    fn check(&mut self, result: Option<isize>, span: &Span) -> Option<Literal> {
+        if result.is_none() {
+            self.error(ConstantErrorKind::Overflow, span);
+        }
+
+        result.map(Literal::Integer)
+    }
+
+    fn error(&mut self, kind: ConstantErrorKind, span: &Span) {
+        self.errors.push(ConstantError {
+            kind,
+            span: span.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{front_end, ir::lower};
+
+    /// Folds the source, returning the dump of the folded program.
+    fn fold(source: &str) -> Result<String, ConstantErrors> {
+        let (_, typed_ast) = front_end(source).unwrap();
+        let mut module = lower(&typed_ast);
+        fold_constants(&mut module)?;
+
+        Ok(module.to_string())
+    }
+
+    #[test]
+    fn folds_and_propagates() {
+        let dump = fold(
+            "fn scale(x: Integer) -> Integer { let factor = 2 ^ 3; x * factor }
+            let a = 3;
+            let b = 5;
+            let c = a + b * -2;
+            let d = if true { c } else { 0 };
+            print(scale(d));
+            print(\"con\" + \"cat\");",
+        )
+        .unwrap();
+
+        // Only the multiplication by the parameter remains, and the branch is taken
+        // unconditionally, so `d` is replaced by the value of `c`
+        assert_eq!(
+            dump,
+            "fn scale(%0: Integer) -> Integer {
+bb0:
+    %1: Integer = const 2
+    %2: Integer = const 3
+    %3: Integer = const 8
+    %4: Integer = mul %0, %3
+    return %4
+}
+
+fn <main>() -> Unit {
+bb0:
+    %0: Integer = const 3
+    %1: Integer = const 5
+    %2: Integer = const 2
+    %3: Integer = const -2
+    %4: Integer = const -10
+    %5: Integer = const -7
+    %6: Boolean = const true
+    jump bb1
+bb1:
+    jump bb2
+bb2:
+    %9: Integer = call scale(%5)
+    %10: Unit = call print<Integer>(%9)
+    %11: String = const \"con\"
+    %12: String = const \"cat\"
+    %13: String = const \"concat\"
+    %14: Unit = call print<String>(%13)
+    %15: Unit = unit
+    return %15
+}
+"
+        );
+    }
+
+    #[test]
+    fn huge_exponents() {
+        let dump = fold("print(1 ^ 4294967296); print(-1 ^ 4294967297); print((-1) ^ 4294967297);")
+            .unwrap();
+
+        assert_eq!(
+            dump,
+            "fn <main>() -> Unit {
+bb0:
+    %0: Integer = const 1
+    %1: Integer = const 4294967296
+    %2: Integer = const 1
+    %3: Unit = call print<Integer>(%2)
+    %4: Integer = const 1
+    %5: Integer = const 4294967297
+    %6: Integer = const 1
+    %7: Integer = const -1
+    %8: Unit = call print<Integer>(%7)
+    %9: Integer = const 1
+    %10: Integer = const -1
+    %11: Integer = const 4294967297
+    %12: Integer = const -1
+    %13: Unit = call print<Integer>(%12)
+    %14: Unit = unit
+    return %14
+}
+"
+        );
+    }
+
+    #[test]
+    fn reports_errors() {
+        let errors = fold(
+            "fn f(x: Integer) -> Integer { x / 0 }
+            let a = 0;
+            let b = 9223372036854775807;
+            print(1 / a);
+            print(b + 1);
+            print(2 ^ -1);
+            print(-(-b - 1));
+            print(b << a - 1);
+            print(2 ^ 4294967296);",
+        )
+        .err()
+        .unwrap()
+        .0;
+
+        let errors = errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "1:31: attempted to divide by zero",
+                "4:19: attempted to divide by zero",
+                "5:19: integer overflow",
+                "6:19: attempted to raise to a negative exponent",
+                "7:19: integer overflow",
+                "8:19: attempted to shift by a negative amount",
+                "9:19: integer overflow",
+            ]
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! Passes that simplify a program in SSA form before it is handed to a backend, without changing
+//! what it does.
+
+pub use self::{dead::*, fold::*};
+
+mod dead;
+mod fold;
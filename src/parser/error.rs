@@ -21,3 +21,13 @@ pub enum ParserError {
         position: Position,
     },
 }
+impl ParserError {
+    /// Where the error was found, if the parser hadn't run out of tokens.
+    pub fn position(&self) -> Option<&Position> {
+        match self {
+            ParserError::ExpectedTokenToFollow => None,
+            ParserError::ExpectedToken { position, .. }
+            | ParserError::UnexpectedToken { position, .. } => Some(position),
+        }
+    }
+}
@@ -1,40 +1,124 @@
 use crate::{
+    syntax::{Event, SyntaxKind},
     token::{Keyword, TokenKind},
     token_stream::{TokenIterator, TokenStream},
 };
 
 use self::{
     error::{ParserError, ParserResult},
-    parsers::{Expression, Let},
+    parsers::{Attribute, Enum, Expression, Function, Let, Struct},
 };
 
 pub mod error;
 pub mod parsers;
 
 #[allow(unused)]
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum AstNode {
     Let(Let),
     Expression(Expression),
+    Function(Function),
+    Struct(Struct),
+    Enum(Enum),
+}
+impl AstNode {
+    /// The kind of node that the statement appears as within a syntax tree.
+    pub fn syntax_kind(&self) -> SyntaxKind {
+        match self {
+            AstNode::Let(_) => SyntaxKind::Let,
+            AstNode::Expression(_) => SyntaxKind::ExpressionStatement,
+            AstNode::Function(_) => SyntaxKind::Function,
+            AstNode::Struct(_) => SyntaxKind::Struct,
+            AstNode::Enum(_) => SyntaxKind::Enum,
+        }
+    }
 }
 
 pub fn parse<I>(mut tokens: TokenStream<I>) -> ParserResult<Vec<AstNode>>
+where
+    I: TokenIterator,
+{
+    parse_program(&mut tokens)
+}
+
+/// Parses the tokens in the same way as [parse], whilst also recording the events needed to build
+/// a lossless syntax tree.
+pub fn parse_lossless<I>(tokens: TokenStream<I>) -> ParserResult<(Vec<AstNode>, Vec<Event>)>
+where
+    I: TokenIterator,
+{
+    let mut tokens = tokens.lossless();
+    let nodes = parse_program(&mut tokens)?;
+
+    Ok((nodes, tokens.finish()))
+}
+
+fn parse_program<I>(tokens: &mut TokenStream<I>) -> ParserResult<Vec<AstNode>>
 where
     I: TokenIterator,
 {
     let mut nodes = Vec::new();
 
-    while let Ok(token) = tokens.next() {
-        match token.kind {
-            TokenKind::Keyword(Keyword::Let) => nodes.push(AstNode::Let(Let::parse(&mut tokens)?)),
-            TokenKind::Comment(_) => (),
-            t => {
-                return Err(ParserError::UnexpectedToken {
-                    token: t,
-                    position: token.position,
-                })
+    while let Some(kind) = tokens.peek().map(|token| token.kind.clone()) {
+        let checkpoint = tokens.checkpoint();
+
+        let node = match kind {
+            TokenKind::Keyword(Keyword::Let) => {
+                tokens.next()?;
+                AstNode::Let(Let::parse(tokens)?)
             }
-        }
+            TokenKind::Keyword(Keyword::Fn) => {
+                tokens.next()?;
+                AstNode::Function(Function::parse(tokens)?)
+            }
+            TokenKind::Keyword(Keyword::Struct) => {
+                tokens.next()?;
+                AstNode::Struct(Struct::parse(tokens)?)
+            }
+            TokenKind::Keyword(Keyword::Enum) => {
+                tokens.next()?;
+                AstNode::Enum(Enum::parse(tokens)?)
+            }
+            TokenKind::Hash => {
+                let attributes = Attribute::parse_all(tokens)?;
+
+                // Attributes can only be attached to bindings and functions
+                let token = tokens.next()?;
+                match token.kind {
+                    TokenKind::Keyword(Keyword::Let) => {
+                        let mut let_node = Let::parse(tokens)?;
+                        let_node.attributes = attributes;
+                        AstNode::Let(let_node)
+                    }
+                    TokenKind::Keyword(Keyword::Fn) => {
+                        let mut function = Function::parse(tokens)?;
+                        function.attributes = attributes;
+                        AstNode::Function(function)
+                    }
+                    token_kind => {
+                        return Err(ParserError::UnexpectedToken {
+                            token: token_kind,
+                            position: token.span.start,
+                        })
+                    }
+                }
+            }
+            _ => {
+                // Anything else must be an expression statement
+                let expression = Expression::parse(tokens)?;
+
+                // Block-like statements don't require a semicolon, but may still have one
+                if tokens.expect(TokenKind::Semi).is_err() && !expression.is_block_like() {
+                    tokens.expect(TokenKind::Semi)?;
+                }
+
+                AstNode::Expression(expression)
+            }
+        };
+
+        tokens.start_node_at(checkpoint, node.syntax_kind());
+        tokens.finish_node();
+        nodes.push(node);
     }
 
     Ok(nodes)
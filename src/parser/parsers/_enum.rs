@@ -0,0 +1,83 @@
+use crate::{
+    lexer::cursor::Span,
+    parser::error::ParserResult,
+    token::TokenKind,
+    token_stream::{TokenIterator, TokenStream},
+};
+
+use super::{parse_type_parameters, TypeAnnotation};
+
+/// An enum declaration, which may be generic over some type parameters. Each variant may carry a
+/// list of values. Eg `enum Option<T> { Some(T), None }`.
+#[derive(Debug)]
+pub struct Enum {
+    pub(crate) ident: String,
+    pub(crate) ident_span: Span,
+    /// The span of the whole declaration, from the ident through to the closing brace.
+    pub(crate) span: Span,
+    pub(crate) type_parameters: Vec<String>,
+    pub(crate) variants: Vec<(String, Vec<TypeAnnotation>)>,
+}
+impl PartialEq for Enum {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident
+            && self.type_parameters == other.type_parameters
+            && self.variants == other.variants
+    }
+}
+impl Eq for Enum {}
+impl Enum {
+    /// Parses an enum declaration, assuming that the `enum` keyword has already been consumed.
+    /// ```txt
+    /// enum -> ident ["<" ident {"," ident} ">"] "{" [V {"," V}] "}"
+    /// V -> ident ["(" A {"," A} ")"]
+    /// ```
+    pub fn parse<I>(tokens: &mut TokenStream<I>) -> ParserResult<Enum>
+    where
+        I: TokenIterator,
+    {
+        let (ident, ident_span) = tokens.expect_ident_spanned()?;
+
+        let type_parameters = if tokens.expect(TokenKind::LAngle).is_ok() {
+            parse_type_parameters(tokens)?
+        } else {
+            Vec::new()
+        };
+
+        tokens.expect(TokenKind::LCurly)?;
+
+        let mut variants = Vec::new();
+        while tokens.expect(TokenKind::RCurly).is_err() {
+            let variant = tokens.expect_ident()?;
+
+            let mut values = Vec::new();
+            if tokens.expect(TokenKind::LSmooth).is_ok() {
+                while tokens.expect(TokenKind::RSmooth).is_err() {
+                    values.push(TypeAnnotation::parse(tokens)?);
+
+                    if tokens.expect(TokenKind::Comma).is_err() {
+                        tokens.expect(TokenKind::RSmooth)?;
+                        break;
+                    }
+                }
+            }
+
+            variants.push((variant, values));
+
+            if tokens.expect(TokenKind::Comma).is_err() {
+                tokens.expect(TokenKind::RCurly)?;
+                break;
+            }
+        }
+
+        let span = Span::new(ident_span.start.clone(), tokens.end());
+
+        Ok(Enum {
+            ident,
+            ident_span,
+            type_parameters,
+            variants,
+            span,
+        })
+    }
+}
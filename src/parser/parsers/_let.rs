@@ -1,19 +1,31 @@
-use crate::{token::TokenKind, token_stream::TokenIterator};
+use crate::{lexer::cursor::Span, token::TokenKind, token_stream::TokenIterator};
 
 use super::{
     super::{
         error::{ParserError, ParserResult},
         TokenStream,
     },
-    Expression,
+    Attribute, Expression, TypeAnnotation,
 };
 
 #[allow(unused)]
 #[derive(Debug)]
 pub struct Let {
     pub(crate) ident: String,
+    pub(crate) ident_span: Span,
+    pub(crate) type_annotation: Option<TypeAnnotation>,
     pub(crate) rhs: Expression,
+    pub(crate) attributes: Vec<Attribute>,
 }
+impl PartialEq for Let {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident
+            && self.type_annotation == other.type_annotation
+            && self.rhs == other.rhs
+            && self.attributes == other.attributes
+    }
+}
+impl Eq for Let {}
 impl Let {
     pub fn parse<I>(tokens: &mut TokenStream<I>) -> ParserResult<Let>
     where
@@ -21,7 +33,16 @@ impl Let {
     {
         let token = tokens.next()?;
         let TokenKind::Identifier(ident) = token.kind else {
-            return Err(ParserError::ExpectedToken { token: TokenKind::Identifier(String::new()), position: token.position })
+            return Err(ParserError::ExpectedToken {
+                token: TokenKind::Identifier(String::new()),
+                position: token.span.start,
+            });
+        };
+
+        let type_annotation = if tokens.expect(TokenKind::Colon).is_ok() {
+            Some(TypeAnnotation::parse(tokens)?)
+        } else {
+            None
         };
 
         tokens.expect(TokenKind::Equals)?;
@@ -32,7 +53,10 @@ impl Let {
 
         Ok(Let {
             ident,
+            ident_span: token.span,
+            type_annotation,
             rhs: expression,
+            attributes: Vec::new(),
         })
     }
 }
@@ -0,0 +1,70 @@
+use crate::{
+    lexer::cursor::Span,
+    parser::error::ParserResult,
+    token::TokenKind,
+    token_stream::{TokenIterator, TokenStream},
+};
+
+use super::{parse_type_parameters, TypeAnnotation};
+
+/// A struct declaration, which may be generic over some type parameters. Eg
+/// `struct Pair<A, B> { first: A, second: B }`.
+#[derive(Debug)]
+pub struct Struct {
+    pub(crate) ident: String,
+    pub(crate) ident_span: Span,
+    /// The span of the whole declaration, from the ident through to the closing brace.
+    pub(crate) span: Span,
+    pub(crate) type_parameters: Vec<String>,
+    pub(crate) fields: Vec<(String, TypeAnnotation)>,
+}
+impl PartialEq for Struct {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident
+            && self.type_parameters == other.type_parameters
+            && self.fields == other.fields
+    }
+}
+impl Eq for Struct {}
+impl Struct {
+    /// Parses a struct declaration, assuming that the `struct` keyword has already been consumed.
+    /// ```txt
+    /// struct -> ident ["<" ident {"," ident} ">"] "{" [ident ":" A {"," ident ":" A}] "}"
+    /// ```
+    pub fn parse<I>(tokens: &mut TokenStream<I>) -> ParserResult<Struct>
+    where
+        I: TokenIterator,
+    {
+        let (ident, ident_span) = tokens.expect_ident_spanned()?;
+
+        let type_parameters = if tokens.expect(TokenKind::LAngle).is_ok() {
+            parse_type_parameters(tokens)?
+        } else {
+            Vec::new()
+        };
+
+        tokens.expect(TokenKind::LCurly)?;
+
+        let mut fields = Vec::new();
+        while tokens.expect(TokenKind::RCurly).is_err() {
+            let field = tokens.expect_ident()?;
+            tokens.expect(TokenKind::Colon)?;
+            fields.push((field, TypeAnnotation::parse(tokens)?));
+
+            if tokens.expect(TokenKind::Comma).is_err() {
+                tokens.expect(TokenKind::RCurly)?;
+                break;
+            }
+        }
+
+        let span = Span::new(ident_span.start.clone(), tokens.end());
+
+        Ok(Struct {
+            ident,
+            ident_span,
+            type_parameters,
+            fields,
+            span,
+        })
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    lexer::cursor::Span,
+    parser::error::ParserResult,
+    syntax::SyntaxKind,
+    token::TokenKind,
+    token_stream::{TokenIterator, TokenStream},
+};
+
+/// An attribute attached to the declaration that follows it, along with the idents that it was
+/// given. Eg `#[allow(unused_variable)]`.
+#[derive(Debug)]
+pub struct Attribute {
+    pub(crate) ident: String,
+    pub(crate) span: Span,
+    pub(crate) arguments: Vec<(String, Span)>,
+}
+impl PartialEq for Attribute {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident
+            && self.arguments.len() == other.arguments.len()
+            && self
+                .arguments
+                .iter()
+                .zip(&other.arguments)
+                .all(|((a, _), (b, _))| a == b)
+    }
+}
+impl Eq for Attribute {}
+impl Attribute {
+    /// Parses a single attribute, assuming that the `#` has already been consumed.
+    /// ```txt
+    /// attribute -> "#" "[" ident ["(" [ident {"," ident}] ")"] "]"
+    /// ```
+    pub fn parse<I>(tokens: &mut TokenStream<I>) -> ParserResult<Attribute>
+    where
+        I: TokenIterator,
+    {
+        tokens.expect(TokenKind::LSquare)?;
+        let (ident, span) = tokens.expect_ident_spanned()?;
+
+        let mut arguments = Vec::new();
+        if tokens.expect(TokenKind::LSmooth).is_ok() {
+            while tokens.expect(TokenKind::RSmooth).is_err() {
+                arguments.push(tokens.expect_ident_spanned()?);
+
+                if tokens.expect(TokenKind::Comma).is_err() {
+                    tokens.expect(TokenKind::RSmooth)?;
+                    break;
+                }
+            }
+        }
+
+        tokens.expect(TokenKind::RSquare)?;
+
+        Ok(Attribute {
+            ident,
+            span,
+            arguments,
+        })
+    }
+
+    /// Parses every attribute up to the start of the declaration that they're attached to.
+    pub fn parse_all<I>(tokens: &mut TokenStream<I>) -> ParserResult<Vec<Attribute>>
+    where
+        I: TokenIterator,
+    {
+        let mut attributes = Vec::new();
+        while tokens
+            .peek()
+            .is_some_and(|token| token.kind == TokenKind::Hash)
+        {
+            tokens.start_node(SyntaxKind::Attribute);
+            tokens.next()?;
+            attributes.push(Self::parse(tokens)?);
+            tokens.finish_node();
+        }
+
+        Ok(attributes)
+    }
+}
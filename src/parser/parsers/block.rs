@@ -0,0 +1,94 @@
+use crate::{
+    lexer::cursor::Span,
+    parser::{error::ParserResult, AstNode},
+    syntax::SyntaxKind,
+    token::{Keyword, TokenKind},
+    token_stream::{TokenIterator, TokenStream},
+};
+
+use super::{Attribute, Expression, Let};
+
+/// A sequence of statements surrounded by curly braces, optionally ending with an expression that
+/// the block evaluates to.
+#[derive(Debug)]
+pub struct Block {
+    pub(crate) statements: Vec<AstNode>,
+    pub(crate) expression: Option<Box<Expression>>,
+    /// The span from the opening brace through to the closing brace.
+    pub(crate) span: Span,
+}
+impl PartialEq for Block {
+    fn eq(&self, other: &Self) -> bool {
+        self.statements == other.statements && self.expression == other.expression
+    }
+}
+impl Eq for Block {}
+impl Block {
+    /// Parses a block, including the surrounding curly braces.
+    /// ```txt
+    /// B -> "{" {({attribute} "let" L | E ";")} [E] "}"
+    /// ```
+    ///
+    /// An `if` can be used as a statement without a following semicolon.
+    pub fn parse<I>(tokens: &mut TokenStream<I>) -> ParserResult<Block>
+    where
+        I: TokenIterator,
+    {
+        tokens.start_node(SyntaxKind::Block);
+        let start = tokens.expect(TokenKind::LCurly)?.span.start;
+
+        // The braces remove any ambiguity, even within the condition of an `if`
+        tokens.with_struct_literals(true, |tokens| {
+            let mut statements = Vec::new();
+            let mut expression = None;
+
+            while tokens.expect(TokenKind::RCurly).is_err() {
+                let checkpoint = tokens.checkpoint();
+
+                let attributes = Attribute::parse_all(tokens)?;
+                if !attributes.is_empty()
+                    || tokens
+                        .peek()
+                        .is_some_and(|token| token.kind == TokenKind::Keyword(Keyword::Let))
+                {
+                    // Attributes can only be attached to bindings within a block
+                    tokens.expect(TokenKind::Keyword(Keyword::Let))?;
+
+                    let mut let_node = Let::parse(tokens)?;
+                    let_node.attributes = attributes;
+                    statements.push(AstNode::Let(let_node));
+
+                    tokens.start_node_at(checkpoint, SyntaxKind::Let);
+                    tokens.finish_node();
+                    continue;
+                }
+
+                let statement = Expression::parse(tokens)?;
+
+                if tokens.expect(TokenKind::Semi).is_ok()
+                    || (statement.is_block_like()
+                        && tokens
+                            .peek()
+                            .is_some_and(|token| token.kind != TokenKind::RCurly))
+                {
+                    statements.push(AstNode::Expression(statement));
+
+                    tokens.start_node_at(checkpoint, SyntaxKind::ExpressionStatement);
+                    tokens.finish_node();
+                } else {
+                    // An expression without a semicolon must be the final one in the block
+                    tokens.expect(TokenKind::RCurly)?;
+                    expression = Some(Box::new(statement));
+                    break;
+                }
+            }
+            tokens.finish_node();
+
+            Ok(Block {
+                statements,
+                expression,
+                span: Span::new(start, tokens.end()),
+            })
+        })
+    }
+}
@@ -1,11 +1,15 @@
 use crate::{
+    lexer::cursor::Span,
     parser::error::{ParserError, ParserResult},
-    token::{Literal, TokenKind},
+    syntax::SyntaxKind,
+    token::{Keyword, Literal, TokenKind},
     token_stream::{TokenIterator, TokenStream},
 };
 
+use super::{Block, TypeAnnotation};
+
 /// Each of the binary operations that can take place within an expression.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinaryOperationKind {
     /// Addition
     Add,
@@ -13,22 +17,260 @@ pub enum BinaryOperationKind {
     Sub,
     /// Multiplication
     Mult,
-    /// Division
+    /// Division, rounding towards zero
     Div,
+    /// Remainder of division, with the same sign as the lhs
+    Mod,
     /// Exponent
     Exp,
+    /// Bitwise and
+    And,
+    /// Bitwise or
+    Or,
+    /// Bitwise exclusive or, written `~` as `^` is taken by exponents
+    Xor,
+    /// Shift left
+    Shl,
+    /// Arithmetic shift right
+    Shr,
+}
+
+/// Which way a chain of operations with the same precedence groups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`.
+    Right,
+}
+
+/// How a binary operator is written, and how it binds to its operands.
+pub struct Operator {
+    pub token: TokenKind,
+    pub operation: BinaryOperationKind,
+    /// How tightly the operator binds to its operands. An operator with a higher precedence is
+    /// evaluated first.
+    pub precedence: u8,
+    pub associativity: Associativity,
+}
+
+/// Every binary operator. The expression parser is driven by this table, so adding an operator
+/// only requires a new entry here. The bitwise operators bind less tightly than the arithmetic
+/// ones, so `a & b + 1` is `a & (b + 1)`.
+pub static OPERATORS: [Operator; 11] = [
+    Operator {
+        token: TokenKind::Pipe,
+        operation: BinaryOperationKind::Or,
+        precedence: 1,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::Tilde,
+        operation: BinaryOperationKind::Xor,
+        precedence: 2,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::Ampersand,
+        operation: BinaryOperationKind::And,
+        precedence: 3,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::DoubleLAngle,
+        operation: BinaryOperationKind::Shl,
+        precedence: 4,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::DoubleRAngle,
+        operation: BinaryOperationKind::Shr,
+        precedence: 4,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::Plus,
+        operation: BinaryOperationKind::Add,
+        precedence: 5,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::Minus,
+        operation: BinaryOperationKind::Sub,
+        precedence: 5,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::Asterix,
+        operation: BinaryOperationKind::Mult,
+        precedence: 6,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::Slash,
+        operation: BinaryOperationKind::Div,
+        precedence: 6,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::Percent,
+        operation: BinaryOperationKind::Mod,
+        precedence: 6,
+        associativity: Associativity::Left,
+    },
+    Operator {
+        token: TokenKind::Hat,
+        operation: BinaryOperationKind::Exp,
+        precedence: 7,
+        associativity: Associativity::Right,
+    },
+];
+
+impl BinaryOperationKind {
+    /// The operator written as the token, if there is one.
+    pub fn from_token(token: &TokenKind) -> Option<Self> {
+        OPERATORS
+            .iter()
+            .find(|operator| operator.token == *token)
+            .map(|operator| operator.operation)
+    }
+
+    fn operator(self) -> &'static Operator {
+        OPERATORS
+            .iter()
+            .find(|operator| operator.operation == self)
+            .expect("every operation to be in the operator table")
+    }
+
+    /// How tightly the operation binds to its operands. An operation with a higher precedence is
+    /// evaluated first.
+    pub fn precedence(self) -> u8 {
+        self.operator().precedence
+    }
+
+    /// Whether a chain of this operation groups from the right, such as `a ^ b ^ c` being
+    /// `a ^ (b ^ c)`.
+    pub fn is_right_associative(self) -> bool {
+        self.operator().associativity == Associativity::Right
+    }
+
+    /// Whether the operands of the operation have to be integers. Only addition can be applied
+    /// to another type, concatenating strings.
+    pub fn is_integer_only(self) -> bool {
+        self != BinaryOperationKind::Add
+    }
 }
 
 /// Each of the unary operations that can take place within an expression.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOperationKind {
     /// Negation (eg `-8`)
     Negative,
+    /// Bitwise complement (eg `~8`)
+    Complement,
 }
 
+impl UnaryOperationKind {
+    /// The operation written as the token, if there is one.
+    pub fn from_token(token: &TokenKind) -> Option<Self> {
+        match token {
+            TokenKind::Minus => Some(UnaryOperationKind::Negative),
+            TokenKind::Tilde => Some(UnaryOperationKind::Complement),
+            _ => None,
+        }
+    }
+
+    /// The precedence of the binary operations that the operand extends over. Unary operations
+    /// bind more tightly than `*` and `/` but less tightly than `^`, so `-a * b` is `(-a) * b` but
+    /// `-a ^ b` is `-(a ^ b)`.
+    pub fn precedence(self) -> u8 {
+        BinaryOperationKind::Exp.precedence()
+    }
+}
+
+/// Where an expression appears within its parent, which determines whether it has to be surrounded
+/// by parentheses to be parsed in the same way.
+#[derive(Debug, Clone, Copy)]
+pub enum Placement {
+    /// Anywhere that a full expression can appear, such as the rhs of a let.
+    Free,
+    /// The operand of a unary operation.
+    Operand(UnaryOperationKind),
+    /// The left hand side of a binary operation.
+    Lhs(BinaryOperationKind),
+    /// The right hand side of a binary operation.
+    Rhs(BinaryOperationKind),
+}
+
+/// An expression, along with the span of source that it was parsed from.
+#[derive(Debug)]
+pub struct Expression {
+    pub(crate) kind: ExpressionKind,
+    pub(crate) span: Span,
+    /// The number of pairs of parentheses that the expression was surrounded by. These don't
+    /// affect the meaning of the expression, so aren't compared.
+    pub(crate) parentheses: usize,
+}
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            parentheses: 0,
+        }
+    }
+
+    /// Whether the expression has to be surrounded by parentheses at `placement` to be parsed in
+    /// the same way, following the precedence of the grammar below. This doesn't consider struct
+    /// literals within the condition of an `if`, which also have to be surrounded by parentheses.
+    pub fn requires_parentheses(&self, placement: Placement) -> bool {
+        match (placement, &self.kind) {
+            (Placement::Free, _) => false,
+            // The operand of a unary operation extends over operations that bind tightly enough
+            (Placement::Operand(parent), ExpressionKind::BinaryOperation { operation, .. }) => {
+                operation.precedence() < parent.precedence()
+            }
+            (Placement::Lhs(parent), ExpressionKind::BinaryOperation { operation, .. }) => {
+                operation.precedence() < parent.precedence()
+                    || (operation.precedence() == parent.precedence()
+                        && parent.is_right_associative())
+            }
+            (Placement::Rhs(parent), ExpressionKind::BinaryOperation { operation, .. }) => {
+                operation.precedence() < parent.precedence()
+                    || (operation.precedence() == parent.precedence()
+                        && !parent.is_right_associative())
+            }
+            // Without parentheses, a unary operation would extend over the operations after it
+            (Placement::Lhs(parent), ExpressionKind::UnaryOperation { operation, .. }) => {
+                parent.precedence() >= operation.precedence()
+            }
+            // Only the operations after the parent can follow its rhs, and those bind less tightly
+            // unless the parent groups from the left
+            (Placement::Rhs(parent), ExpressionKind::UnaryOperation { operation, .. }) => {
+                parent.precedence() > operation.precedence()
+                    || (parent.precedence() == operation.precedence()
+                        && !parent.is_right_associative())
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the expression ends with a block, allowing it to be used as a statement without a
+    /// following semicolon.
+    pub fn is_block_like(&self) -> bool {
+        matches!(self.kind, ExpressionKind::If { .. })
+    }
+}
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+impl Eq for Expression {}
+
 /// Each of the possible expression types.
 #[derive(Debug, PartialEq, Eq)]
-pub enum Expression {
+pub enum ExpressionKind {
     /// A variable. Eg `a`.
     Ident(String),
     /// A binary operation. Eg `a + 8`.
@@ -44,6 +286,58 @@ pub enum Expression {
     },
     /// A literal.
     Literal(Literal),
+    /// A function call, optionally with explicit type arguments. Eg `id::<Integer>(5)`.
+    Call {
+        ident: String,
+        type_arguments: Vec<TypeAnnotation>,
+        arguments: Vec<Expression>,
+    },
+    /// Construction of an enum variant. Eg `Option::Some(5)` or `Option::<Integer>::None`.
+    Variant {
+        ident: String,
+        type_arguments: Vec<TypeAnnotation>,
+        variant: String,
+        arguments: Vec<Expression>,
+    },
+    /// Construction of a struct. Eg `Pair { first: 1, second: true }`.
+    Struct {
+        ident: String,
+        type_arguments: Vec<TypeAnnotation>,
+        fields: Vec<(String, Expression)>,
+    },
+    /// A string with embedded expressions. Eg `"hello {name}, you are {age + 1}"`.
+    Interpolation(Vec<InterpolationPart>),
+    /// A conditional. Eg `if a { 1 } else { 2 }`. An `else if` is represented as an `otherwise`
+    /// block containing only the nested conditional.
+    If {
+        condition: Box<Expression>,
+        then: Block,
+        otherwise: Option<Block>,
+    },
+}
+
+/// A piece of an interpolated string, which is either text or an embedded expression.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InterpolationPart {
+    Text(String),
+    Expression(Expression),
+}
+
+impl ExpressionKind {
+    /// The kind of node that the expression appears as within a syntax tree.
+    pub fn syntax_kind(&self) -> SyntaxKind {
+        match self {
+            ExpressionKind::Ident(_) => SyntaxKind::Ident,
+            ExpressionKind::BinaryOperation { .. } => SyntaxKind::BinaryOperation,
+            ExpressionKind::UnaryOperation { .. } => SyntaxKind::UnaryOperation,
+            ExpressionKind::Literal(_) => SyntaxKind::Literal,
+            ExpressionKind::Call { .. } => SyntaxKind::Call,
+            ExpressionKind::Variant { .. } => SyntaxKind::Variant,
+            ExpressionKind::Struct { .. } => SyntaxKind::StructLiteral,
+            ExpressionKind::Interpolation(_) => SyntaxKind::Interpolation,
+            ExpressionKind::If { .. } => SyntaxKind::If,
+        }
+    }
 }
 
 /// The following grammar is used to parse expressions. Expressions can be terminated by a number,
@@ -51,122 +345,332 @@ pub enum Expression {
 ///
 /// ```txt
 /// S -> E end
-/// E -> T {("+" | "-") T}
-/// T -> F {("*" | "/") F}
-/// F -> P ["^" F]
-/// P -> v | "(" E ")" | "-" T
-/// v -> [0-9]+ | function | variable
+/// E -> X {"|" X}
+/// X -> N {"~" N}
+/// N -> H {"&" H}
+/// H -> A {("<<" | ">>") A}
+/// A -> T {("+" | "-") T}
+/// T -> U {("*" | "/" | "%") U}
+/// U -> ("-" | "~") U | F
+/// F -> P ["^" U]
+/// P -> v | "(" E ")" | I | J
+/// I -> "if" E B ["else" (B | I)]
+/// J -> string_start E {string_middle E} string_end
+/// v -> [0-9]+ | function | variable | variant | struct
 /// ```
+///
+/// Unary operations bind more tightly than `*` and `/`, but less tightly than `^`, so `-2 ^ 2` is
+/// `-4` and `-2 * 3 + 1` is `((-2) * 3) + 1`. The exponent can itself be negated, as in `2 ^ -1`.
+/// The bitwise operations bind less tightly than arithmetic, so `a & b + 1` is `a & (b + 1)`, and
+/// `~` is a complement before an operand but an exclusive or between two.
+///
+/// Rather than having a function for each level of precedence, `E` through to `F` are parsed
+/// by precedence climbing, using the precedence and associativity of each operator in
+/// [OPERATORS] and of each unary operator.
+///
+/// The condition of an `if` can't contain a struct literal outside of parentheses, as the `{`
+/// would be ambiguous with the start of the body. The expressions embedded in an interpolated
+/// string are already delimited by the lexer, so they can.
 impl Expression {
-    /// Parse the `E` term from the grammar
-    /// ```txt
-    /// E -> T {("+" | "-") T}
-    /// ```
+    /// Parse the `E` term from the grammar, which is a chain of binary operations.
     pub fn parse_expression<I>(tokens: &mut TokenStream<I>) -> ParserResult<Expression>
     where
         I: TokenIterator,
     {
-        let mut expr = Self::parse_term(tokens)?;
+        Self::parse_operations(tokens, 0)
+    }
 
-        loop {
-            let Some(operation) = tokens.peek().and_then(|t| match t.kind {
-                TokenKind::Plus => Some(BinaryOperationKind::Add),
-                TokenKind::Minus => Some(BinaryOperationKind::Sub),
-                _ => None,
-            }) else { break };
+    /// Parses a chain of binary operations, only including those with at least the given
+    /// precedence. The operands of an operation are parsed by recursing with a higher precedence,
+    /// so that they only contain operations that bind more tightly.
+    fn parse_operations<I>(tokens: &mut TokenStream<I>, precedence: u8) -> ParserResult<Expression>
+    where
+        I: TokenIterator,
+    {
+        let checkpoint = tokens.checkpoint();
+        let mut expr = Self::parse_primary(tokens)?;
 
+        while let Some(operation) = tokens
+            .peek()
+            .and_then(|t| BinaryOperationKind::from_token(&t.kind))
+            .filter(|operation| operation.precedence() >= precedence)
+        {
             // Consume peeked token
+            tokens.start_node_at(checkpoint, SyntaxKind::BinaryOperation);
             tokens.next()?;
 
-            expr = Expression::BinaryOperation {
-                operation,
-                lhs: Box::new(expr),
-                rhs: Box::new(Self::parse_term(tokens)?),
+            // A right associative operation can have another of the same precedence on its rhs
+            let rhs_precedence = if operation.is_right_associative() {
+                operation.precedence()
+            } else {
+                operation.precedence() + 1
             };
+            let rhs = Self::parse_operations(tokens, rhs_precedence)?;
+            tokens.finish_node();
+            let span = expr.span.to(&rhs.span);
+
+            expr = Expression::new(
+                ExpressionKind::BinaryOperation {
+                    operation,
+                    lhs: Box::new(expr),
+                    rhs: Box::new(rhs),
+                },
+                span,
+            );
         }
 
         Ok(expr)
     }
 
-    /// Parse the `T` term from the grammar
+    /// Parse the `P` term from the grammar, along with the unary operations that can begin an
+    /// operand.
     /// ```txt
-    /// T -> F {("*" | "/") F}
+    /// U -> ("-" | "~") U | F
+    /// P -> v | "(" E ")" | I | J
     /// ```
-    pub fn parse_term<I>(tokens: &mut TokenStream<I>) -> ParserResult<Expression>
+    pub fn parse_primary<I>(tokens: &mut TokenStream<I>) -> ParserResult<Expression>
     where
         I: TokenIterator,
     {
-        let mut expr = Self::parse_factor(tokens)?;
+        let checkpoint = tokens.checkpoint();
+        let token = tokens.next()?;
+        let start = token.span.start.clone();
 
-        loop {
-            let Some(operation) = tokens.peek().and_then(|t| match t.kind {
-                TokenKind::Asterix => Some(BinaryOperationKind::Mult),
-                TokenKind::Slash => Some(BinaryOperationKind::Div),
-                _ => None,
-            }) else { break };
+        let expression = match token.kind {
+            TokenKind::Literal(literal) => Ok(Expression::new(
+                ExpressionKind::Literal(literal),
+                token.span,
+            )),
+            TokenKind::Identifier(ident) => {
+                let kind = Self::parse_path(ident, tokens)?;
 
-            // Consume peeked token
-            tokens.next()?;
+                Ok(Expression::new(kind, Span::new(start, tokens.end())))
+            }
+            TokenKind::LSmooth => {
+                let mut expression =
+                    tokens.with_struct_literals(true, |tokens| Self::parse_expression(tokens))?;
 
-            expr = Expression::BinaryOperation {
-                operation,
-                lhs: Box::new(expr),
-                rhs: Box::new(Self::parse_factor(tokens)?),
-            };
+                tokens.expect(TokenKind::RSmooth)?;
+                expression.parentheses += 1;
+
+                Ok(expression)
+            }
+            kind @ (TokenKind::Minus | TokenKind::Tilde) => {
+                let operation = UnaryOperationKind::from_token(&kind)
+                    .expect("minus and tilde to be unary operators");
+                let rhs = Self::parse_operations(tokens, operation.precedence())?;
+                let end = rhs.span.end.clone();
+
+                Ok(Expression::new(
+                    ExpressionKind::UnaryOperation {
+                        operation,
+                        rhs: Box::new(rhs),
+                    },
+                    Span::new(start, end),
+                ))
+            }
+            TokenKind::Keyword(Keyword::If) => {
+                let kind = Self::parse_if(tokens)?;
+
+                Ok(Expression::new(kind, Span::new(start, tokens.end())))
+            }
+            TokenKind::StringStart(text) => {
+                let kind = Self::parse_interpolation(text, tokens)?;
+
+                Ok(Expression::new(kind, Span::new(start, tokens.end())))
+            }
+            t => Err(ParserError::UnexpectedToken {
+                token: t,
+                position: start,
+            }),
+        }?;
+
+        let kind = if expression.parentheses > 0 {
+            SyntaxKind::Parentheses
+        } else {
+            expression.kind.syntax_kind()
+        };
+        tokens.start_node_at(checkpoint, kind);
+        tokens.finish_node();
+
+        Ok(expression)
+    }
+
+    /// Parse the `J` term from the grammar, after the start of the string has been consumed.
+    /// ```txt
+    /// J -> string_start E {string_middle E} string_end
+    /// ```
+    fn parse_interpolation<I>(
+        text: String,
+        tokens: &mut TokenStream<I>,
+    ) -> ParserResult<ExpressionKind>
+    where
+        I: TokenIterator,
+    {
+        let mut parts = vec![InterpolationPart::Text(text)];
+
+        loop {
+            let expression =
+                tokens.with_struct_literals(true, |tokens| Self::parse_expression(tokens))?;
+            parts.push(InterpolationPart::Expression(expression));
+
+            let token = tokens.next()?;
+            match token.kind {
+                TokenKind::StringMiddle(text) => parts.push(InterpolationPart::Text(text)),
+                TokenKind::StringEnd(text) => {
+                    parts.push(InterpolationPart::Text(text));
+                    break;
+                }
+                t => {
+                    return Err(ParserError::UnexpectedToken {
+                        token: t,
+                        position: token.span.start,
+                    })
+                }
+            }
         }
 
-        Ok(expr)
+        // Empty text between expressions doesn't need keeping
+        parts.retain(|part| !matches!(part, InterpolationPart::Text(text) if text.is_empty()));
+
+        Ok(ExpressionKind::Interpolation(parts))
     }
 
-    /// Parse the `F` term from the grammar
+    /// Parse the `I` term from the grammar, after the `if` keyword has been consumed.
     /// ```txt
-    /// F -> P ["^" F]
+    /// I -> "if" E B ["else" (B | I)]
     /// ```
-    pub fn parse_factor<I>(tokens: &mut TokenStream<I>) -> ParserResult<Expression>
+    fn parse_if<I>(tokens: &mut TokenStream<I>) -> ParserResult<ExpressionKind>
     where
         I: TokenIterator,
     {
-        let p = Self::parse_primary(tokens)?;
+        let condition =
+            tokens.with_struct_literals(false, |tokens| Self::parse_expression(tokens))?;
+        let then = Block::parse(tokens)?;
+
+        let otherwise = if tokens.expect(TokenKind::Keyword(Keyword::Else)).is_ok() {
+            match tokens.peek() {
+                Some(token) if token.kind == TokenKind::Keyword(Keyword::If) => {
+                    tokens.start_node(SyntaxKind::If);
+                    let start = tokens.next()?.span.start;
+                    let kind = Self::parse_if(tokens)?;
+                    tokens.finish_node();
 
-        Ok(if tokens.expect(TokenKind::Hat).is_ok() {
-            Expression::BinaryOperation {
-                operation: BinaryOperationKind::Exp,
-                lhs: Box::new(p),
-                rhs: Box::new(Self::parse_factor(tokens)?),
+                    let span = Span::new(start, tokens.end());
+
+                    Some(Block {
+                        statements: Vec::new(),
+                        expression: Some(Box::new(Expression::new(kind, span.clone()))),
+                        span,
+                    })
+                }
+                _ => Some(Block::parse(tokens)?),
             }
         } else {
-            p
+            None
+        };
+
+        Ok(ExpressionKind::If {
+            condition: Box::new(condition),
+            then,
+            otherwise,
         })
     }
 
-    /// Parse the `P` term from the grammar
+    /// Parse the `v` term from the grammar that begins with an identifier, which has already been
+    /// consumed. Depending on what follows, this may be a variable, a function call, an enum
+    /// variant or a struct.
     /// ```txt
-    /// P -> v | "(" E ")" | "-" T
+    /// v -> ident ["::" "<" A {"," A} ">"] ["::" ident] ["(" [E {"," E}] ")" | "{" [ident ":" E {"," ident ":" E}] "}"]
     /// ```
-    pub fn parse_primary<I>(tokens: &mut TokenStream<I>) -> ParserResult<Expression>
+    fn parse_path<I>(ident: String, tokens: &mut TokenStream<I>) -> ParserResult<ExpressionKind>
     where
         I: TokenIterator,
     {
-        let token = tokens.next()?;
-        match token.kind {
-            TokenKind::Literal(literal) => Ok(Expression::Literal(literal)),
-            TokenKind::Identifier(ident) => Ok(Expression::Ident(ident)),
-            TokenKind::LSmooth => {
-                let expression = Self::parse_expression(tokens)?;
+        let mut type_arguments = Vec::new();
+        let mut variant = None;
 
-                tokens.expect(TokenKind::RSmooth)?;
+        if tokens.expect(TokenKind::DoubleColon).is_ok() {
+            if tokens.expect(TokenKind::LAngle).is_ok() {
+                type_arguments = TypeAnnotation::parse_arguments(tokens)?;
 
-                Ok(expression)
+                if tokens.expect(TokenKind::DoubleColon).is_ok() {
+                    variant = Some(tokens.expect_ident()?);
+                }
+            } else {
+                variant = Some(tokens.expect_ident()?);
             }
-            TokenKind::Minus => Ok(Expression::UnaryOperation {
-                operation: UnaryOperationKind::Negative,
-                rhs: Box::new(Self::parse_term(tokens)?),
-            }),
-            t => Err(ParserError::UnexpectedToken {
-                token: t,
-                position: token.position,
-            }),
         }
+
+        if let Some(variant) = variant {
+            let arguments = if tokens.expect(TokenKind::LSmooth).is_ok() {
+                Self::parse_arguments(tokens)?
+            } else {
+                Vec::new()
+            };
+
+            return Ok(ExpressionKind::Variant {
+                ident,
+                type_arguments,
+                variant,
+                arguments,
+            });
+        }
+
+        if tokens.struct_literals() && tokens.expect(TokenKind::LCurly).is_ok() {
+            let mut fields = Vec::new();
+            while tokens.expect(TokenKind::RCurly).is_err() {
+                let field = tokens.expect_ident()?;
+                tokens.expect(TokenKind::Colon)?;
+                fields.push((field, Self::parse_expression(tokens)?));
+
+                if tokens.expect(TokenKind::Comma).is_err() {
+                    tokens.expect(TokenKind::RCurly)?;
+                    break;
+                }
+            }
+
+            return Ok(ExpressionKind::Struct {
+                ident,
+                type_arguments,
+                fields,
+            });
+        }
+
+        if !type_arguments.is_empty() {
+            // Explicit type arguments without a variant can only be provided to a function call
+            tokens.expect(TokenKind::LSmooth)?;
+        } else if tokens.expect(TokenKind::LSmooth).is_err() {
+            return Ok(ExpressionKind::Ident(ident));
+        }
+
+        Ok(ExpressionKind::Call {
+            ident,
+            type_arguments,
+            arguments: Self::parse_arguments(tokens)?,
+        })
+    }
+
+    /// Parses a comma separated list of expressions, up to and including the closing `)`. The
+    /// opening `(` must already have been consumed.
+    fn parse_arguments<I>(tokens: &mut TokenStream<I>) -> ParserResult<Vec<Expression>>
+    where
+        I: TokenIterator,
+    {
+        tokens.with_struct_literals(true, |tokens| {
+            let mut arguments = Vec::new();
+
+            while tokens.expect(TokenKind::RSmooth).is_err() {
+                arguments.push(Self::parse_expression(tokens)?);
+
+                if tokens.expect(TokenKind::Comma).is_err() {
+                    tokens.expect(TokenKind::RSmooth)?;
+                    break;
+                }
+            }
+
+            Ok(arguments)
+        })
     }
 
     /// Parses tokens into an expression (identical to [Self::parse_expression] call).
@@ -181,7 +685,12 @@ impl Expression {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{lexer::cursor::Position, token::Token};
+    use crate::{lexer::Lexer, token::Token};
+
+    /// Creates an expression with an empty span, as spans aren't compared.
+    fn expression(kind: ExpressionKind) -> Expression {
+        Expression::new(kind, Span::default())
+    }
 
     #[test]
     fn number_expressions() {
@@ -189,12 +698,12 @@ mod tests {
             Expression::parse(&mut TokenStream::from(
                 [Token {
                     kind: TokenKind::Literal(Literal::Integer(90)),
-                    position: Position::new(),
+                    span: Span::default(),
                 }]
                 .into_iter()
             ))
             .unwrap(),
-            Expression::Literal(Literal::Integer(90))
+            expression(ExpressionKind::Literal(Literal::Integer(90)))
         );
     }
 
@@ -205,20 +714,20 @@ mod tests {
                 [
                     Token {
                         kind: TokenKind::Minus,
-                        position: Position::new(),
+                        span: Span::default(),
                     },
                     Token {
                         kind: TokenKind::Literal(Literal::Integer(90)),
-                        position: Position::new(),
+                        span: Span::default(),
                     }
                 ]
                 .into_iter()
             ))
             .unwrap(),
-            Expression::UnaryOperation {
+            expression(ExpressionKind::UnaryOperation {
                 operation: UnaryOperationKind::Negative,
-                rhs: Box::new(Expression::Literal(Literal::Integer(90)))
-            }
+                rhs: Box::new(expression(ExpressionKind::Literal(Literal::Integer(90))))
+            })
         );
 
         assert_eq!(
@@ -226,27 +735,167 @@ mod tests {
                 [
                     Token {
                         kind: TokenKind::Minus,
-                        position: Position::new(),
+                        span: Span::default(),
                     },
                     Token {
                         kind: TokenKind::Minus,
-                        position: Position::new(),
+                        span: Span::default(),
                     },
                     Token {
                         kind: TokenKind::Literal(Literal::Integer(90)),
-                        position: Position::new(),
+                        span: Span::default(),
                     }
                 ]
                 .into_iter()
             ))
             .unwrap(),
-            Expression::UnaryOperation {
+            expression(ExpressionKind::UnaryOperation {
                 operation: UnaryOperationKind::Negative,
-                rhs: Box::new(Expression::UnaryOperation {
+                rhs: Box::new(expression(ExpressionKind::UnaryOperation {
                     operation: UnaryOperationKind::Negative,
-                    rhs: Box::new(Expression::Literal(Literal::Integer(90)))
+                    rhs: Box::new(expression(ExpressionKind::Literal(Literal::Integer(90))))
+                }))
+            })
+        );
+    }
+
+    /// Lexes and parses an expression.
+    fn parse(source: &str) -> Expression {
+        let tokens = Lexer::new(source)
+            .map(|token| token.unwrap())
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace));
+
+        Expression::parse(&mut TokenStream::from(tokens)).unwrap()
+    }
+
+    #[test]
+    fn precedence() {
+        // Each operator with its expected precedence, and whether it is right associative
+        let operators = [
+            ("|", BinaryOperationKind::Or, 1, false),
+            ("~", BinaryOperationKind::Xor, 2, false),
+            ("&", BinaryOperationKind::And, 3, false),
+            ("<<", BinaryOperationKind::Shl, 4, false),
+            (">>", BinaryOperationKind::Shr, 4, false),
+            ("+", BinaryOperationKind::Add, 5, false),
+            ("-", BinaryOperationKind::Sub, 5, false),
+            ("*", BinaryOperationKind::Mult, 6, false),
+            ("/", BinaryOperationKind::Div, 6, false),
+            ("%", BinaryOperationKind::Mod, 6, false),
+            ("^", BinaryOperationKind::Exp, 7, true),
+        ];
+
+        // Parentheses aren't compared, so these check how the operations are grouped
+        for (first, operation, precedence, right) in operators {
+            assert_eq!(
+                parse(&format!("a {first} b")),
+                expression(ExpressionKind::BinaryOperation {
+                    operation,
+                    lhs: Box::new(expression(ExpressionKind::Ident("a".to_string()))),
+                    rhs: Box::new(expression(ExpressionKind::Ident("b".to_string()))),
                 })
+            );
+
+            for (second, _, second_precedence, _) in operators {
+                let source = format!("a {first} b {second} c");
+                let grouped = if precedence > second_precedence
+                    || (precedence == second_precedence && !right)
+                {
+                    format!("(a {first} b) {second} c")
+                } else {
+                    format!("a {first} (b {second} c)")
+                };
+
+                assert_eq!(parse(&source), parse(&grouped), "{source}");
             }
+
+            // Negation only extends over `^`, and can always begin an operand
+            let negated = if precedence >= 7 {
+                format!("-(a {first} b)")
+            } else {
+                format!("(-a) {first} b")
+            };
+            assert_eq!(
+                parse(&format!("-a {first} b")),
+                parse(&negated),
+                "-a {first} b"
+            );
+            assert_eq!(
+                parse(&format!("a {first} -b")),
+                parse(&format!("a {first} (-b)")),
+                "a {first} -b"
+            );
+        }
+
+        assert_eq!(parse("-2 * 3 + 1"), parse("((-2) * 3) + 1"));
+        assert_eq!(parse("-2 ^ 2"), parse("-(2 ^ 2)"));
+        assert_eq!(parse("2 ^ -1 ^ 2 * 3"), parse("(2 ^ (-(1 ^ 2))) * 3"));
+        assert_eq!(parse("--a ^ b"), parse("-(-(a ^ b))"));
+        assert_ne!(parse("a - b - c"), parse("a - (b - c)"));
+        assert_eq!(parse("~a ~ ~b ^ c"), parse("(~a) ~ (~(b ^ c))"));
+        assert_eq!(parse("a << b >> c"), parse("(a << b) >> c"));
+    }
+
+    #[test]
+    fn if_expression() {
+        let tokens = Lexer::new("if a { b } else if c { d {} } else { 1 }")
+            .map(|token| token.unwrap())
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace));
+
+        let ident = |ident: &str| Box::new(expression(ExpressionKind::Ident(ident.to_string())));
+        let block = |expression| Block {
+            statements: Vec::new(),
+            expression: Some(expression),
+            span: Span::default(),
+        };
+
+        // Struct literals aren't allowed in the condition, so `d {}` is a struct within the body
+        assert_eq!(
+            Expression::parse(&mut TokenStream::from(tokens)).unwrap(),
+            expression(ExpressionKind::If {
+                condition: ident("a"),
+                then: block(ident("b")),
+                otherwise: Some(block(Box::new(expression(ExpressionKind::If {
+                    condition: ident("c"),
+                    then: block(Box::new(expression(ExpressionKind::Struct {
+                        ident: "d".to_string(),
+                        type_arguments: Vec::new(),
+                        fields: Vec::new(),
+                    }))),
+                    otherwise: Some(block(Box::new(expression(ExpressionKind::Literal(
+                        Literal::Integer(1)
+                    ))))),
+                })))),
+            })
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        let tokens = Lexer::new(r#""hello {name}, you are {age + 1}{Pair {}}""#)
+            .map(|token| token.unwrap())
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace));
+
+        let ident = |ident: &str| expression(ExpressionKind::Ident(ident.to_string()));
+
+        // Struct literals are allowed within the braces, and empty text between them is dropped
+        assert_eq!(
+            Expression::parse(&mut TokenStream::from(tokens)).unwrap(),
+            expression(ExpressionKind::Interpolation(vec![
+                InterpolationPart::Text("hello ".to_string()),
+                InterpolationPart::Expression(ident("name")),
+                InterpolationPart::Text(", you are ".to_string()),
+                InterpolationPart::Expression(expression(ExpressionKind::BinaryOperation {
+                    operation: BinaryOperationKind::Add,
+                    lhs: Box::new(ident("age")),
+                    rhs: Box::new(expression(ExpressionKind::Literal(Literal::Integer(1)))),
+                })),
+                InterpolationPart::Expression(expression(ExpressionKind::Struct {
+                    ident: "Pair".to_string(),
+                    type_arguments: Vec::new(),
+                    fields: Vec::new(),
+                })),
+            ]))
         );
     }
 }
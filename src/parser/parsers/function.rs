@@ -0,0 +1,82 @@
+use crate::{
+    lexer::cursor::Span,
+    parser::error::ParserResult,
+    token::TokenKind,
+    token_stream::{TokenIterator, TokenStream},
+};
+
+use super::{parse_type_parameters, Attribute, Block, TypeAnnotation};
+
+/// A function declaration, which may be generic over some type parameters. Eg
+/// `fn id<T>(x: T) -> T { x }`.
+#[derive(Debug)]
+pub struct Function {
+    pub(crate) ident: String,
+    pub(crate) ident_span: Span,
+    pub(crate) type_parameters: Vec<String>,
+    pub(crate) parameters: Vec<(String, TypeAnnotation)>,
+    pub(crate) return_type: Option<TypeAnnotation>,
+    pub(crate) body: Block,
+    pub(crate) attributes: Vec<Attribute>,
+}
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident
+            && self.type_parameters == other.type_parameters
+            && self.parameters == other.parameters
+            && self.return_type == other.return_type
+            && self.body == other.body
+            && self.attributes == other.attributes
+    }
+}
+impl Eq for Function {}
+impl Function {
+    /// Parses a function declaration, assuming that the `fn` keyword has already been consumed.
+    /// ```txt
+    /// fn -> ident ["<" ident {"," ident} ">"] "(" [ident ":" A {"," ident ":" A}] ")" ["->" A] B
+    /// ```
+    pub fn parse<I>(tokens: &mut TokenStream<I>) -> ParserResult<Function>
+    where
+        I: TokenIterator,
+    {
+        let (ident, ident_span) = tokens.expect_ident_spanned()?;
+
+        let type_parameters = if tokens.expect(TokenKind::LAngle).is_ok() {
+            parse_type_parameters(tokens)?
+        } else {
+            Vec::new()
+        };
+
+        tokens.expect(TokenKind::LSmooth)?;
+
+        let mut parameters = Vec::new();
+        while tokens.expect(TokenKind::RSmooth).is_err() {
+            let parameter = tokens.expect_ident()?;
+            tokens.expect(TokenKind::Colon)?;
+            parameters.push((parameter, TypeAnnotation::parse(tokens)?));
+
+            if tokens.expect(TokenKind::Comma).is_err() {
+                tokens.expect(TokenKind::RSmooth)?;
+                break;
+            }
+        }
+
+        let return_type = if tokens.expect(TokenKind::Arrow).is_ok() {
+            Some(TypeAnnotation::parse(tokens)?)
+        } else {
+            None
+        };
+
+        let body = Block::parse(tokens)?;
+
+        Ok(Function {
+            ident,
+            ident_span,
+            type_parameters,
+            parameters,
+            return_type,
+            body,
+            attributes: Vec::new(),
+        })
+    }
+}
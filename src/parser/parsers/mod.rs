@@ -1,6 +1,17 @@
+mod _enum;
 mod _let;
+mod _struct;
+mod attribute;
+mod block;
 mod expression;
+mod function;
+mod type_annotation;
 
+pub use _enum::*;
 pub use _let::*;
+pub use _struct::*;
+pub use attribute::*;
+pub use block::*;
 pub use expression::*;
-
+pub use function::*;
+pub use type_annotation::*;
@@ -0,0 +1,88 @@
+use crate::{
+    lexer::cursor::Span,
+    parser::error::ParserResult,
+    syntax::SyntaxKind,
+    token::TokenKind,
+    token_stream::{TokenIterator, TokenStream},
+};
+
+/// A type as written in the source, before it has been resolved by the type checker. Eg
+/// `Integer`, `T` or `Option<Integer>`.
+#[derive(Debug, Clone)]
+pub struct TypeAnnotation {
+    pub(crate) ident: String,
+    pub(crate) arguments: Vec<TypeAnnotation>,
+    pub(crate) span: Span,
+}
+impl PartialEq for TypeAnnotation {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident && self.arguments == other.arguments
+    }
+}
+impl Eq for TypeAnnotation {}
+impl TypeAnnotation {
+    /// Parses a type annotation, including any type arguments surrounded by angle brackets.
+    /// ```txt
+    /// A -> ident ["<" A {"," A} ">"]
+    /// ```
+    pub fn parse<I>(tokens: &mut TokenStream<I>) -> ParserResult<TypeAnnotation>
+    where
+        I: TokenIterator,
+    {
+        tokens.start_node(SyntaxKind::TypeAnnotation);
+        let (ident, ident_span) = tokens.expect_ident_spanned()?;
+
+        let arguments = if tokens.expect(TokenKind::LAngle).is_ok() {
+            Self::parse_arguments(tokens)?
+        } else {
+            Vec::new()
+        };
+        tokens.finish_node();
+
+        Ok(TypeAnnotation {
+            ident,
+            arguments,
+            span: Span::new(ident_span.start, tokens.end()),
+        })
+    }
+
+    /// Parses a comma separated list of type annotations, up to and including the closing `>`. The
+    /// opening `<` must already have been consumed.
+    pub fn parse_arguments<I>(tokens: &mut TokenStream<I>) -> ParserResult<Vec<TypeAnnotation>>
+    where
+        I: TokenIterator,
+    {
+        let mut arguments = Vec::new();
+
+        while tokens.expect_closing_angle().is_err() {
+            arguments.push(Self::parse(tokens)?);
+
+            if tokens.expect(TokenKind::Comma).is_err() {
+                tokens.expect_closing_angle()?;
+                break;
+            }
+        }
+
+        Ok(arguments)
+    }
+}
+
+/// Parses the identifiers of a list of type parameters (eg `<T, U>`), up to and including the
+/// closing `>`. The opening `<` must already have been consumed.
+pub fn parse_type_parameters<I>(tokens: &mut TokenStream<I>) -> ParserResult<Vec<String>>
+where
+    I: TokenIterator,
+{
+    let mut parameters = Vec::new();
+
+    while tokens.expect_closing_angle().is_err() {
+        parameters.push(tokens.expect_ident()?);
+
+        if tokens.expect(TokenKind::Comma).is_err() {
+            tokens.expect_closing_angle()?;
+            break;
+        }
+    }
+
+    Ok(parameters)
+}
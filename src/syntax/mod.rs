@@ -0,0 +1,318 @@
+use std::fmt::{Display, Write};
+
+use crate::{
+    lexer::cursor::Span,
+    parser::{error::ParserResult, parse, parse_lossless, AstNode},
+    token::{Token, TokenKind},
+    token_stream::TokenStream,
+};
+
+/// Each of the kinds of node within a [SyntaxNode] tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    /// The root of the tree, containing every statement.
+    Program,
+    Attribute,
+    Let,
+    Function,
+    Struct,
+    Enum,
+    TypeAnnotation,
+    Block,
+    /// An expression followed by a semicolon, or a block-like expression used as a statement.
+    ExpressionStatement,
+    Literal,
+    Ident,
+    Call,
+    Variant,
+    StructLiteral,
+    /// A string with embedded expressions.
+    Interpolation,
+    BinaryOperation,
+    UnaryOperation,
+    /// An expression surrounded by parentheses.
+    Parentheses,
+    If,
+}
+
+/// Produced by the parser whilst consuming tokens, describing the shape of the syntax tree.
+#[derive(Debug)]
+pub enum Event {
+    Start(SyntaxKind),
+    Token(Token),
+    Finish,
+}
+
+/// A token within the syntax tree, along with the exact source text that it was lexed from.
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+impl SyntaxToken {
+    /// Whether the token is whitespace or a comment, which doesn't affect the meaning of the
+    /// program.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment(_))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// A lossless concrete syntax tree. Unlike the AST, every token from the source is kept, including
+/// whitespace, comments and punctuation, so the source can be reproduced exactly with
+/// [Display].
+#[derive(Debug, Clone)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+impl SyntaxNode {
+    /// Every token within the node in the order that they appear, including trivia.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    /// Each of the nodes directly within this one.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// The span from the start of the first token within the node to the end of the last,
+    /// ignoring any trivia.
+    pub fn span(&self) -> Option<Span> {
+        let tokens = self.tokens();
+        let mut significant = tokens.iter().filter(|token| !token.is_trivia());
+
+        let first = significant.next()?;
+        let last = significant.next_back().unwrap_or(first);
+        Some(first.span.to(&last.span))
+    }
+
+    /// Derives the AST from the tokens within a [SyntaxKind::Program] node.
+    pub fn ast(&self) -> ParserResult<Vec<AstNode>> {
+        let tokens = self
+            .tokens()
+            .into_iter()
+            .map(|token| Token::new(token.kind.clone(), token.span.clone()))
+            .collect::<Vec<_>>();
+
+        parse(TokenStream::from(tokens.into_iter()))
+    }
+
+    /// Writes an indented outline of the tree, with each node followed by the tokens within it.
+    pub fn dump(&self) -> String {
+        let mut output = String::new();
+        self.dump_into(&mut output, 0);
+        output
+    }
+
+    fn dump_into(&self, output: &mut String, depth: usize) {
+        writeln!(output, "{}{:?}", "  ".repeat(depth), self.kind).unwrap();
+
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.dump_into(output, depth + 1),
+                SyntaxElement::Token(token) => writeln!(
+                    output,
+                    "{}{} {:?}",
+                    "  ".repeat(depth + 1),
+                    token.span.start,
+                    token.text
+                )
+                .unwrap(),
+            }
+        }
+    }
+}
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses every token lexed from the source, including whitespace and comments, into a lossless
+/// syntax tree rooted at a [SyntaxKind::Program] node. The AST is returned alongside it.
+pub fn parse_tree(source: &str, tokens: Vec<Token>) -> ParserResult<(SyntaxNode, Vec<AstNode>)> {
+    let mut texts = token_texts(source, &tokens).into_iter();
+    let (ast, events) = parse_lossless(TokenStream::from(tokens.into_iter()))?;
+
+    let mut stack = vec![SyntaxNode {
+        kind: SyntaxKind::Program,
+        children: Vec::new(),
+    }];
+    for event in events {
+        match event {
+            Event::Start(kind) => stack.push(SyntaxNode {
+                kind,
+                children: Vec::new(),
+            }),
+            Event::Token(token) => {
+                let token = SyntaxToken {
+                    kind: token.kind,
+                    text: texts.next().unwrap_or_default(),
+                    span: token.span,
+                };
+                stack
+                    .last_mut()
+                    .expect("root node to remain on the stack")
+                    .children
+                    .push(SyntaxElement::Token(token));
+            }
+            Event::Finish => {
+                let node = stack.pop().expect("node to have been started");
+                stack
+                    .last_mut()
+                    .expect("root node to remain on the stack")
+                    .children
+                    .push(SyntaxElement::Node(node));
+            }
+        }
+    }
+
+    let root = stack.pop().expect("root node to remain on the stack");
+    debug_assert!(stack.is_empty(), "every node should have been finished");
+
+    Ok((root, ast))
+}
+
+/// Finds the source text of each token. Tokens don't store their text, and some don't keep all of
+/// it (such as the escapes within a string), so it is sliced from the source up to the start of
+/// the following token.
+fn token_texts(source: &str, tokens: &[Token]) -> Vec<String> {
+    let mut starts = Vec::with_capacity(tokens.len());
+    let mut pending = tokens.iter().map(|token| &token.span.start).peekable();
+    let (mut line, mut character) = (0, 0);
+
+    for (offset, c) in source.char_indices() {
+        while pending
+            .next_if(|start| start.line() == line && start.character() == character)
+            .is_some()
+        {
+            starts.push(offset);
+        }
+
+        if c == '\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += 1;
+        }
+    }
+
+    // The text before the first token is kept with it, so that nothing is lost
+    if let Some(first) = starts.first_mut() {
+        *first = 0;
+    }
+    starts.push(source.len());
+
+    starts
+        .windows(2)
+        .map(|range| source[range[0]..range[1]].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_source, syntax_tree, SAMPLE};
+
+    /// Builds the syntax tree for the source, checking that it reproduces the source exactly and
+    /// that the AST derived from it matches the one from the parser.
+    fn tree(source: &str) -> super::SyntaxNode {
+        let tree = syntax_tree(source).unwrap();
+
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(tree.ast().unwrap(), parse_source(source).unwrap());
+
+        tree
+    }
+
+    #[test]
+    fn lossless() {
+        tree(SAMPLE);
+        tree(
+            "  // Leading comment
+struct Pair<A,B>{first:A,second:B} enum Option<T>{Some(T),None}
+#[allow(unused_variable)] #[warn(shadowed_binding)]
+fn pick<T>( first : Boolean , a:T,b:T )->T{if first{a}else if false { b } else {b}} // trailing
+let p=Pair{first:1,second:\"a \\\"b\\\" \\\\c\"};let o=Option::<Integer>::None;
+let c = -(1 + 2) * 3 ^ 4 ^ 5 - 6;
+let r = r#\"raw \"x\"\"#;let m = \"multi
+  line \\
+  \\u{e9}\";
+let n: Option<Option<Integer>>=Option::<Option<Integer>>::None;let s = ~c<<2>>1&7|c~c%3;
+let i = \"a{ c + 1 }\\{{\"{c}\"}{Pair{first:1,second:2}}\";
+
+if true { #[allow(unused_variable)] let d = 3; d + 1 };
+  // Final comment",
+        );
+    }
+
+    #[test]
+    fn dump() {
+        let tree = tree("let a = -(1) + b; // comment\n");
+
+        assert_eq!(
+            tree.dump(),
+            r#"Program
+  Let
+    1:1 "let"
+    1:4 " "
+    1:5 "a"
+    1:6 " "
+    1:7 "="
+    1:8 " "
+    BinaryOperation
+      UnaryOperation
+        1:9 "-"
+        Parentheses
+          1:10 "("
+          Literal
+            1:11 "1"
+          1:12 ")"
+      1:13 " "
+      1:14 "+"
+      1:15 " "
+      Ident
+        1:16 "b"
+    1:17 ";"
+  1:18 " "
+  1:19 "// comment"
+  1:29 "\n"
+"#
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let tree = tree("fn f(a: Integer) -> Integer {\n    a\n}\n");
+        let function = tree.child_nodes().next().unwrap();
+
+        let span = function.span().unwrap();
+        assert_eq!(span.start.to_string(), "1:1");
+        assert_eq!(span.end.to_string(), "3:1");
+    }
+}
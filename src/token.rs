@@ -1,13 +1,13 @@
-use crate::lexer::cursor::Position;
+use crate::lexer::cursor::Span;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Token {
     pub kind: TokenKind,
-    pub position: Position,
+    pub span: Span,
 }
 impl Token {
-    pub fn new(kind: TokenKind, position: Position) -> Self {
-        Self { kind, position }
+    pub fn new(kind: TokenKind, span: Span) -> Self {
+        Self { kind, span }
     }
 }
 impl PartialEq for Token {
@@ -26,6 +26,11 @@ pub enum Literal {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Keyword {
     Let,
+    Fn,
+    Struct,
+    Enum,
+    If,
+    Else,
 }
 impl TryFrom<&str> for Keyword {
     type Error = ();
@@ -35,6 +40,11 @@ impl TryFrom<&str> for Keyword {
 
         match keyword {
             "let" => Ok(Let),
+            "fn" => Ok(Fn),
+            "struct" => Ok(Struct),
+            "enum" => Ok(Enum),
+            "if" => Ok(If),
+            "else" => Ok(Else),
             _ => Err(()),
         }
     }
@@ -47,7 +57,18 @@ pub enum TokenKind {
     Identifier(String),
     Whitespace,
     Semi,
+    Colon,
+    DoubleColon,
+    Comma,
+    Arrow,
     Comment(String),
+    Hash,
+    /// The text of an interpolated string up to its first `{`.
+    StringStart(String),
+    /// The text of an interpolated string between a `}` and the next `{`.
+    StringMiddle(String),
+    /// The text of an interpolated string after its last `}`, up to the closing `"`.
+    StringEnd(String),
 
     Equals,
     Plus,
@@ -55,9 +76,21 @@ pub enum TokenKind {
     Asterix,
     Slash,
     Hat,
+    Percent,
+    Ampersand,
+    Pipe,
+    Tilde,
+    DoubleLAngle,
+    DoubleRAngle,
 
     LSmooth,
     RSmooth,
+    LCurly,
+    RCurly,
+    LSquare,
+    RSquare,
+    LAngle,
+    RAngle,
 
     Unknown,
 }
@@ -1,10 +1,9 @@
-use std::{
-    iter::Peekable,
-    ops::{Deref, DerefMut},
-};
+use std::iter::Peekable;
 
 use crate::{
+    lexer::cursor::{Position, Span},
     parser::error::{ParserError, ParserResult},
+    syntax::{Event, SyntaxKind},
     token::{Token, TokenKind},
 };
 
@@ -12,38 +11,151 @@ use crate::{
 pub trait TokenIterator: Iterator<Item = Token> {}
 impl<I> TokenIterator for I where I: Iterator<Item = Token> {}
 
-pub struct TokenStream<I>(Peekable<I>)
-where
-    I: TokenIterator;
-
-impl<I> Deref for TokenStream<I>
+pub struct TokenStream<I>
 where
     I: TokenIterator,
 {
-    type Target = Peekable<I>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
+    tokens: Peekable<I>,
+    /// The position at which the most recently consumed token ended.
+    end: Position,
+    /// Whether an identifier followed by `{` may begin a struct literal. This is disabled whilst
+    /// parsing the condition of an `if`, where the `{` instead begins its body.
+    struct_literals: bool,
+    /// The events describing the syntax tree of everything consumed so far, if one is being built.
+    events: Option<Vec<Event>>,
+    /// Whitespace and comments that have been skipped over, but not yet added to the events.
+    trivia: Vec<Token>,
+    /// The second half of a `>>` that was split to close a list of type arguments, which is the
+    /// next token to be consumed. The whole `>>` was added to the events along with the first half.
+    split: Option<Token>,
 }
 
-impl<I> DerefMut for TokenStream<I>
-where
-    I: TokenIterator,
-{
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
+/// A point in the events that a node can later be started at, once it is known that the tokens
+/// consumed since belong to it.
+#[derive(Clone, Copy)]
+pub struct Checkpoint(usize);
 
 impl<I> TokenStream<I>
 where
     I: TokenIterator,
 {
+    /// Records the syntax tree of everything that is parsed from the stream, including whitespace
+    /// and comments, which can be taken with [Self::finish].
+    pub fn lossless(mut self) -> Self {
+        self.events = Some(Vec::new());
+        self
+    }
+
+    /// Peeks the next token in the stream, skipping over any whitespace and comments.
+    pub fn peek(&mut self) -> Option<&Token> {
+        if self.split.is_some() {
+            return self.split.as_ref();
+        }
+
+        while let Some(token) = self
+            .tokens
+            .next_if(|token| matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment(_)))
+        {
+            if self.events.is_some() {
+                self.trivia.push(token);
+            }
+        }
+
+        self.tokens.peek()
+    }
+
     /// Consumes and returns the next token from the iterator, returning a
     /// [ParserError::ExpectedTokenToFollow] error if the next item is [None].
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> ParserResult<Token> {
-        self.0.next().ok_or(ParserError::ExpectedTokenToFollow)
+        if let Some(token) = self.split.take() {
+            self.end = token.span.end.clone();
+            return Ok(token);
+        }
+
+        self.peek();
+        self.flush_trivia();
+
+        let token = self
+            .tokens
+            .next()
+            .ok_or(ParserError::ExpectedTokenToFollow)?;
+
+        self.end = token.span.end.clone();
+        if let Some(events) = &mut self.events {
+            events.push(Event::Token(token.clone()));
+        }
+
+        Ok(token)
+    }
+
+    /// Adds any skipped whitespace and comments to the events, so that they come before whatever
+    /// is added next.
+    fn flush_trivia(&mut self) {
+        if let Some(events) = &mut self.events {
+            events.extend(self.trivia.drain(..).map(Event::Token));
+        }
+    }
+
+    /// Marks the current point in the events, after any whitespace and comments that come first.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.peek();
+        self.flush_trivia();
+
+        Checkpoint(self.events.as_ref().map(Vec::len).unwrap_or_default())
+    }
+
+    /// Starts a node containing everything consumed from here until [Self::finish_node].
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        let checkpoint = self.checkpoint();
+        self.start_node_at(checkpoint, kind);
+    }
+
+    /// Starts a node at an earlier checkpoint, so that it also contains everything consumed since.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        if let Some(events) = &mut self.events {
+            events.insert(checkpoint.0, Event::Start(kind));
+        }
+    }
+
+    /// Finishes the most recently started node.
+    pub fn finish_node(&mut self) {
+        if let Some(events) = &mut self.events {
+            events.push(Event::Finish);
+        }
+    }
+
+    /// Returns the events recorded since [Self::lossless], including any whitespace and comments
+    /// left at the end of the stream.
+    pub fn finish(mut self) -> Vec<Event> {
+        self.peek();
+        self.flush_trivia();
+
+        self.events.unwrap_or_default()
+    }
+
+    /// The position at which the most recently consumed token ended, used to finish the span of a
+    /// node once all of its tokens have been consumed.
+    pub fn end(&self) -> Position {
+        self.end.clone()
+    }
+
+    pub fn struct_literals(&self) -> bool {
+        self.struct_literals
+    }
+
+    /// Runs `parse` with struct literals either allowed or disallowed, restoring the previous
+    /// setting afterwards.
+    pub fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let previous = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = parse(self);
+        self.struct_literals = previous;
+
+        result
     }
 
     /// Peeks the next token in the stream, consuming it if it matches `token`, otherwise returns a
@@ -56,18 +168,67 @@ where
         } else {
             Err(ParserError::ExpectedToken {
                 token: next_token.kind.clone(),
-                position: next_token.position.clone(),
+                position: next_token.span.start.clone(),
             })
         }
     }
+
+    /// Consumes a `>` closing a list of type arguments or parameters. A `>>` is split in two, so
+    /// that nested lists can be closed together (eg `Option<Option<Integer>>`), leaving the
+    /// second `>` to be consumed next.
+    pub fn expect_closing_angle(&mut self) -> ParserResult<Token> {
+        if self
+            .peek()
+            .is_some_and(|token| token.kind == TokenKind::DoubleRAngle)
+        {
+            let token = self.next()?;
+            let (start, end) = (token.span.start, token.span.end);
+
+            self.end = start.clone();
+            self.split = Some(Token::new(TokenKind::RAngle, Span::new(end.clone(), end)));
+
+            return Ok(Token::new(
+                TokenKind::RAngle,
+                Span::new(start.clone(), start),
+            ));
+        }
+
+        self.expect(TokenKind::RAngle)
+    }
+
+    /// Consumes the next token, returning the contained identifier if it is a
+    /// [TokenKind::Identifier], otherwise returns a [ParserError].
+    pub fn expect_ident(&mut self) -> ParserResult<String> {
+        self.expect_ident_spanned().map(|(ident, _)| ident)
+    }
+
+    /// Identical to [Self::expect_ident], but also returns the span of the identifier.
+    pub fn expect_ident_spanned(&mut self) -> ParserResult<(String, Span)> {
+        let token = self.next()?;
+
+        match token.kind {
+            TokenKind::Identifier(ident) => Ok((ident, token.span)),
+            _ => Err(ParserError::ExpectedToken {
+                token: TokenKind::Identifier(String::new()),
+                position: token.span.start,
+            }),
+        }
+    }
 }
 
 impl<I> From<Peekable<I>> for TokenStream<I>
 where
     I: TokenIterator,
 {
-    fn from(iter: Peekable<I>) -> Self {
-        Self(iter)
+    fn from(tokens: Peekable<I>) -> Self {
+        Self {
+            tokens,
+            end: Position::new(),
+            struct_literals: true,
+            events: None,
+            trivia: Vec::new(),
+            split: None,
+        }
     }
 }
 
@@ -76,6 +237,6 @@ where
     I: TokenIterator,
 {
     fn from(iter: I) -> Self {
-        Self(iter.peekable())
+        Self::from(iter.peekable())
     }
 }
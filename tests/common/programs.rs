@@ -0,0 +1,28 @@
+//! Finds the programs that drive the golden tests and the backend tests, which are shared between
+//! the integration tests and the tests within the library.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Every `.lang` file within the directory and its subdirectories, sorted by path.
+pub fn programs(directory: &Path) -> Vec<PathBuf> {
+    let mut programs = Vec::new();
+
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            programs.extend(self::programs(&path));
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "lang")
+        {
+            programs.push(path);
+        }
+    }
+
+    programs.sort();
+    programs
+}
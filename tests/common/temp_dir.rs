@@ -0,0 +1,33 @@
+//! A temporary directory for tests that build executables, shared between the integration tests
+//! and the tests within the library.
+
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// A temporary directory, which is removed when it is dropped so that it is cleaned up even if a
+/// test fails.
+pub struct TempDir(PathBuf);
+impl TempDir {
+    /// Creates a directory for `name`, which is unique to this process.
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+}
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
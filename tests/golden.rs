@@ -0,0 +1,138 @@
+//! Runs every program in `tests/golden`, comparing what happens against the expectations written
+//! in its comments:
+//!
+//! - `// type: x = Integer` expects the top level binding `x` to have the type `Integer`.
+//! - `// output: ...` expects a line that the program prints.
+//! - `// error: ...` expects an error found whilst compiling or running the program.
+//!
+//! Output and error expectations are kept together at the end of the file, in the order they
+//! happen, and aren't part of the program that is compiled. Running with `BLESS=1` rewrites every
+//! expectation to match what actually happened.
+
+use std::{env, fs, path::Path};
+
+use lang::{compile, Program};
+
+use self::programs::programs;
+
+#[path = "common/programs.rs"]
+mod programs;
+
+const TYPE: &str = "// type: ";
+const OUTPUT: &str = "// output:";
+const ERROR: &str = "// error: ";
+
+#[test]
+fn golden() {
+    let bless = env::var_os("BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for path in programs(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")) {
+        let contents = fs::read_to_string(&path).unwrap();
+        let code = code(&contents);
+        let actual = evaluate(code).to_file(code);
+
+        if bless {
+            fs::write(&path, actual).unwrap();
+        } else if actual != contents {
+            failures.push(format!(
+                "{}\nexpected:\n{contents}\nfound:\n{actual}",
+                path.display()
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} golden file(s) failed, run with BLESS=1 to update them\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+/// The program within a file, without the output and error expectations at the end of it.
+fn code(contents: &str) -> &str {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let code_lines = lines
+        .iter()
+        .rposition(|line| !(line.is_empty() || is_result(line)))
+        .map_or(0, |last| last + 1);
+
+    let code_length = lines[..code_lines]
+        .iter()
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        .min(contents.len());
+
+    &contents[..code_length]
+}
+
+fn is_result(line: &str) -> bool {
+    line.starts_with(OUTPUT) || line.starts_with(ERROR)
+}
+
+/// What happened when a program was compiled and run.
+struct Actual {
+    program: Option<Program>,
+    results: Vec<String>,
+}
+impl Actual {
+    /// Writes the program back out with its expectations matching what actually happened.
+    fn to_file(&self, code: &str) -> String {
+        let mut file = String::new();
+
+        for line in code.lines() {
+            match line.strip_prefix(TYPE).and_then(|ty| ty.split_once(" = ")) {
+                Some((ident, _)) => {
+                    let ty = self
+                        .program
+                        .as_ref()
+                        .and_then(|program| program.types().binding(ident))
+                        .map_or("<unknown>".to_string(), ToString::to_string);
+
+                    file.push_str(&format!("{TYPE}{ident} = {ty}\n"));
+                }
+                None => file.push_str(&format!("{line}\n")),
+            }
+        }
+
+        if !self.results.is_empty() {
+            file = format!("{}\n\n{}\n", file.trim_end(), self.results.join("\n"));
+        }
+
+        file
+    }
+}
+
+/// Compiles and runs the program, recording anything that it prints and any errors.
+fn evaluate(code: &str) -> Actual {
+    let program = match compile(code) {
+        Ok(program) => program,
+        Err(diagnostics) => {
+            return Actual {
+                program: None,
+                results: diagnostics
+                    .errors()
+                    .map(|diagnostic| format!("{ERROR}{diagnostic}"))
+                    .collect(),
+            }
+        }
+    };
+
+    let mut output = Vec::new();
+    let result = program.run_with_output(&mut output);
+
+    let mut results = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| format!("{OUTPUT} {line}").trim_end().to_string())
+        .collect::<Vec<_>>();
+    if let Err(error) = result {
+        results.push(format!("{ERROR}{error}"));
+    }
+
+    Actual {
+        program: Some(program),
+        results,
+    }
+}
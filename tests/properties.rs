@@ -0,0 +1,605 @@
+//! Property tests for the front end and backends, using randomly generated programs. Each run is
+//! deterministic, starting from the seed in `FUZZ_SEED` if it is set, and failures report the
+//! seed and program so that they can be reproduced.
+
+use std::{env, fs, path::Path, process::Command};
+
+use lang::{
+    backend::{emit_c, emit_wasm, validate_wasm},
+    bytecode::file::{deserialise, serialise},
+    compile, format_source,
+    ir::verify,
+    lexer::Lexer,
+    parse_source, Program,
+};
+
+use self::temp_dir::TempDir;
+
+#[path = "common/temp_dir.rs"]
+mod temp_dir;
+
+/// A xorshift pseudo random number generator, which is plenty for generating test cases.
+struct Rng(u64);
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state can't be zero, otherwise every number would be zero
+        Self(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// True with a probability of one in `n`.
+    fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Each seed to run with, along with a random number generator for it.
+fn seeds(count: u64) -> impl Iterator<Item = (u64, Rng)> {
+    let start = env::var("FUZZ_SEED")
+        .ok()
+        .map(|seed| seed.parse().expect("FUZZ_SEED to be a number"))
+        .unwrap_or(0);
+
+    (start..start + count).map(|seed| (seed, Rng::new(seed)))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Type {
+    Integer,
+    Boolean,
+    String,
+}
+impl Type {
+    const ALL: [Type; 3] = [Type::Integer, Type::Boolean, Type::String];
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Integer => "Integer",
+            Type::Boolean => "Boolean",
+            Type::String => "String",
+        }
+    }
+}
+
+struct Signature {
+    ident: String,
+    parameters: Vec<Type>,
+    return_type: Type,
+}
+
+/// Generates well typed programs, following the expression grammar documented on
+/// `lang::parser::parsers::Expression`. Functions can only call those declared before them, so
+/// every program terminates.
+struct Generator {
+    rng: Rng,
+    /// The bindings in scope, in the order they were declared.
+    scope: Vec<(String, Type)>,
+    functions: Vec<Signature>,
+    /// Used to give every binding and function a unique name.
+    names: usize,
+    /// The types that bindings, functions and printed expressions can have.
+    types: &'static [Type],
+}
+impl Generator {
+    /// How deeply expressions can be nested.
+    const DEPTH: usize = 3;
+
+    fn new(rng: Rng) -> Self {
+        Self {
+            rng,
+            scope: Vec::new(),
+            functions: Vec::new(),
+            names: 0,
+            types: &Type::ALL,
+        }
+    }
+
+    /// Generates programs that only use integers and booleans, for backends that don't support
+    /// strings.
+    fn without_strings(rng: Rng) -> Self {
+        Self {
+            types: &[Type::Integer, Type::Boolean],
+            ..Self::new(rng)
+        }
+    }
+
+    /// A new name starting with `prefix`. Identifiers can't contain digits, so the count is
+    /// written with letters.
+    fn name(&mut self, prefix: &str) -> String {
+        self.names += 1;
+
+        let mut name = prefix.to_string();
+        let mut count = self.names;
+        while count > 0 {
+            name.push((b'a' + (count % 26) as u8) as char);
+            count /= 26;
+        }
+        name
+    }
+
+    fn program(&mut self) -> String {
+        let mut source = String::new();
+
+        for _ in 0..self.rng.below(4) {
+            source.push_str(&self.function());
+            source.push('\n');
+        }
+
+        for _ in 0..1 + self.rng.below(6) {
+            if self.rng.one_in(2) {
+                source.push_str(&self.let_statement(Self::DEPTH));
+            } else {
+                let ty = *self.rng.choose(self.types);
+                let expression = self.e(ty, Self::DEPTH);
+                source.push_str(&format!("print({expression});"));
+            }
+            source.push('\n');
+        }
+
+        source
+    }
+
+    fn function(&mut self) -> String {
+        let ident = self.name("f_");
+        let parameters = (0..self.rng.below(4))
+            .map(|_| (self.name("p_"), *self.rng.choose(self.types)))
+            .collect::<Vec<_>>();
+        let return_type = *self.rng.choose(self.types);
+
+        // Functions can only see their own parameters
+        let outer = std::mem::replace(&mut self.scope, parameters.clone());
+        let body = self.block(return_type, Self::DEPTH);
+        self.scope = outer;
+
+        self.functions.push(Signature {
+            ident: ident.clone(),
+            parameters: parameters.iter().map(|(_, ty)| *ty).collect(),
+            return_type,
+        });
+
+        let parameters = parameters
+            .iter()
+            .map(|(ident, ty)| format!("{ident}: {}", ty.name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("fn {ident}({parameters}) -> {} {body}", return_type.name())
+    }
+
+    fn let_statement(&mut self, depth: usize) -> String {
+        let ty = *self.rng.choose(self.types);
+        let rhs = self.e(ty, depth);
+        let ident = self.name("v_");
+
+        let statement = if self.rng.one_in(3) {
+            format!("let {ident}: {} = {rhs};", ty.name())
+        } else {
+            format!("let {ident} = {rhs};")
+        };
+        self.scope.push((ident, ty));
+
+        statement
+    }
+
+    /// A block evaluating to `ty`, whose bindings go out of scope at the end of it.
+    fn block(&mut self, ty: Type, depth: usize) -> String {
+        let scope = self.scope.len();
+
+        let mut block = "{ ".to_string();
+        for _ in 0..self.rng.below(3) {
+            block.push_str(&self.let_statement(depth / 2));
+            block.push(' ');
+        }
+        block.push_str(&self.e(ty, depth));
+        block.push_str(" }");
+
+        self.scope.truncate(scope);
+        block
+    }
+
+    /// `E -> A {("|" | "~" | "&" | "<<" | ">>") A}`, for integers. The levels of precedence of
+    /// the bitwise operators are flattened into one, as the parser separates them again.
+    fn e(&mut self, ty: Type, depth: usize) -> String {
+        let mut expression = self.a(ty, depth);
+
+        if ty == Type::Integer && self.rng.one_in(4) {
+            let operator = *self.rng.choose(&["|", "~", "&", "<<", ">>"]);
+            expression = format!("{expression} {operator} {}", self.a(ty, depth));
+        }
+
+        expression
+    }
+
+    /// `A -> T {("+" | "-") T}`, where only integers can be subtracted and only integers and
+    /// strings can be added.
+    fn a(&mut self, ty: Type, depth: usize) -> String {
+        let mut expression = self.t(ty, depth);
+        if ty == Type::Boolean {
+            return expression;
+        }
+
+        for _ in 0..self.rng.below(2) {
+            let operator = if ty == Type::Integer && self.rng.one_in(2) {
+                "-"
+            } else {
+                "+"
+            };
+            expression = format!("{expression} {operator} {}", self.t(ty, depth));
+        }
+
+        expression
+    }
+
+    /// `T -> U {("*" | "/" | "%") U}`, for integers.
+    fn t(&mut self, ty: Type, depth: usize) -> String {
+        let mut expression = self.u(ty, depth);
+        if ty != Type::Integer {
+            return expression;
+        }
+
+        for _ in 0..self.rng.below(2) {
+            let operator = *self.rng.choose(&["*", "/", "%"]);
+            expression = format!("{expression} {operator} {}", self.u(ty, depth));
+        }
+
+        expression
+    }
+
+    /// `U -> ("-" | "~") U | F`, where only integers can be negated or complemented.
+    fn u(&mut self, ty: Type, depth: usize) -> String {
+        if ty == Type::Integer && depth > 0 && self.rng.one_in(6) {
+            let operator = *self.rng.choose(&["-", "-", "~"]);
+            format!("{operator}{}", self.u(ty, depth - 1))
+        } else {
+            self.f(ty, depth)
+        }
+    }
+
+    /// `F -> P ["^" U]`, for integers. Exponents are kept small, so that most programs don't
+    /// overflow, and are occasionally negated.
+    fn f(&mut self, ty: Type, depth: usize) -> String {
+        let expression = self.p(ty, depth);
+
+        if ty == Type::Integer && self.rng.one_in(4) {
+            let sign = if self.rng.one_in(10) { "-" } else { "" };
+            format!("{expression} ^ {sign}{}", self.rng.below(4))
+        } else {
+            expression
+        }
+    }
+
+    /// `P -> v | "(" E ")" | I | J`
+    fn p(&mut self, ty: Type, depth: usize) -> String {
+        if depth == 0 {
+            return self.v(ty);
+        }
+        let depth = depth - 1;
+
+        match self.rng.below(8) {
+            0 => format!("({})", self.e(ty, depth)),
+            1 => self.i(ty, depth),
+            2 | 3 => self.call(ty, depth).unwrap_or_else(|| self.v(ty)),
+            4 if ty == Type::String => self.j(depth),
+            _ => self.v(ty),
+        }
+    }
+
+    /// `J -> string_start E {string_middle E} string_end`, embedding expressions of any
+    /// displayable type.
+    fn j(&mut self, depth: usize) -> String {
+        let mut string = "\"".to_string();
+        for _ in 0..1 + self.rng.below(3) {
+            let ty = *self.rng.choose(&Type::ALL);
+            let expression = self.e(ty, depth);
+            let text = self.rng.choose(&["", " ", "\\{x\\}", "a\\\""]);
+
+            string.push_str(&format!("{text}{{{expression}}}"));
+        }
+        string.push('"');
+
+        string
+    }
+
+    /// `I -> "if" E B ["else" (B | I)]`. The `else` can only be left out of an `if` whose value
+    /// isn't used, so it is always included.
+    fn i(&mut self, ty: Type, depth: usize) -> String {
+        let condition = self.e(Type::Boolean, depth);
+        let then = self.block(ty, depth);
+        let otherwise = if self.rng.one_in(3) {
+            self.i(ty, depth.saturating_sub(1))
+        } else {
+            self.block(ty, depth)
+        };
+
+        format!("if {condition} {then} else {otherwise}")
+    }
+
+    /// A call to one of the functions that returns `ty`, if there are any.
+    fn call(&mut self, ty: Type, depth: usize) -> Option<String> {
+        let candidates = self
+            .functions
+            .iter()
+            .enumerate()
+            .filter(|(_, signature)| signature.return_type == ty)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let function = *self.rng.choose(&candidates);
+        let parameters = self.functions[function].parameters.clone();
+        let arguments = parameters
+            .into_iter()
+            .map(|parameter| self.e(parameter, depth))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Some(format!("{}({arguments})", self.functions[function].ident))
+    }
+
+    /// A literal or a binding in scope.
+    fn v(&mut self, ty: Type) -> String {
+        let bindings = self
+            .scope
+            .iter()
+            .filter(|(_, binding)| *binding == ty)
+            .map(|(ident, _)| ident.clone())
+            .collect::<Vec<_>>();
+        if !bindings.is_empty() && self.rng.one_in(2) {
+            return self.rng.choose(&bindings).clone();
+        }
+
+        match ty {
+            // Zero and the largest integer are rare, so that most programs don't fail whilst
+            // being optimised
+            Type::Integer if self.rng.one_in(50) => "9223372036854775807".to_string(),
+            Type::Integer if self.rng.one_in(50) => "0".to_string(),
+            Type::Integer => (1 + self.rng.below(19)).to_string(),
+            Type::Boolean => self.rng.choose(&["true", "false"]).to_string(),
+            Type::String => format!(
+                "\"{}\"",
+                self.rng
+                    .choose(&["", "a", "hello", "a b", r#"\"q\"\t\u{e9}"#])
+            ),
+        }
+    }
+}
+
+/// Whether a program failed to compile only because the optimiser found that it always fails at
+/// runtime, which the generator doesn't try to avoid.
+fn is_constant_error(message: &str) -> bool {
+    [
+        "attempted to divide by zero",
+        "integer overflow",
+        "attempted to raise to a negative exponent",
+        "attempted to shift by a negative amount",
+    ]
+    .contains(&message)
+}
+
+/// Compiles a generated program, which must succeed other than for constant evaluation errors.
+fn compile_generated(seed: u64, source: &str) -> Option<Program> {
+    match compile(source) {
+        Ok(program) => Some(program),
+        Err(diagnostics) => {
+            assert!(
+                diagnostics
+                    .errors()
+                    .all(|diagnostic| is_constant_error(&diagnostic.message)),
+                "seed {seed} generated an invalid program:\n{source}\n{diagnostics}"
+            );
+            None
+        }
+    }
+}
+
+#[test]
+fn lexer_never_panics() {
+    const ALPHABET: &[char] = &[
+        'a', 'z', '_', '0', '9', ' ', '\n', '\t', '+', '-', '*', '/', '^', '(', ')', '{', '}', '<',
+        '>', ':', ';', ',', '=', '"', '\\', '#', '[', ']', '!', '.', 'é', '∑', '\0',
+    ];
+
+    for (seed, mut rng) in seeds(500) {
+        // Programs with random edits are close to valid, so reach deeper into the front end
+        let mut source = if rng.one_in(2) {
+            Generator::new(Rng::new(seed)).program().chars().collect()
+        } else {
+            Vec::new()
+        };
+        for _ in 0..rng.below(20) {
+            let position = rng.below(source.len() + 1);
+            if rng.one_in(2) && position < source.len() {
+                source.remove(position);
+            } else {
+                source.insert(position, *rng.choose(ALPHABET));
+            }
+        }
+        let source = source.into_iter().collect::<String>();
+
+        // The lexer may keep reporting errors, but must always make progress
+        let tokens = Lexer::new(&source).take(source.len() + 1).count();
+        assert!(tokens <= source.len(), "seed {seed}: lexer didn't finish");
+
+        let _ = compile(&source);
+    }
+}
+
+#[test]
+fn format_round_trips() {
+    for (seed, rng) in seeds(300) {
+        let source = Generator::new(rng).program();
+        let ast = parse_source(&source)
+            .unwrap_or_else(|e| panic!("seed {seed} generated unparseable source:\n{source}\n{e}"));
+
+        let formatted = format_source(&source).unwrap();
+        let reparsed = parse_source(&formatted).unwrap_or_else(|e| {
+            panic!("seed {seed} formatted to unparseable source:\n{formatted}\n{e}")
+        });
+
+        assert!(
+            ast == reparsed,
+            "seed {seed} changed meaning when formatted:\n{source}\n{formatted}"
+        );
+        assert_eq!(
+            format_source(&formatted).unwrap(),
+            formatted,
+            "seed {seed} isn't formatted idempotently"
+        );
+    }
+}
+
+#[test]
+fn compiled_bytecode_validates() {
+    for (seed, rng) in seeds(100) {
+        let source = Generator::new(rng).program();
+        let Some(program) = compile_generated(seed, &source) else {
+            continue;
+        };
+
+        if let Err(error) = deserialise(&serialise(program.bytecode())) {
+            panic!("seed {seed} compiled to invalid bytecode: {error}\n{source}");
+        }
+    }
+}
+
+#[test]
+fn wasm_modules_validate() {
+    for (seed, rng) in seeds(100) {
+        let source = Generator::without_strings(rng).program();
+        let Some(program) = compile_generated(seed, &source) else {
+            continue;
+        };
+
+        let bytes = match emit_wasm(program.typed_ast()) {
+            Ok(bytes) => bytes,
+            Err(error) => panic!("seed {seed} failed to emit: {error}\n{source}"),
+        };
+        if let Err(error) = validate_wasm(&bytes) {
+            panic!("seed {seed} emitted an invalid module: {error}\n{source}");
+        }
+    }
+}
+
+#[test]
+fn optimised_ir_verifies() {
+    for (seed, rng) in seeds(100) {
+        let source = Generator::new(rng).program();
+        let Some(program) = compile_generated(seed, &source) else {
+            continue;
+        };
+
+        if let Err(error) = verify(program.ir()) {
+            panic!(
+                "seed {seed} optimised to invalid IR: {error}\n{source}\n{}",
+                program.ir()
+            );
+        }
+    }
+}
+
+#[test]
+fn ill_typed_operators_are_rejected() {
+    const BINARY: &[&str] = &["+", "-", "*", "/", "%", "^", "&", "|", "~", "<<", ">>"];
+
+    for (seed, rng) in seeds(200) {
+        let mut generator = Generator::new(rng);
+        let mut source = generator.program();
+
+        // Strings can be added, but every other operator only accepts integers
+        let (expression, ty) = if generator.rng.one_in(3) {
+            let operator = *generator.rng.choose(&["-", "~"]);
+            let ty = *generator.rng.choose(&[Type::Boolean, Type::String]);
+            (format!("{operator}({})", generator.e(ty, 2)), ty)
+        } else {
+            let operator = *generator.rng.choose(BINARY);
+            let ty = match operator {
+                "+" => Type::Boolean,
+                _ => *generator.rng.choose(&[Type::Boolean, Type::String]),
+            };
+            let (lhs, rhs) = (generator.e(ty, 2), generator.e(ty, 2));
+            (format!("({lhs}) {operator} ({rhs})"), ty)
+        };
+        source.push_str(&format!("print({expression});\n"));
+
+        let Err(diagnostics) = compile(&source) else {
+            panic!("seed {seed} compiled an ill-typed operator:\n{source}");
+        };
+        let expected = format!("Expected type Integer, found {}", ty.name());
+        assert!(
+            diagnostics
+                .errors()
+                .any(|diagnostic| diagnostic.message == expected),
+            "seed {seed} didn't report the ill-typed operator:\n{source}\n{diagnostics}"
+        );
+    }
+}
+
+/// Runs a compiled program, returning what it printed to stdout and stderr.
+fn run_binary(path: &Path) -> (String, String) {
+    let output = Command::new(path).output().unwrap();
+
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn backends_agree_with_interpreter() {
+    let directory = TempDir::new("lang-properties");
+
+    for (seed, rng) in seeds(30) {
+        let source = Generator::new(rng).program();
+        let Some(program) = compile_generated(seed, &source) else {
+            continue;
+        };
+
+        let mut output = Vec::new();
+        let error = program
+            .run_with_output(&mut output)
+            .err()
+            .map_or(String::new(), |error| format!("{error}\n"));
+        let expected = (String::from_utf8(output).unwrap(), error);
+
+        let c_path = directory.join(format!("{seed}.c"));
+        let c_binary = directory.join(format!("{seed}-c"));
+        fs::write(&c_path, emit_c(program.ir()).unwrap()).unwrap();
+        let status = Command::new("cc")
+            .args(["-std=c99", "-o"])
+            .arg(&c_binary)
+            .arg(&c_path)
+            .status()
+            .expect("a C compiler to be installed");
+        assert!(status.success(), "seed {seed} didn't compile as C");
+        assert_eq!(run_binary(&c_binary), expected, "seed {seed}:\n{source}");
+
+        #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+        {
+            use lang::backend::{emit_x86_64, link_x86_64};
+
+            let native_binary = directory.join(format!("{seed}-x86-64"));
+            link_x86_64(&emit_x86_64(program.typed_ast()).unwrap(), &native_binary).unwrap();
+            assert_eq!(
+                run_binary(&native_binary),
+                expected,
+                "seed {seed}:\n{source}"
+            );
+        }
+    }
+}